@@ -0,0 +1,31 @@
+#!/bin/bash
+
+set -ex
+
+cd "$(dirname "$0")"/../swym-hamt
+
+# the "+rtm" feature has to be set because the travis linux vm incorrectly thinks it doesn't support
+# rtm
+export RTM="-Ctarget-feature=+rtm"
+if [[ "$TRAVIS_OS_NAME" == "osx" ]]; then
+    # no rtm support
+    export RTM=""
+fi
+
+export RUSTFLAGS="-D warnings -Ctarget-cpu=native ${RTM}"
+
+# check all combinations of features
+cargo check --no-default-features --benches --bins --examples --tests
+cargo check --features stats --benches --bins --examples --tests
+cargo check --features nightly --benches --bins --examples --tests
+cargo check --features stats,nightly --benches --bins --examples --tests
+# debug-alloc shouldn't change anything
+cargo check --features debug-alloc,nightly,stats --benches --bins --examples --tests
+
+# run tests
+RUST_TEST_THREADS=1 cargo test --features stats,nightly --lib --tests
+RUST_TEST_THREADS=1 \
+    time cargo test --features debug-alloc,stats
+
+# benchmarks
+cargo bench --features nightly
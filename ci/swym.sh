@@ -28,10 +28,13 @@ cargo check --features stats,nightly --benches --bins --examples --tests
 cargo check --features nightly,stats --benches --bins --examples --tests
 # debug-alloc shouldn't change anything
 cargo check --features debug-alloc,nightly,stats --benches --bins --examples --tests
+# optional serde support
+cargo check --features serde --benches --bins --examples --tests
 
 # run tests
 ./x.py test
 RUST_TEST_THREADS=1 cargo test --features stats,nightly --lib --tests
+RUST_TEST_THREADS=1 cargo test --features serde --lib --tests
 
 # examples
 RUSTFLAGS="${RUSTFLAGS} ${ASAN_FLAG}" \
@@ -8,7 +8,7 @@ pub mod alloc;
 pub mod phoenix_tls;
 
 pub mod bloom;
-mod commit;
+pub mod commit;
 mod gc;
 mod parking;
 mod starvation;
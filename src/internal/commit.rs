@@ -1,8 +1,9 @@
 use crate::{
     internal::{
         epoch::{EpochLock, ParkStatus, QuiesceEpoch, EPOCH_CLOCK},
+        tcell_erased::TCellErased,
         thread::{Logs, PinRw},
-        write_log::{WriteEntry, WriteLog},
+        write_log::{FieldLocation, WriteEntry, WriteLog},
     },
     stats,
 };
@@ -15,20 +16,43 @@ use swym_htm::{BoundedHtxErr, HardwareTx};
 
 const MAX_HTX_RETRIES: u8 = 3;
 
+/// Performs a write outside of a transaction.
+///
+/// The calling thread must hold all of the `locks`, which are published with a fresh epoch after
+/// `write` has been called.
+#[inline]
+pub unsafe fn write_locked<'a, I: IntoIterator<Item = &'a EpochLock>, F: FnOnce()>(
+    locks: I,
+    park_status: ParkStatus,
+    write: F,
+) {
+    atomic::fence(Release);
+    write();
+    let sync_epoch = EPOCH_CLOCK.fetch_and_tick().next();
+    for epoch_lock in locks {
+        epoch_lock.unlock_publish(sync_epoch)
+    }
+    if unlikely!(park_status == ParkStatus::HasParked) {
+        crate::internal::parking::unpark();
+    }
+}
+
 impl<'tcell> Logs<'tcell> {
     #[inline]
     pub unsafe fn remove_writes_from_reads(&mut self) {
         let mut count = 0;
 
-        let write_log = &mut self.write_log;
-        self.read_log.filter_in_place(|src| {
-            if write_log.find(src).is_none() {
-                true
-            } else {
-                count += 1;
-                false
-            }
-        });
+        let write_log = &self.write_log;
+        self.read_log
+            .filter_in_place(|src| match write_log.find(src) {
+                None => true,
+                Some(entry) => {
+                    // no longer blind, so the write is validated when it's locked instead
+                    write_log.chain_root(entry).mark_read();
+                    count += 1;
+                    false
+                }
+            });
         stats::write_after_logged_read(count)
     }
 }
@@ -37,33 +61,56 @@ impl<'tcell> dyn WriteEntry + 'tcell {
     #[inline]
     fn try_lock_htm(&self, htx: &HardwareTx, pin_epoch: QuiesceEpoch) -> ParkStatus {
         match self.tcell() {
-            Some(tcell) => tcell.current_epoch.try_lock_htm(htx, pin_epoch),
-            None => ParkStatus::NoParked,
+            Some(tcell) if self.prev().is_none() => {
+                // reads are validated separately, so blind writes only need the lock
+                let max_expected = if self.is_blind() {
+                    QuiesceEpoch::end_of_time()
+                } else {
+                    pin_epoch
+                };
+                tcell.current_epoch.try_lock_htm(htx, max_expected)
+            }
+            _ => ParkStatus::NoParked,
         }
     }
 
     #[inline]
     unsafe fn perform_write(&self) {
         match self.tcell() {
-            Some(tcell) => {
-                let size = mem::size_of_val(self);
-                assume!(
-                    size % mem::size_of::<usize>() == 0,
-                    "buggy alignment on `WriteEntry`"
-                );
-                let len = size / mem::size_of::<usize>() - 1;
-                assume!(
-                    len > 0,
-                    "`WriteEntry` performing a write of size 0 unexpectedly"
-                );
-                self.pending().as_ptr().copy_to_nonoverlapping(
-                    NonNull::from(*tcell).cast::<usize>().as_ptr().sub(len),
-                    len,
-                );
-            }
+            Some(tcell) => match self.field() {
+                None => self.perform_write_words(tcell),
+                Some((location, len)) => {
+                    let dest = match location {
+                        FieldLocation::Back(back) => NonNull::from(*tcell)
+                            .cast::<u8>()
+                            .as_ptr()
+                            .wrapping_sub(back),
+                        FieldLocation::Ptr(dest) => dest,
+                    };
+                    (self.pending().as_ptr() as *const u8).copy_to_nonoverlapping(dest, len)
+                }
+            },
             None => {}
         }
     }
+
+    #[inline]
+    unsafe fn perform_write_words(&self, tcell: &TCellErased) {
+        let size = mem::size_of_val(self);
+        assume!(
+            size % mem::size_of::<usize>() == 0,
+            "buggy alignment on `WriteEntry`"
+        );
+        let len = size / mem::size_of::<usize>() - 1;
+        assume!(
+            len > 0,
+            "`WriteEntry` performing a write of size 0 unexpectedly"
+        );
+        self.pending().as_ptr().copy_to_nonoverlapping(
+            NonNull::from(tcell).cast::<usize>().as_ptr().sub(len),
+            len,
+        );
+    }
 }
 
 impl<'tcell> WriteLog<'tcell> {
@@ -185,17 +232,26 @@ impl<'tx, 'tcell> PinRw<'tx, 'tcell> {
     fn commit_soft(mut self) -> bool {
         // Locking the write log, would cause validation of any reads to the same TCell to fail.
         // So we remove all TCells in the read log that are also in the write log, and assume all
-        // TCells in the write log were also in the read log.
+        // TCells in the write log were also in the read log, except for blind striped writes.
         unsafe { self.logs_mut().remove_writes_from_reads() };
         let logs = self.logs();
 
         // Locking the write set can fail if another thread has the lock, or if any TCell in the
-        // write set has been updated since the transaction began.
+        // write set, other than those only written blindly, has been updated since the transaction
+        // began.
         let mut park_status = ParkStatus::NoParked;
         let pin_epoch = self.pin_epoch();
         let mut unlock_until = None;
-        for epoch_lock in logs.write_log.epoch_locks() {
-            match epoch_lock.try_lock(pin_epoch) {
+        for (index, (epoch_lock, blind)) in logs.write_log.blind_epoch_locks().enumerate() {
+            let locked = if !blind {
+                epoch_lock.try_lock(pin_epoch)
+            } else if index == 0 {
+                // no other locks are held yet, so waiting for the lock can't deadlock
+                Some(epoch_lock.lock())
+            } else {
+                epoch_lock.try_lock_blind()
+            };
+            match locked {
                 Some(cur_status) => park_status = park_status.merge(cur_status),
                 None => {
                     unlock_until = Some(epoch_lock as *const _);
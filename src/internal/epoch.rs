@@ -30,6 +30,7 @@ use core::{
     num::NonZeroUsize,
     sync::atomic::Ordering::{self, Acquire, Relaxed, Release},
 };
+use crossbeam_utils::Backoff;
 use swym_htm::{HardwareTx, HtmUsize};
 
 type Storage = usize;
@@ -137,7 +138,7 @@ impl QuiesceEpoch {
 
     /// The last epoch that is still a valid "time" (e.g. not locked).
     #[inline]
-    fn end_of_time() -> Self {
+    pub fn end_of_time() -> Self {
         let r = QuiesceEpoch::new(!LOCK_BIT).unwrap();
         debug_assert!(
             r.is_active(),
@@ -245,6 +246,51 @@ impl EpochLock {
         lock_bit_set(self.0.load(o))
     }
 
+    /// Returns the epoch of the EpochLock, or None if the lock is currently held.
+    ///
+    /// This is used for seqlock style reads outside of transactions.
+    #[inline]
+    pub fn unlocked_epoch(&self, o: Ordering) -> Option<QuiesceEpoch> {
+        let e = self.load_raw(o).get();
+        if lock_bit_set(e) {
+            None
+        } else {
+            Some(unsafe { QuiesceEpoch::new_unchecked(e) })
+        }
+    }
+
+    /// Acquires the lock regardless of the epoch it contains, spinning while another thread holds
+    /// the lock.
+    #[inline]
+    pub fn lock(&self) -> ParkStatus {
+        let backoff = Backoff::new();
+        loop {
+            if let Some(status) = self.try_lock(QuiesceEpoch::end_of_time()) {
+                return status;
+            }
+            backoff.snooze();
+        }
+    }
+
+    /// Attempts to acquire the lock regardless of the epoch it contains, spinning for a short while
+    /// if another thread holds the lock.
+    ///
+    /// Gives up eventually, so that threads acquiring several locks at once can't deadlock.
+    #[inline]
+    #[must_use]
+    pub fn try_lock_blind(&self) -> Option<ParkStatus> {
+        let backoff = Backoff::new();
+        loop {
+            if let Some(status) = self.try_lock(QuiesceEpoch::end_of_time()) {
+                return Some(status);
+            }
+            if backoff.is_completed() {
+                return None;
+            }
+            backoff.snooze();
+        }
+    }
+
     /// Attempts to lock the EpochLock, returning true on success, or false if the lock is already
     /// held, or if the lock contains an epoch greater than the max_expected epoch.
     ///
@@ -9,6 +9,7 @@ use crate::{
     stats,
 };
 use core::{
+    cell::Cell,
     marker::PhantomData,
     mem::{self, ManuallyDrop},
     ptr::{self, NonNull},
@@ -31,8 +32,116 @@ impl<'tcell, T> WriteEntryImpl<'tcell, T> {
     }
 }
 
-pub unsafe trait WriteEntry {}
+const NO_PREV: usize = usize::max_value();
+
+/// The destination of a field write.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum FieldLocation {
+    /// A field of the `TCell` owning the `TCellErased`, `back` bytes before the `TCellErased`. May
+    /// wrap if the field is stored after the `TCellErased`.
+    Back(usize),
+    /// An element of a striped collection. The elements are stored in a different allocation than
+    /// the `TCellErased` guarding them, so they can't be addressed relative to it.
+    Ptr(*mut u8),
+}
+
+/// A write to a single field of a `TCell`'s value, or a single element of a striped collection.
+///
+/// Only the bytes of the field are copied to its `location` on commit.
+///
+/// Field writes to different locations guarded by the same `TCellErased` are chained together
+/// through `prev`. Only the first entry of a chain locks the `TCellErased`.
+///
+/// A chain of striped writes is blind unless the transaction has logged a read of the
+/// `TCellErased`. Blind writes don't depend on the value the transaction started with, so they are
+/// not validated on commit. Field writes to a `TCell` are located using its value, and are always
+/// validated.
+#[repr(C)]
+pub struct FieldWriteEntryImpl<'tcell, T> {
+    dest:     Option<&'tcell TCellErased>,
+    pending:  ForcedUsizeAligned<T>,
+    location: FieldLocation,
+    prev:     usize,
+    read:     Cell<bool>,
+}
+
+impl<'tcell, T> FieldWriteEntryImpl<'tcell, T> {
+    #[inline]
+    pub const fn new(dest: &'tcell TCellErased, pending: T, location: FieldLocation) -> Self {
+        FieldWriteEntryImpl::chained(dest, pending, location, NO_PREV)
+    }
+
+    #[inline]
+    const fn chained(
+        dest: &'tcell TCellErased,
+        pending: T,
+        location: FieldLocation,
+        prev: usize,
+    ) -> Self {
+        FieldWriteEntryImpl {
+            dest: Some(dest),
+            pending: ForcedUsizeAligned::new(pending),
+            location,
+            prev,
+            read: Cell::new(false),
+        }
+    }
+}
+
+pub unsafe trait WriteEntry {
+    /// Returns the destination of the pending field, and the length in bytes of the field. Returns
+    /// None if the entry holds the entire value.
+    #[inline]
+    fn field(&self) -> Option<(FieldLocation, usize)> {
+        None
+    }
+
+    /// Returns the word index of the previous field write to the same `TCellErased`, if any.
+    #[inline]
+    fn prev(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns true if the `TCellErased` can be locked without validating it. Only meaningful for
+    /// the first entry of a chain.
+    #[inline]
+    fn is_blind(&self) -> bool {
+        false
+    }
+
+    /// Records that the transaction has read the `TCellErased`, so the write must be validated.
+    #[inline]
+    fn mark_read(&self) {}
+}
 unsafe impl<'tcell, T> WriteEntry for WriteEntryImpl<'tcell, T> {}
+unsafe impl<'tcell, T> WriteEntry for FieldWriteEntryImpl<'tcell, T> {
+    #[inline]
+    fn field(&self) -> Option<(FieldLocation, usize)> {
+        Some((self.location, mem::size_of::<T>()))
+    }
+
+    #[inline]
+    fn prev(&self) -> Option<usize> {
+        if self.prev == NO_PREV {
+            None
+        } else {
+            Some(self.prev)
+        }
+    }
+
+    #[inline]
+    fn is_blind(&self) -> bool {
+        match self.location {
+            FieldLocation::Back(_) => false,
+            FieldLocation::Ptr(_) => !self.read.get(),
+        }
+    }
+
+    #[inline]
+    fn mark_read(&self) {
+        self.read.set(true)
+    }
+}
 
 impl<'tcell> dyn WriteEntry + 'tcell {
     fn data_ptr(&self) -> NonNull<usize> {
@@ -66,6 +175,10 @@ impl<'tcell> dyn WriteEntry + 'tcell {
 
     #[inline]
     pub unsafe fn read<T>(&self) -> ManuallyDrop<T> {
+        debug_assert!(
+            self.field().is_none(),
+            "attempt to `WriteEntry::read` a field write"
+        );
         debug_assert!(
             mem::size_of_val(self) == mem::size_of::<WriteEntryImpl<'tcell, T>>(),
             "destination size error during `WriteEntry::read`"
@@ -161,9 +274,21 @@ impl<'tcell> WriteLog<'tcell> {
         Option<&'a EpochLock>,
         impl FnMut(&'a (dyn WriteEntry + 'tcell)) -> Option<&'a EpochLock>,
     > {
-        self.data
-            .iter()
-            .flat_map(|entry| entry.tcell().map(|erased| &erased.current_epoch))
+        self.data.iter().flat_map(|entry| match entry.prev() {
+            None => entry.tcell().map(|erased| &erased.current_epoch),
+            Some(_) => None,
+        })
+    }
+
+    /// Returns the locks of the write log, and whether they can be acquired without validation.
+    #[inline]
+    pub fn blind_epoch_locks(&self) -> impl Iterator<Item = (&EpochLock, bool)> {
+        self.data.iter().flat_map(|entry| match entry.prev() {
+            None => entry
+                .tcell()
+                .map(|erased| (&erased.current_epoch, entry.is_blind())),
+            Some(_) => None,
+        })
     }
 
     #[inline]
@@ -222,6 +347,41 @@ impl<'tcell> WriteLog<'tcell> {
         }
     }
 
+    /// Returns the first entry of the chain of field writes that `entry` belongs to.
+    #[inline]
+    pub fn chain_root<'a>(&'a self, mut entry: &'a dyn WriteEntry) -> &'a dyn WriteEntry {
+        while let Some(prev) = entry.prev() {
+            entry = unsafe { self.data.word_index_unchecked(prev) };
+        }
+        entry
+    }
+
+    /// Records that the transaction has read `dest_tcell`, so that field writes to it are validated
+    /// on commit.
+    #[inline]
+    pub fn mark_read(&self, dest_tcell: &TCellErased) {
+        if let Some(entry) = self.find(dest_tcell) {
+            self.chain_root(entry).mark_read()
+        }
+    }
+
+    /// Finds the field write to `location` guarded by `dest_tcell`.
+    #[inline]
+    pub fn find_field(
+        &self,
+        dest_tcell: &TCellErased,
+        location: FieldLocation,
+    ) -> Option<&dyn WriteEntry> {
+        let mut entry = self.find(dest_tcell)?;
+        loop {
+            match entry.field() {
+                Some((entry_location, _)) if entry_location == location => return Some(entry),
+                _ => {}
+            }
+            entry = unsafe { self.data.word_index_unchecked(entry.prev()?) };
+        }
+    }
+
     #[inline]
     pub fn entry<'a>(&'a mut self, dest_tcell: &TCellErased) -> Entry<'a, 'tcell> {
         self.overflow();
@@ -265,6 +425,42 @@ impl<'tcell> WriteLog<'tcell> {
         self.data.push(WriteEntryImpl::new(dest_tcell, val));
     }
 
+    #[inline]
+    pub fn next_field_push_allocates<T>(&self) -> bool {
+        self.data
+            .next_push_allocates::<FieldWriteEntryImpl<'tcell, T>>()
+    }
+
+    #[inline]
+    pub unsafe fn record_field_unchecked<T: 'static>(
+        &mut self,
+        dest_tcell: &'tcell TCellErased,
+        val: T,
+        location: FieldLocation,
+    ) {
+        debug_assert!(
+            self.epoch_locks()
+                .find(|&x| ptr::eq(x, &dest_tcell.current_epoch))
+                .is_none(),
+            "attempt to add `TCell` to the `WriteLog` twice"
+        );
+        debug_assert!(self.bloom.contained(dest_tcell) == Contained::Maybe);
+
+        self.data
+            .push_unchecked(FieldWriteEntryImpl::new(dest_tcell, val, location));
+    }
+
+    #[inline]
+    pub fn record_field<T: 'static>(
+        &mut self,
+        dest_tcell: &'tcell TCellErased,
+        val: T,
+        location: FieldLocation,
+    ) {
+        self.data
+            .push(FieldWriteEntryImpl::new(dest_tcell, val, location));
+    }
+
     #[inline]
     pub fn validate_writes(&self, pin_epoch: QuiesceEpoch) -> bool {
         for epoch_lock in self.epoch_locks() {
@@ -302,6 +498,24 @@ pub struct OccupiedEntry<'a, 'tcell> {
 }
 
 impl<'a, 'tcell> OccupiedEntry<'a, 'tcell> {
+    #[inline]
+    pub fn get(&self) -> &(dyn WriteEntry + 'tcell) {
+        unsafe { self.data.word_index_unchecked(*self.entry.get()) }
+    }
+
+    /// Writes value into the pending data of the entry at the byte offset.
+    pub unsafe fn write_pending<T>(&mut self, offset: usize, val: T) {
+        let entry = self.data.word_index_unchecked_mut(*self.entry.get());
+        debug_assert!(
+            offset + mem::size_of::<T>() + mem::size_of::<usize>() <= mem::size_of_val(&*entry),
+            "attempt to write past the end of a `WriteEntry`"
+        );
+        ptr::write_unaligned(
+            (entry.pending().as_ptr() as *mut u8).add(offset) as *mut T,
+            val,
+        )
+    }
+
     pub fn overwrite<T: 'static>(self, dest_tcell: &'tcell TCellErased, val: T) {
         let new_entry = WriteEntryImpl::new(dest_tcell, val);
         let new_vtable = dyn_vec::vtable::<dyn WriteEntry + 'tcell>(&new_entry);
@@ -321,4 +535,53 @@ impl<'a, 'tcell> OccupiedEntry<'a, 'tcell> {
         *entry.tcell_mut() = None;
         self.data.push(WriteEntryImpl::new(dest_tcell, val));
     }
+
+    /// Sets the field at `location` guarded by `dest_tcell`, adding a new field write to the chain
+    /// if the field has not been written to yet.
+    pub fn chain_field<T: 'static>(
+        mut self,
+        dest_tcell: &'tcell TCellErased,
+        val: T,
+        location: FieldLocation,
+    ) {
+        let mut index = *self.entry.get();
+        loop {
+            let entry = unsafe { self.data.word_index_unchecked_mut(index) };
+            debug_assert!(
+                entry.tcell().is_some(),
+                "unexpectedly chaining a field write to a tombstoned write log entry"
+            );
+            if entry.field() == Some((location, mem::size_of::<T>())) {
+                unsafe {
+                    ptr::write_unaligned(entry.pending().as_ptr() as *mut T, val);
+                }
+                return;
+            }
+            match entry.prev() {
+                Some(prev) => index = prev,
+                None => break,
+            }
+        }
+        let prev = self.entry.insert(self.data.word_len());
+        self.data.push(FieldWriteEntryImpl::chained(
+            dest_tcell, val, location, prev,
+        ));
+    }
+
+    pub fn tombstone_replace_field<T: 'static>(
+        mut self,
+        dest_tcell: &'tcell TCellErased,
+        val: T,
+        location: FieldLocation,
+    ) {
+        let prev = self.entry.insert(self.data.word_len());
+        let mut entry = unsafe { self.data.word_index_unchecked_mut(prev) };
+        debug_assert!(
+            entry.tcell().is_some(),
+            "unexpectedly tombstoning an already tombstoned write log entry"
+        );
+        *entry.tcell_mut() = None;
+        self.data
+            .push(FieldWriteEntryImpl::new(dest_tcell, val, location));
+    }
 }
@@ -56,6 +56,20 @@
 //!
 //! * [`TCell`], a low level transactional memory location - does not perform any heap allocation.
 //! * [`TPtr`], a low level transactional pointer for building heap allocated data structures.
+//! * [`TBox`], a copy-on-write transactional memory location for large values.
+//! * [`TArray`], a fixed-size transactional array whose elements share striped locks.
+//! * [`TCounter`], a sharded transactional counter whose writers don't conflict with each other.
+//! * [`TQueue`], a transactional FIFO queue that can be used as a blocking channel.
+//! * [`TPriorityQueue`], a transactional min-priority queue with a blocking pop.
+//! * [`TStack`], a transactional LIFO stack.
+//! * [`TDeque`], a transactional double-ended queue.
+//!
+//! ## Synchronization
+//!
+//! * [`TMVar`], a transactional memory location that is either empty or full.
+//! * [`TSemaphore`], a transactional counting semaphore.
+//! * [`TBarrier`], a reusable barrier.
+//! * [`TLock`], a mutex implementing `lock_api::RawMutex`.
 //!
 //! ## Running Transactions
 //!
@@ -65,13 +79,28 @@
 //!   started.
 //! * [`try_read`], starts a read only transaction returning an error if the transaction could not
 //!   be started.
+//! * [`snapshot`], atomically reads multiple `TCell`s without a transaction.
+//! * [`mcas`], atomically compares and swaps multiple `TCell`s without a transaction.
 //!
 //! [`TCell`]: tcell/struct.TCell.html
 //! [`TPtr`]: tptr/struct.TPtr.html
+//! [`TBox`]: tbox/struct.TBox.html
+//! [`TArray`]: tarray/struct.TArray.html
+//! [`TCounter`]: tcounter/struct.TCounter.html
+//! [`TQueue`]: tqueue/struct.TQueue.html
+//! [`TPriorityQueue`]: tpriority_queue/struct.TPriorityQueue.html
+//! [`TStack`]: tstack/struct.TStack.html
+//! [`TDeque`]: tdeque/struct.TDeque.html
+//! [`TMVar`]: tmvar/struct.TMVar.html
+//! [`TSemaphore`]: tsemaphore/struct.TSemaphore.html
+//! [`TBarrier`]: tbarrier/struct.TBarrier.html
+//! [`TLock`]: tlock/type.TLock.html
 //! [`rw`]: thread_key/struct.ThreadKey.html#method.rw
 //! [`read`]: thread_key/struct.ThreadKey.html#method.read
 //! [`try_rw`]: thread_key/struct.ThreadKey.html#method.try_rw
 //! [`try_read`]: thread_key/struct.ThreadKey.html#method.try_read
+//! [`snapshot`]: fn.snapshot.html
+//! [`mcas`]: fn.mcas.html
 
 #![feature(optin_builtin_traits)]
 #![cfg_attr(feature = "nightly", feature(cfg_target_thread_local))]
@@ -91,14 +120,27 @@
 #[macro_use]
 mod internal;
 
+pub mod mcas;
 mod read;
 mod rw;
 pub mod stats;
+pub mod tarray;
+pub mod tbarrier;
+pub mod tbox;
 pub mod tcell;
+pub mod tcounter;
+pub mod tdeque;
 pub mod thread_key;
+pub mod tlock;
+pub mod tmvar;
+pub mod tpriority_queue;
 pub mod tptr;
+pub mod tqueue;
+pub mod tsemaphore;
+pub mod tstack;
 pub mod tx;
 
+pub use mcas::{mcas, snapshot};
 pub use read::ReadTx;
 pub use rw::RwTx;
 #[doc(inline)]
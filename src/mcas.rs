@@ -0,0 +1,330 @@
+//! Atomic operations on multiple [`TCell`]s that do not require a transaction.
+//!
+//! [`snapshot`] atomically reads several `TCell`s, and [`mcas`] performs a multi-word
+//! compare-and-swap. Neither requires a `ThreadKey`, nor runs user code in a retry loop, making
+//! them cheaper than [`ThreadKey::rw`] for these simple cases.
+//!
+//! # Examples
+//!
+//! ```
+//! use swym::tcell::TCell;
+//!
+//! let a = TCell::new(1);
+//! let b = TCell::new(2);
+//! assert!(swym::mcas(&[(&a, 1, 3), (&b, 2, 4)]));
+//! assert_eq!(swym::snapshot((&a, &b)), (3, 4));
+//! ```
+//!
+//! [`TCell`]: ../tcell/struct.TCell.html
+//! [`snapshot`]: fn.snapshot.html
+//! [`mcas`]: fn.mcas.html
+//! [`ThreadKey::rw`]: ../thread_key/struct.ThreadKey.html#method.rw
+
+use crate::{
+    internal::{
+        commit,
+        epoch::{ParkStatus, QuiesceEpoch, EPOCH_CLOCK},
+    },
+    tcell::TCell,
+};
+use core::{
+    mem::ManuallyDrop,
+    sync::atomic::{self, Ordering::Acquire},
+};
+use crossbeam_utils::Backoff;
+
+/// Trait for groups of `TCell`s that can be read atomically by [`snapshot`].
+///
+/// This is implemented for `&TCell<T>` where `T: Copy`, and tuples of up to 8 `Snapshot`s.
+///
+/// # Notes
+///
+/// Don't implement this trait.
+///
+/// [`snapshot`]: fn.snapshot.html
+pub trait Snapshot {
+    /// The type of the values read from the `TCell`s.
+    type Output;
+
+    #[doc(hidden)]
+    unsafe fn read_unvalidated(&self) -> Self::Output;
+
+    #[doc(hidden)]
+    fn is_valid(&self, epoch: QuiesceEpoch) -> bool;
+}
+
+impl<'tcell, T: Copy> Snapshot for &'tcell TCell<T> {
+    type Output = T;
+
+    #[inline]
+    unsafe fn read_unvalidated(&self) -> T {
+        ManuallyDrop::into_inner(self.optimistic_read_relaxed())
+    }
+
+    #[inline]
+    fn is_valid(&self, epoch: QuiesceEpoch) -> bool {
+        epoch.read_write_valid_lockable(&self.erased.current_epoch)
+    }
+}
+
+macro_rules! tuple_impls {
+    ($($name:ident)+) => {
+        impl<$($name: Snapshot),+> Snapshot for ($($name,)+) {
+            type Output = ($($name::Output,)+);
+
+            #[inline]
+            #[allow(non_snake_case)]
+            unsafe fn read_unvalidated(&self) -> Self::Output {
+                let ($(ref $name,)+) = *self;
+                ($($name.read_unvalidated(),)+)
+            }
+
+            #[inline]
+            #[allow(non_snake_case)]
+            fn is_valid(&self, epoch: QuiesceEpoch) -> bool {
+                let ($(ref $name,)+) = *self;
+                $($name.is_valid(epoch))&&+
+            }
+        }
+    };
+}
+
+tuple_impls! { A }
+tuple_impls! { A B }
+tuple_impls! { A B C }
+tuple_impls! { A B C D }
+tuple_impls! { A B C D E }
+tuple_impls! { A B C D E F }
+tuple_impls! { A B C D E F G }
+tuple_impls! { A B C D E F G H }
+
+#[inline]
+fn now() -> QuiesceEpoch {
+    EPOCH_CLOCK.now().unwrap_or_else(|| abort!())
+}
+
+/// Atomically reads the values of a group of `TCell`s.
+///
+/// The returned values were all simultaneously held by their `TCell`s at some point during the
+/// call.
+///
+/// # Examples
+///
+/// ```
+/// use swym::{tcell::TCell, thread_key};
+///
+/// let a = TCell::new(1);
+/// let b = TCell::new(2);
+/// let c = TCell::new("three");
+/// thread_key::get().rw(|tx| {
+///     a.set(tx, 4)?;
+///     b.set(tx, 5)?;
+///     Ok(())
+/// });
+/// assert_eq!(swym::snapshot((&a, &b, &c)), (4, 5, "three"));
+/// ```
+pub fn snapshot<S: Snapshot>(cells: S) -> S::Output {
+    let backoff = Backoff::new();
+    loop {
+        let epoch = now();
+        let values = unsafe { cells.read_unvalidated() };
+        atomic::fence(Acquire);
+        if likely!(cells.is_valid(epoch)) {
+            return values;
+        }
+        backoff.snooze();
+    }
+}
+
+/// Atomically sets the value of each `TCell` to `new` if every `TCell` contains its `expected`
+/// value.
+///
+/// Returns true if the values were set, or false if any `TCell` did not contain the `expected`
+/// value, in which case nothing is modified.
+///
+/// # Panics
+///
+/// Panics if the same `TCell` appears more than once.
+///
+/// # Examples
+///
+/// ```
+/// use swym::tcell::TCell;
+///
+/// let a = TCell::new(1);
+/// let b = TCell::new(2);
+/// assert!(!swym::mcas(&[(&a, 1, 3), (&b, 3, 4)]));
+/// assert!(swym::mcas(&[(&a, 1, 3), (&b, 2, 4)]));
+/// assert_eq!((a.into_inner(), b.into_inner()), (3, 4));
+/// ```
+pub fn mcas<T: Copy + PartialEq>(cells: &[(&TCell<T>, T, T)]) -> bool {
+    for (i, &(tcell, _, _)) in cells.iter().enumerate() {
+        assert!(
+            cells[..i].iter().all(|&(prev, _, _)| !core::ptr::eq(prev, tcell)),
+            "attempt to `mcas` the same `TCell` twice"
+        );
+    }
+
+    if cells.is_empty() {
+        return true;
+    }
+
+    let backoff = Backoff::new();
+    let mut values = Vec::with_capacity(cells.len());
+    loop {
+        // A concurrent commit may tear the values, so they are only copied out here, and compared
+        // after validation.
+        let epoch = now();
+        values.clear();
+        values.extend(
+            cells
+                .iter()
+                .map(|(tcell, _, _)| unsafe { tcell.optimistic_read_uninit() }),
+        );
+        atomic::fence(Acquire);
+        let valid = cells.iter().all(|(tcell, _, _)| tcell.is_valid(epoch));
+        if likely!(valid) {
+            // Compare without locking. If the comparison fails, the values that were read were
+            // valid at `epoch`, so the mcas can fail without modifying anything.
+            let equal = values
+                .iter()
+                .zip(cells)
+                .all(|(value, &(_, expected, _))| unsafe { value.assume_init() } == expected);
+            if !equal {
+                return false;
+            }
+
+            // Locking succeeds only if no `TCell` has been modified since `epoch`.
+            let mut park_status = ParkStatus::NoParked;
+            let mut locked = 0;
+            for (tcell, _, _) in cells {
+                match tcell.erased.current_epoch.try_lock(epoch) {
+                    Some(status) => park_status = park_status.merge(status),
+                    None => break,
+                }
+                locked += 1;
+            }
+            if likely!(locked == cells.len()) {
+                unsafe {
+                    commit::write_locked(
+                        cells.iter().map(|(tcell, _, _)| &tcell.erased.current_epoch),
+                        park_status,
+                        || {
+                            for &(tcell, _, new) in cells {
+                                tcell.locked_write(new)
+                            }
+                        },
+                    )
+                };
+                return true;
+            }
+            for (tcell, _, _) in &cells[..locked] {
+                unsafe { tcell.erased.current_epoch.unlock_undo() }
+            }
+        }
+        backoff.snooze();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{tcell::TCell, thread_key, tx::Ordering};
+    use crossbeam_utils::thread;
+
+    #[test]
+    fn snapshot_consistent() {
+        const ITER_COUNT: usize = 10_000;
+        let a = TCell::new(0);
+        let b = TCell::new(0);
+        let c = TCell::new(0);
+        thread::scope(|s| {
+            s.spawn(|_| {
+                let thread_key = thread_key::get();
+                for i in 1..=ITER_COUNT {
+                    thread_key.rw(|tx| {
+                        a.set(tx, i)?;
+                        b.set(tx, i)?;
+                        c.set(tx, i)?;
+                        Ok(())
+                    });
+                }
+            });
+            s.spawn(|_| loop {
+                let (x, y, z) = super::snapshot((&a, &b, &c));
+                assert!(x == y && y == z);
+                if x == ITER_COUNT {
+                    break;
+                }
+            });
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn mcas_increment() {
+        const ITER_COUNT: usize = 1_000;
+        const THREAD_COUNT: usize = 4;
+        let a = TCell::new(0);
+        let b = TCell::new(0);
+        thread::scope(|s| {
+            for _ in 0..THREAD_COUNT {
+                s.spawn(|_| {
+                    let thread_key = thread_key::get();
+                    for _ in 0..ITER_COUNT {
+                        loop {
+                            let (x, y) = super::snapshot((&a, &b));
+                            if super::mcas(&[(&a, x, x + 1), (&b, y, y + 1)]) {
+                                break;
+                            }
+                        }
+                        thread_key.rw(|tx| {
+                            let x = a.get(tx, Ordering::default())?;
+                            let y = b.get(tx, Ordering::default())?;
+                            assert_eq!(x, y);
+                            a.set(tx, x + 1)?;
+                            b.set(tx, y + 1)?;
+                            Ok(())
+                        });
+                    }
+                });
+            }
+        })
+        .unwrap();
+        assert_eq!(a.into_inner(), 2 * ITER_COUNT * THREAD_COUNT);
+        assert_eq!(b.into_inner(), 2 * ITER_COUNT * THREAD_COUNT);
+    }
+
+    #[test]
+    fn mcas_wide() {
+        const ITER_COUNT: usize = 10_000;
+        // several words wide, so unvalidated reads racing with the writer can be torn
+        let a = TCell::new([0; 4]);
+        thread::scope(|s| {
+            s.spawn(|_| {
+                let thread_key = thread_key::get();
+                for i in 1..=ITER_COUNT {
+                    thread_key.rw(|tx| {
+                        a.set(tx, [i; 4])?;
+                        Ok(())
+                    });
+                }
+            });
+            s.spawn(|_| loop {
+                let x = super::snapshot(&a);
+                assert!(x.iter().all(|&y| y == x[0]));
+                if x[0] == ITER_COUNT {
+                    break;
+                }
+                super::mcas(&[(&a, x, x)]);
+            });
+        })
+        .unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn mcas_duplicate() {
+        let a = TCell::new(0);
+        super::mcas(&[(&a, 0, 1), (&a, 0, 2)]);
+    }
+}
@@ -4,9 +4,12 @@ use crate::{
     tx::{Borrow, Error, Ordering, Read},
 };
 use core::{
+    cell::UnsafeCell,
     fmt::{self, Debug, Formatter},
     marker::PhantomData,
     mem::{self, ManuallyDrop},
+    ptr,
+    sync::atomic::{self, Ordering::Acquire},
 };
 
 /// A read only transaction.
@@ -72,4 +75,25 @@ impl<'tcell> Read<'tcell> for ReadTx<'tcell> {
             }
         }
     }
+
+    #[inline]
+    fn get_striped<T: Copy>(
+        &self,
+        lock: &'tcell TCell<()>,
+        value: &'tcell UnsafeCell<T>,
+        _: Ordering,
+    ) -> Result<T, Error> {
+        unsafe {
+            let value = ptr::read_volatile(value.get());
+            atomic::fence(Acquire);
+            if likely!(self
+                .pin_epoch()
+                .read_write_valid_lockable(&lock.erased.current_epoch))
+            {
+                Ok(value)
+            } else {
+                Err(Error::CONFLICT)
+            }
+        }
+    }
 }
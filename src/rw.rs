@@ -14,19 +14,47 @@ use crate::{
         bloom::Contained,
         tcell_erased::TCellErased,
         thread::{PinMutRef, PinRw},
-        write_log::Entry,
+        write_log::{Entry, FieldLocation, WriteEntry},
     },
     stats,
     tcell::{Ref, TCell},
     tx::{self, Error, Ordering, SetError, Write, _TValue},
 };
 use core::{
+    cell::UnsafeCell,
     fmt::{self, Debug, Formatter},
     marker::PhantomData,
     mem::{self, ManuallyDrop},
     ptr,
+    sync::atomic::{self, Ordering::Acquire},
 };
 
+/// Reads the value of a `TCell` that has an entry in the write log.
+///
+/// Field writes only contain part of the value, so the rest is read from the `TCell`.
+#[inline]
+unsafe fn read_entry<T>(entry: &dyn WriteEntry, tcell: &TCell<T>) -> ManuallyDrop<T> {
+    match entry.field() {
+        None => entry.read::<T>(),
+        Some((FieldLocation::Back(back), len)) => {
+            let mut value = tcell.optimistic_read_acquire();
+            let offset = tcell.field_back(0) - back;
+            (entry.pending().as_ptr() as *const u8).copy_to_nonoverlapping(
+                (&mut *value as *mut T as *mut u8).add(offset),
+                len,
+            );
+            value
+        }
+        Some((FieldLocation::Ptr(_), _)) => unreachable!("striped write to a `TCell`"),
+    }
+}
+
+/// Returns the location of an element of a striped collection.
+#[inline]
+fn stripe_location<T>(value: &UnsafeCell<T>) -> FieldLocation {
+    FieldLocation::Ptr(value.get() as *mut u8)
+}
+
 #[derive(Debug)]
 struct RwTxImpl<'tx, 'tcell> {
     pin_ref: PinMutRef<'tx, 'tcell>,
@@ -78,8 +106,11 @@ impl<'tx, 'tcell> RwTxImpl<'tx, 'tcell> {
                 }
                 Some(entry) => {
                     stats::read_after_write();
-                    let value = Ref::new(entry.read::<T>());
+                    let value = Ref::new(read_entry(entry, tcell));
                     if likely!(self.rw_valid(&tcell.erased)) {
+                        if entry.field().is_some() {
+                            logs.write_log.chain_root(entry).mark_read();
+                        }
                         return Ok(value);
                     }
                 }
@@ -116,7 +147,7 @@ impl<'tx, 'tcell> RwTxImpl<'tx, 'tcell> {
                 None => Ref::new(tcell.optimistic_read_acquire()),
                 Some(entry) => {
                     stats::read_after_write();
-                    Ref::new(entry.read::<T>())
+                    Ref::new(read_entry(entry, tcell))
                 }
             };
             if likely!(self.rw_valid(&tcell.erased)) {
@@ -160,7 +191,16 @@ impl<'tx, 'tcell> RwTxImpl<'tx, 'tcell> {
                     }
                 }
                 Entry::Occupied(o) => {
-                    if V::REQUEST_TCELL_LIFETIME {
+                    if o.get().field().is_some() {
+                        // field writes do not take ownership of the previous value, so it has not
+                        // been disposed of yet.
+                        o.tombstone_replace(&tcell.erased, value);
+                        if mem::needs_drop::<T>() {
+                            self.logs_mut()
+                                .garbage
+                                .dispose(tcell.optimistic_read_relaxed())
+                        }
+                    } else if V::REQUEST_TCELL_LIFETIME {
                         o.tombstone_replace(&tcell.erased, value);
                     } else {
                         o.overwrite(&tcell.erased, value);
@@ -205,6 +245,182 @@ impl<'tx, 'tcell> RwTxImpl<'tx, 'tcell> {
     }
 }
 
+impl<'tx, 'tcell> RwTxImpl<'tx, 'tcell> {
+    #[inline(never)]
+    #[cold]
+    fn set_field_slow<T: 'static, U: Copy + Send + 'static>(
+        mut self,
+        tcell: &'tcell TCell<T>,
+        offset: usize,
+        value: U,
+    ) -> Result<(), SetError<U>> {
+        let location = FieldLocation::Back(tcell.field_back(offset));
+        let pin_epoch = self.pin_epoch();
+        unsafe {
+            match self.logs_mut().write_log.entry(&tcell.erased) {
+                Entry::Vacant => {
+                    self.logs_mut()
+                        .write_log
+                        .record_field(&tcell.erased, value, location);
+                    return Ok(());
+                }
+                Entry::Occupied(mut o) => match o.get().field() {
+                    None => {
+                        o.write_pending(offset, value);
+                        return Ok(());
+                    }
+                    Some((prev_location, len))
+                        if prev_location == location && len == mem::size_of::<U>() =>
+                    {
+                        o.write_pending(0, value);
+                        return Ok(());
+                    }
+                    Some(_) => {
+                        // a different field was written, merge both fields with the current value
+                        // into a single field write of the entire value.
+                        let mut merged = read_entry(o.get(), tcell);
+                        if likely!(pin_epoch.read_write_valid_lockable(&tcell.erased.current_epoch))
+                        {
+                            ptr::write_unaligned(
+                                (&mut *merged as *mut T as *mut u8).add(offset) as *mut U,
+                                value,
+                            );
+                            o.tombstone_replace_field(
+                                &tcell.erased,
+                                merged,
+                                FieldLocation::Back(tcell.field_back(0)),
+                            );
+                            // the other fields were read, so the merged write is not blind
+                            self.logs().write_log.mark_read(&tcell.erased);
+                            return Ok(());
+                        }
+                    }
+                },
+            };
+            Err(SetError {
+                value,
+                error: Error::CONFLICT,
+            })
+        }
+    }
+
+    #[inline]
+    fn set_field_impl<T: 'static, U: Copy + Send + 'static>(
+        mut self,
+        tcell: &'tcell TCell<T>,
+        offset: usize,
+        value: U,
+    ) -> Result<(), SetError<U>> {
+        // `offset` was located using the current value of the `TCell`, which is only meaningful if
+        // the transaction could have read that value.
+        atomic::fence(Acquire);
+        if unlikely!(!self.rw_valid(&tcell.erased)) {
+            return Err(SetError {
+                value,
+                error: Error::CONFLICT,
+            });
+        }
+        let logs = self.logs();
+        if likely!(!logs.write_log.next_field_push_allocates::<U>())
+            && likely!(logs.write_log.contained_set(&tcell.erased) == Contained::No)
+        {
+            unsafe {
+                self.logs_mut().write_log.record_field_unchecked(
+                    &tcell.erased,
+                    value,
+                    FieldLocation::Back(tcell.field_back(offset)),
+                )
+            }
+            Ok(())
+        } else {
+            self.set_field_slow(tcell, offset, value)
+        }
+    }
+}
+
+impl<'tx, 'tcell> RwTxImpl<'tx, 'tcell> {
+    #[inline]
+    fn get_striped_impl<T: Copy>(
+        mut self,
+        lock: &'tcell TCell<()>,
+        value: &UnsafeCell<T>,
+        ordering: Ordering,
+    ) -> Result<T, Error> {
+        let logs = self.logs();
+        unsafe {
+            let (snapshot, logged) = match logs.write_log.find(&lock.erased) {
+                None => (ptr::read_volatile(value.get()), false),
+                Some(head) => {
+                    stats::read_after_write();
+                    if ordering == Ordering::ReadWrite {
+                        logs.write_log.chain_root(head).mark_read();
+                    }
+                    let snapshot = match logs
+                        .write_log
+                        .find_field(&lock.erased, stripe_location(value))
+                    {
+                        Some(entry) => ptr::read_unaligned(entry.pending().as_ptr() as *const T),
+                        None => ptr::read_volatile(value.get()),
+                    };
+                    (snapshot, true)
+                }
+            };
+            atomic::fence(Acquire);
+            if likely!(self.rw_valid(&lock.erased)) {
+                if ordering == Ordering::ReadWrite && !logged {
+                    self.logs_mut().read_log.record(&lock.erased);
+                }
+                return Ok(snapshot);
+            }
+        }
+        Err(Error::CONFLICT)
+    }
+
+    #[inline(never)]
+    #[cold]
+    fn set_striped_slow<T: Copy + Send + 'static>(
+        mut self,
+        lock: &'tcell TCell<()>,
+        dest: &UnsafeCell<T>,
+        value: T,
+    ) -> Result<(), SetError<T>> {
+        let location = stripe_location(dest);
+        match self.logs_mut().write_log.entry(&lock.erased) {
+            // blind writes are not validated
+            Entry::Vacant => self
+                .logs_mut()
+                .write_log
+                .record_field(&lock.erased, value, location),
+            Entry::Occupied(o) => o.chain_field(&lock.erased, value, location),
+        };
+        Ok(())
+    }
+
+    #[inline]
+    fn set_striped_impl<T: Copy + Send + 'static>(
+        mut self,
+        lock: &'tcell TCell<()>,
+        dest: &UnsafeCell<T>,
+        value: T,
+    ) -> Result<(), SetError<T>> {
+        let logs = self.logs();
+        if likely!(!logs.write_log.next_field_push_allocates::<T>())
+            && likely!(logs.write_log.contained_set(&lock.erased) == Contained::No)
+        {
+            unsafe {
+                self.logs_mut().write_log.record_field_unchecked(
+                    &lock.erased,
+                    value,
+                    stripe_location(dest),
+                )
+            }
+            Ok(())
+        } else {
+            self.set_striped_slow(lock, dest, value)
+        }
+    }
+}
+
 /// A read write transaction.
 //
 // No instances of this type are ever created. References to values of this type are created by
@@ -251,6 +467,21 @@ impl<'tcell> tx::Read<'tcell> for RwTx<'tcell> {
             Ok(Ref::new(unsafe { mem::zeroed::<ManuallyDrop<T>>() }))
         }
     }
+
+    #[inline]
+    fn get_striped<T: Copy>(
+        &self,
+        lock: &'tcell TCell<()>,
+        value: &'tcell UnsafeCell<T>,
+        ordering: Ordering,
+    ) -> Result<T, Error> {
+        if mem::size_of::<T>() != 0 {
+            self.as_impl().get_striped_impl(lock, value, ordering)
+        } else {
+            // If the type is zero sized, there's no need to any synchronization.
+            Ok(unsafe { mem::zeroed::<T>() })
+        }
+    }
 }
 
 impl<'tcell> Write<'tcell> for RwTx<'tcell> {
@@ -284,6 +515,40 @@ impl<'tcell> Write<'tcell> for RwTx<'tcell> {
         }
     }
 
+    #[inline]
+    fn set_field<T: Send + 'static, U: Copy + Send + 'static>(
+        &mut self,
+        tcell: &'tcell TCell<T>,
+        offset: usize,
+        value: U,
+    ) -> Result<(), SetError<U>> {
+        debug_assert!(
+            offset + mem::size_of::<U>() <= mem::size_of::<T>(),
+            "attempt to set a field outside of the `TCell`"
+        );
+        if mem::size_of::<U>() != 0 {
+            self.as_impl().set_field_impl(tcell, offset, value)
+        } else {
+            // If the type is zero sized, there's no need to any synchronization.
+            Ok(())
+        }
+    }
+
+    #[inline]
+    fn set_striped<T: Copy + Send + 'static>(
+        &mut self,
+        lock: &'tcell TCell<()>,
+        dest: &'tcell UnsafeCell<T>,
+        value: T,
+    ) -> Result<(), SetError<T>> {
+        if mem::size_of::<T>() != 0 {
+            self.as_impl().set_striped_impl(lock, dest, value)
+        } else {
+            // If the type is zero sized, there's no need to any synchronization.
+            Ok(())
+        }
+    }
+
     #[inline]
     fn _privatize<F: FnOnce() + Copy + Send + 'static>(&mut self, privatizer: F) {
         self.as_impl()
@@ -0,0 +1,310 @@
+//! A fixed-size transactional array with striped locks, [`tarray::TArray`].
+//!
+//! A slice of [`TCell`]s pays one lock word per element, while a `TCell` containing an array makes
+//! every element conflict with every other element. `TArray` sits in between. Its elements are
+//! split into a configurable number of contiguous stripes, and all elements of a stripe share a
+//! single lock.
+//!
+//! Fewer stripes use less memory, but cause more false conflicts between transactions that access
+//! different elements of the same stripe. Writes only log, and copy on commit, the elements that
+//! were actually written.
+//!
+//! # Examples
+//!
+//! ```
+//! use swym::{tarray::TArray, thread_key, tx::Ordering};
+//!
+//! // 1024 counters sharing 16 locks
+//! let counters = TArray::new(vec![0usize; 1024], 16);
+//! thread_key::get().rw(|tx| {
+//!     let count = counters.get(tx, 42, Ordering::default())?;
+//!     counters.set(tx, 42, count + 1)?;
+//!     Ok(())
+//! });
+//! assert_eq!(counters.into_inner()[42], 1);
+//! ```
+//!
+//! [`TCell`]: ../tcell/struct.TCell.html
+
+use crate::{
+    tcell::TCell,
+    tx::{Error, Ordering, Read, SetError, Write},
+};
+use core::{
+    cell::UnsafeCell,
+    fmt::{self, Debug, Formatter},
+};
+
+/// A fixed-size array of transactional memory locations, guarded by striped locks.
+///
+/// Elements are accessed by copy, and so must implement `Copy`.
+pub struct TArray<T> {
+    values:     Box<[UnsafeCell<T>]>,
+    locks:      Box<[TCell<()>]>,
+    stripe_len: usize,
+}
+
+unsafe impl<T: Send> Send for TArray<T> {}
+unsafe impl<T: Send + Sync> Sync for TArray<T> {}
+
+impl<T> Debug for TArray<T> {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("TArray")
+            .field("len", &self.len())
+            .field("stripe_count", &self.stripe_count())
+            .field("values", &"...")
+            .finish()
+    }
+}
+
+impl<T> TArray<T> {
+    /// Constructs a new `TArray` from its initial values, with the elements split into
+    /// `stripe_count` stripes.
+    ///
+    /// The number of stripes is capped at the number of elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `stripe_count` is zero and `values` is not empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::tarray::TArray;
+    ///
+    /// let x = TArray::new(vec![0u8; 4096], 64);
+    /// assert_eq!(x.len(), 4096);
+    /// assert_eq!(x.stripe_count(), 64);
+    /// ```
+    pub fn new(values: Vec<T>, stripe_count: usize) -> TArray<T> {
+        let len = values.len();
+        assert!(
+            stripe_count > 0 || len == 0,
+            "attempt to create a `TArray` with zero stripes"
+        );
+        let stripe_count = stripe_count.min(len).max(1);
+        let stripe_len = ((len + stripe_count - 1) / stripe_count).max(1);
+        let locks = (0..(len + stripe_len - 1) / stripe_len)
+            .map(|_| TCell::new(()))
+            .collect();
+        // UnsafeCell<T> has the same in-memory representation as T.
+        let values = unsafe {
+            Box::from_raw(Box::into_raw(values.into_boxed_slice()) as *mut [UnsafeCell<T>])
+        };
+        TArray {
+            values,
+            locks,
+            stripe_len,
+        }
+    }
+
+    /// Returns the number of elements in the array.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns true if the array contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Returns the number of locks shared by the elements of the array.
+    #[inline]
+    pub fn stripe_count(&self) -> usize {
+        self.locks.len()
+    }
+
+    /// Consumes this `TArray`, returning the underlying data.
+    #[inline]
+    pub fn into_inner(self) -> Vec<T> {
+        // UnsafeCell<T> has the same in-memory representation as T.
+        unsafe { Box::from_raw(Box::into_raw(self.values) as *mut [T]) }.into_vec()
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the `TArray` mutably, no synchronization needs to take place.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::tarray::TArray;
+    ///
+    /// let mut x = TArray::new(vec![1, 2, 3], 1);
+    /// x.borrow_mut()[1] = 4;
+    /// assert_eq!(x.into_inner(), [1, 4, 3]);
+    /// ```
+    #[inline]
+    pub fn borrow_mut(&mut self) -> &mut [T] {
+        // UnsafeCell<T> has the same in-memory representation as T.
+        unsafe { &mut *(&mut *self.values as *mut [UnsafeCell<T>] as *mut [T]) }
+    }
+
+    #[inline]
+    fn location(&self, index: usize) -> (&TCell<()>, &UnsafeCell<T>) {
+        (&self.locks[index / self.stripe_len], &self.values[index])
+    }
+}
+
+impl<T: Copy> TArray<T> {
+    /// Gets a copy of the element at `index` using the specified memory [`Ordering`].
+    ///
+    /// # Errors
+    ///
+    /// If another thread has written to the stripe containing the element during the current
+    /// transaction, an error is returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tarray::TArray, thread_key, tx::Ordering};
+    ///
+    /// let x = TArray::new(vec![1, 2, 3], 2);
+    /// let two = thread_key::get().read(|tx| Ok(x.get(tx, 1, Ordering::Read)?));
+    /// assert_eq!(two, 2);
+    /// ```
+    #[inline]
+    #[must_use = "Calling `TArray::get` without using the result unnecessarily increases the \
+                  chance of transaction failure"]
+    pub fn get<'tcell>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+        index: usize,
+        ordering: Ordering,
+    ) -> Result<T, Error> {
+        let (lock, value) = self.location(index);
+        tx.get_striped(lock, value, ordering)
+    }
+}
+
+impl<T: Copy + Send + 'static> TArray<T> {
+    /// Sets the element at `index`.
+    ///
+    /// Unless the transaction also reads the stripe containing the element with
+    /// [`Ordering::ReadWrite`], the write is blind. Blind writes are not validated on commit, so
+    /// concurrent transactions writing to the same stripe take turns instead of conflicting with
+    /// each other.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tarray::TArray, thread_key};
+    ///
+    /// let x = TArray::new(vec![1, 2, 3], 2);
+    /// thread_key::get().rw(|tx| Ok(x.set(tx, 2, 4)?));
+    /// assert_eq!(x.into_inner(), [1, 2, 4]);
+    /// ```
+    ///
+    /// [`Ordering::ReadWrite`]: ../tx/enum.Ordering.html#variant.ReadWrite
+    #[inline]
+    pub fn set<'tcell>(
+        &'tcell self,
+        tx: &mut impl Write<'tcell>,
+        index: usize,
+        value: T,
+    ) -> Result<(), SetError<T>> {
+        let (lock, dest) = self.location(index);
+        tx.set_striped(lock, dest, value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{tarray::TArray, thread_key, tx::Ordering};
+    use core::sync::atomic::{self, AtomicUsize};
+    use crossbeam_utils::thread;
+
+    #[test]
+    fn stripes() {
+        assert_eq!(TArray::<u8>::new(vec![], 0).stripe_count(), 0);
+        assert_eq!(TArray::new(vec![0u8; 10], 3).stripe_count(), 3);
+        assert_eq!(TArray::new(vec![0u8; 10], 4).stripe_count(), 4);
+        assert_eq!(TArray::new(vec![0u8; 10], 20).stripe_count(), 10);
+        assert_eq!(TArray::new(vec![0u8; 10], 1).stripe_count(), 1);
+    }
+
+    #[test]
+    fn same_stripe() {
+        let x = TArray::new(vec![0u16; 8], 1);
+        thread_key::get().rw(|tx| {
+            for i in 0..8 {
+                x.set(tx, i, i as u16)?;
+            }
+            x.set(tx, 3, 30)?;
+            for i in 0..8 {
+                let expected = if i == 3 { 30 } else { i as u16 };
+                assert_eq!(x.get(tx, i, Ordering::default())?, expected);
+            }
+            Ok(())
+        });
+        assert_eq!(x.into_inner(), [0, 1, 2, 30, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn blind() {
+        const ITER_COUNT: usize = 10_000;
+        // a single stripe, so both threads write through the same lock
+        let x = TArray::new(vec![0usize; 2], 1);
+        let attempts = [AtomicUsize::new(0), AtomicUsize::new(0)];
+        thread::scope(|s| {
+            for (index, attempts) in attempts.iter().enumerate() {
+                let x = &x;
+                s.spawn(move |_| {
+                    let thread_key = thread_key::get();
+                    for i in 1..=ITER_COUNT {
+                        thread_key.rw(|tx| {
+                            attempts.fetch_add(1, atomic::Ordering::Relaxed);
+                            x.set(tx, index, i)?;
+                            Ok(())
+                        });
+                    }
+                });
+            }
+        })
+        .unwrap();
+        // blind writes never conflict
+        for attempts in &attempts {
+            assert_eq!(attempts.load(atomic::Ordering::Relaxed), ITER_COUNT);
+        }
+        assert_eq!(x.into_inner(), [ITER_COUNT, ITER_COUNT]);
+    }
+
+    #[test]
+    fn increment() {
+        const ITER_COUNT: usize = 1_000;
+        const THREAD_COUNT: usize = 8;
+        let x = TArray::new(vec![0u8; 100], 7);
+        thread::scope(|s| {
+            for _ in 0..THREAD_COUNT {
+                s.spawn(|_| {
+                    let thread_key = thread_key::get();
+                    for i in 0..ITER_COUNT {
+                        thread_key.rw(|tx| {
+                            let total = x.get(tx, 99, Ordering::default())?;
+                            let next = x.get(tx, i % 99, Ordering::default())?;
+                            x.set(tx, i % 99, next.wrapping_add(1))?;
+                            x.set(tx, 99, total.wrapping_add(1))?;
+                            Ok(())
+                        });
+                    }
+                });
+            }
+        })
+        .unwrap();
+        let x = x.into_inner();
+        let sum = x[..99].iter().fold(0u8, |sum, &elem| sum.wrapping_add(elem));
+        assert_eq!(sum, x[99]);
+        assert_eq!(x[99], (ITER_COUNT * THREAD_COUNT) as u8);
+    }
+}
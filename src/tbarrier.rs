@@ -0,0 +1,138 @@
+//! A reusable barrier built on transactional memory, [`tbarrier::TBarrier`].
+//!
+//! # Examples
+//!
+//! ```
+//! use crossbeam_utils::thread;
+//! use swym::tbarrier::TBarrier;
+//!
+//! let barrier = TBarrier::new(4);
+//! thread::scope(|s| {
+//!     for _ in 0..4 {
+//!         s.spawn(|_| {
+//!             // no thread returns until all four threads have arrived
+//!             barrier.wait();
+//!         });
+//!     }
+//! })
+//! .unwrap();
+//! ```
+
+use crate::{
+    tcell::TCell,
+    thread_key,
+    tx::{Ordering, Status},
+};
+
+/// A barrier enabling a fixed number of threads to wait for each other.
+///
+/// Waiting threads are parked using [`AWAIT_RETRY`], and are woken by the last thread to arrive.
+///
+/// [`AWAIT_RETRY`]: ../tx/struct.Status.html#associatedconstant.AWAIT_RETRY
+#[derive(Debug)]
+pub struct TBarrier {
+    arrived:    TCell<usize>,
+    generation: TCell<usize>,
+    count:      usize,
+}
+
+impl TBarrier {
+    /// Constructs a new `TBarrier` that blocks until `count` threads have called [`wait`].
+    ///
+    /// A `count` of zero behaves like a `count` of one.
+    ///
+    /// [`wait`]: struct.TBarrier.html#method.wait
+    #[inline]
+    pub const fn new(count: usize) -> TBarrier {
+        TBarrier {
+            arrived: TCell::new(0),
+            generation: TCell::new(0),
+            count,
+        }
+    }
+
+    /// Blocks the current thread until all threads have called `wait`.
+    ///
+    /// The barrier is reset once all threads have arrived, and can be reused. A single thread from
+    /// each group is chosen as the leader, and receives `true`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from within a transaction.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::tbarrier::TBarrier;
+    ///
+    /// let barrier = TBarrier::new(1);
+    /// assert!(barrier.wait());
+    /// assert!(barrier.wait());
+    /// ```
+    pub fn wait(&self) -> bool {
+        let thread_key = thread_key::get();
+        let (generation, leader) = thread_key.rw(|tx| {
+            let generation = self.generation.get(tx, Ordering::default())?;
+            let arrived = self.arrived.get(tx, Ordering::default())? + 1;
+            if arrived >= self.count {
+                self.arrived.set(tx, 0)?;
+                self.generation.set(tx, generation.wrapping_add(1))?;
+                Ok((generation, true))
+            } else {
+                self.arrived.set(tx, arrived)?;
+                Ok((generation, false))
+            }
+        });
+        if !leader {
+            thread_key.rw(|tx| {
+                if self.generation.get(tx, Ordering::default())? == generation {
+                    Err(Status::AWAIT_RETRY)
+                } else {
+                    Ok(())
+                }
+            })
+        }
+        leader
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{tbarrier::TBarrier, tcell::TCell, thread_key, tx::Ordering};
+    use crossbeam_utils::thread;
+
+    #[test]
+    fn phases() {
+        const PHASE_COUNT: usize = 1_000;
+        const THREAD_COUNT: usize = 4;
+        let barrier = TBarrier::new(THREAD_COUNT);
+        let count = TCell::new(0);
+        let leaders = TCell::new(0);
+        thread::scope(|s| {
+            for _ in 0..THREAD_COUNT {
+                s.spawn(|_| {
+                    let thread_key = thread_key::get();
+                    for phase in 0..PHASE_COUNT {
+                        thread_key.rw(|tx| {
+                            let c = count.get(tx, Ordering::default())?;
+                            assert!(c >= phase * THREAD_COUNT);
+                            assert!(c < (phase + 1) * THREAD_COUNT);
+                            Ok(count.set(tx, c + 1)?)
+                        });
+                        if barrier.wait() {
+                            thread_key.rw(|tx| {
+                                let l = leaders.get(tx, Ordering::default())?;
+                                Ok(leaders.set(tx, l + 1)?)
+                            });
+                        }
+                        // nobody starts the next phase until everyone has finished this one
+                        barrier.wait();
+                    }
+                });
+            }
+        })
+        .unwrap();
+        assert_eq!(count.into_inner(), PHASE_COUNT * THREAD_COUNT);
+        assert_eq!(leaders.into_inner(), PHASE_COUNT);
+    }
+}
@@ -0,0 +1,309 @@
+//! A transactional memory location for large values, [`tbox::TBox`].
+//!
+//! [`TCell`] stores its value inline. Every read takes a snapshot of the whole value, and every
+//! write copies the whole value into the write log, and then again into the `TCell` on commit. For
+//! large types (e.g. `[u8; 4096]`) those copies dominate the cost of a transaction.
+//!
+//! `TBox` stores its value on the heap, and treats it as immutable. Only the pointer moves through
+//! the read and write logs. Writes allocate a new value, and the old value is reclaimed by the
+//! garbage collector once no running transaction can observe it.
+//!
+//! # Examples
+//!
+//! ```
+//! use swym::{tbox::TBox, thread_key};
+//!
+//! let x = TBox::new([0u8; 4096]);
+//! thread_key::get().rw(|tx| {
+//!     let first = x.borrow(tx, Default::default())?[0];
+//!     x.modify(tx, |array| array[0] = first + 1)?;
+//!     Ok(())
+//! });
+//! assert_eq!(x.into_inner()[0], 1);
+//! ```
+//!
+//! [`TCell`]: ../tcell/struct.TCell.html
+
+use crate::{
+    tcell::TCell,
+    tx::{Error, Ordering, Read, Rw, SetError, Write},
+};
+use core::fmt::{self, Debug, Formatter};
+
+/// A transactional memory location whose value is stored on the heap.
+///
+/// The value is never modified in place. Instead, writes publish a new heap allocation, making
+/// `TBox` a copy-on-write cell.
+pub struct TBox<T> {
+    cell: TCell<Box<T>>,
+}
+
+impl<T> Debug for TBox<T> {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("TBox")
+            .field("cell", &self.cell)
+            .finish()
+    }
+}
+
+impl<T: Default> Default for TBox<T> {
+    #[inline]
+    fn default() -> TBox<T> {
+        TBox::new(Default::default())
+    }
+}
+
+impl<T> From<T> for TBox<T> {
+    #[inline]
+    fn from(value: T) -> TBox<T> {
+        TBox::new(value)
+    }
+}
+
+impl<T> From<Box<T>> for TBox<T> {
+    #[inline]
+    fn from(value: Box<T>) -> TBox<T> {
+        TBox {
+            cell: TCell::new(value),
+        }
+    }
+}
+
+impl<T> TBox<T> {
+    /// Construct a new `TBox` from an initial value.
+    ///
+    /// This allocates the value on the heap, but does not perform any synchronization.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::tbox::TBox;
+    ///
+    /// let x = TBox::new([0u64; 512]);
+    /// ```
+    #[inline]
+    pub fn new(value: T) -> TBox<T> {
+        TBox::from(Box::new(value))
+    }
+
+    /// Consumes this `TBox`, returning the underlying data.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::tbox::TBox;
+    ///
+    /// let x = TBox::new(42);
+    /// assert_eq!(x.into_inner(), 42);
+    /// ```
+    #[inline]
+    pub fn into_inner(self) -> T {
+        *self.into_box()
+    }
+
+    /// Consumes this `TBox`, returning the underlying heap allocation.
+    #[inline]
+    pub fn into_box(self) -> Box<T> {
+        self.cell.into_inner()
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the `TBox` mutably, no synchronization needs to take place.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::tbox::TBox;
+    ///
+    /// let mut x = TBox::new("hello");
+    /// *x.borrow_mut() = "world";
+    /// assert_eq!(*x.borrow_mut(), "world");
+    /// ```
+    #[inline]
+    pub fn borrow_mut(&mut self) -> &mut T {
+        &mut **self.cell.borrow_mut()
+    }
+}
+
+impl<T: Sync> TBox<T> {
+    /// Gets a reference to the contained value using the specified memory [`Ordering`].
+    ///
+    /// Unlike [`TCell::borrow`], no snapshot of the value is taken. The reference points directly
+    /// at the heap allocation, which is kept alive for the duration of the transaction.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has written to this `TBox` during the current transaction, an error is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tbox::TBox, thread_key, tx::Ordering};
+    ///
+    /// let x = TBox::new([7u8; 4096]);
+    /// let sum = thread_key::get().read(|tx| {
+    ///     let array = x.borrow(tx, Ordering::Read)?;
+    ///     Ok(array.iter().map(|&x| x as usize).sum::<usize>())
+    /// });
+    /// assert_eq!(sum, 7 * 4096);
+    /// ```
+    ///
+    /// [`TCell::borrow`]: ../tcell/struct.TCell.html#method.borrow
+    #[inline]
+    pub fn borrow<'tx, 'tcell>(
+        &'tcell self,
+        tx: &'tx impl Read<'tcell>,
+        ordering: Ordering,
+    ) -> Result<&'tx T, Error> {
+        let snapshot = self.cell.borrow(tx, ordering)?;
+        let value: *const T = &**snapshot;
+        // The pointed to value is immutable. If it came from shared memory, it can only be freed
+        // by the GC after this thread has unpinned. If it came from the write log, it can only be
+        // freed by another write, which requires a mutable borrow of tx.
+        Ok(unsafe { &*value })
+    }
+}
+
+impl<T: Send + Sync + 'static> TBox<T> {
+    /// Sets the contained value.
+    ///
+    /// The previous heap allocation is queued for collection if the transaction succeeds.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has written to this `TBox` during the current transaction, the value is
+    /// not set, and an error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tbox::TBox, thread_key};
+    ///
+    /// let x = TBox::new("hello");
+    /// thread_key::get().rw(|tx| Ok(x.set(tx, "world")?));
+    /// assert_eq!(x.into_inner(), "world");
+    /// ```
+    #[inline]
+    pub fn set<'tcell>(
+        &'tcell self,
+        tx: &mut impl Write<'tcell>,
+        value: T,
+    ) -> Result<(), SetError<T>> {
+        self.set_box(tx, Box::new(value))
+            .map_err(|error| error.map(|value| *value))
+    }
+
+    /// Sets the contained value to an existing heap allocation.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has written to this `TBox` during the current transaction, the value is
+    /// not set, and an error is returned.
+    #[inline]
+    pub fn set_box<'tcell>(
+        &'tcell self,
+        tx: &mut impl Write<'tcell>,
+        value: Box<T>,
+    ) -> Result<(), SetError<Box<T>>> {
+        self.cell.set(tx, value)
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> TBox<T> {
+    /// Clones the contained value, passes the clone to `f` for modification, and sets the result.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has written to this `TBox` during the current transaction, an error is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tbox::TBox, thread_key};
+    ///
+    /// let x = TBox::new(vec![1, 2, 3]);
+    /// thread_key::get().rw(|tx| Ok(x.modify(tx, |v| v.push(4))?));
+    /// assert_eq!(x.into_inner(), [1, 2, 3, 4]);
+    /// ```
+    #[inline]
+    pub fn modify<'tcell, F: FnOnce(&mut T)>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        f: F,
+    ) -> Result<(), Error> {
+        let mut value = Box::new(self.borrow(tx, Ordering::default())?.clone());
+        f(&mut value);
+        Ok(self.set_box(tx, value)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{tbox::TBox, thread_key, tx::Ordering};
+    use core::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+    use crossbeam_utils::thread;
+
+    #[test]
+    fn large_value() {
+        const ITER_COUNT: usize = 1_000;
+        const THREAD_COUNT: usize = 8;
+        let x = TBox::new([0usize; 1024]);
+        thread::scope(|s| {
+            for _ in 0..THREAD_COUNT {
+                s.spawn(|_| {
+                    let thread_key = thread_key::get();
+                    for _ in 0..ITER_COUNT {
+                        thread_key.rw(|tx| {
+                            let array = x.borrow(tx, Ordering::default())?;
+                            assert!(array.iter().all(|&elem| elem == array[0]));
+                            let next = array[0] + 1;
+                            x.set(tx, [next; 1024])?;
+                            Ok(())
+                        });
+                    }
+                });
+            }
+        })
+        .unwrap();
+        assert_eq!(x.into_inner()[1023], ITER_COUNT * THREAD_COUNT);
+    }
+
+    #[test]
+    fn leak() {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        struct Count;
+        impl Count {
+            fn new() -> Self {
+                COUNT.fetch_add(1, Relaxed);
+                Count
+            }
+        }
+        impl Drop for Count {
+            fn drop(&mut self) {
+                COUNT.fetch_sub(1, Relaxed);
+            }
+        }
+
+        const ITER_COUNT: usize = 10_000;
+        let x = TBox::new(Count::new());
+        thread::scope(|s| {
+            s.spawn(|_| {
+                let thread_key = thread_key::get();
+                for _ in 0..ITER_COUNT {
+                    thread_key.rw(|tx| {
+                        x.set(tx, Count::new())?;
+                        x.set(tx, Count::new())?;
+                        Ok(())
+                    });
+                }
+            });
+        })
+        .unwrap();
+        drop(x);
+        assert_eq!(COUNT.load(Relaxed), 0);
+    }
+}
@@ -43,18 +43,22 @@
 //! [`set`]: struct.TCell.html#method.set
 
 use crate::{
-    internal::{tcell_erased::TCellErased, usize_aligned::UsizeAligned},
+    internal::{commit, tcell_erased::TCellErased, usize_aligned::UsizeAligned},
     tx::{AssertBorrow, Borrow, Error, Ordering, Read, Rw, SetError, Write, _TValue},
 };
 use core::{
     cell::UnsafeCell,
     fmt::{self, Debug, Formatter},
     marker::PhantomData,
-    mem::ManuallyDrop,
+    mem::{self, ManuallyDrop, MaybeUninit},
     ops::{Deref, DerefMut},
     ptr,
-    sync::atomic::{self, Ordering::Acquire},
+    sync::atomic::{
+        self,
+        Ordering::{Acquire, Relaxed},
+    },
 };
+use crossbeam_utils::Backoff;
 
 /// A transactional memory location.
 ///
@@ -176,6 +180,42 @@ impl<T> TCell<T> {
     pub(crate) unsafe fn optimistic_read_relaxed(&self) -> ManuallyDrop<T> {
         ptr::read_volatile(self.value.get() as _)
     }
+
+    /// Copies the bytes of the value, which may be torn by a concurrent commit.
+    #[inline]
+    pub(crate) unsafe fn optimistic_read_uninit(&self) -> MaybeUninit<T> {
+        ptr::read_volatile(self.value.get() as _)
+    }
+
+    /// Reads a consistent snapshot of the value like a seqlock, regardless of the epoch it was
+    /// written in.
+    #[inline]
+    pub(crate) unsafe fn seqlock_read(&self) -> ManuallyDrop<T> {
+        let epoch_lock = &self.erased.current_epoch;
+        let backoff = Backoff::new();
+        loop {
+            if let Some(epoch) = epoch_lock.unlocked_epoch(Acquire) {
+                let value = self.optimistic_read_acquire();
+                if likely!(epoch_lock.unlocked_epoch(Relaxed) == Some(epoch)) {
+                    return value;
+                }
+            }
+            backoff.snooze();
+        }
+    }
+
+    /// Writes the value without taking ownership of the previous value. The calling thread must
+    /// hold the lock.
+    #[inline]
+    pub(crate) unsafe fn locked_write(&self, value: T) {
+        ptr::write_volatile(self.value.get() as *mut T, value)
+    }
+
+    /// Returns the distance in bytes from the field at `offset` to the `TCellErased`.
+    #[inline]
+    pub(crate) fn field_back(&self, offset: usize) -> usize {
+        &self.erased as *const TCellErased as usize - (self.value.get() as usize + offset)
+    }
 }
 
 impl<T: Borrow> TCell<T> {
@@ -241,6 +281,55 @@ impl<T: Copy> TCell<T> {
     }
 }
 
+impl<T: Copy> TCell<T> {
+    /// Gets a copy of the contained value without running a transaction.
+    ///
+    /// The value is read like a seqlock, retrying until a consistent snapshot is observed. No
+    /// `ThreadKey` is required, making this well suited for occasional reads of a single `TCell`,
+    /// e.g. from a metrics thread.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tcell::TCell, thread_key};
+    ///
+    /// let x = TCell::new(42);
+    /// thread_key::get().rw(|tx| Ok(x.set(tx, 43)?));
+    /// assert_eq!(x.load_atomic(), 43);
+    /// ```
+    #[inline]
+    pub fn load_atomic(&self) -> T {
+        ManuallyDrop::into_inner(unsafe { self.seqlock_read() })
+    }
+
+    /// Sets the contained value without running a transaction.
+    ///
+    /// This locks the `TCell`, writes the value, and publishes it with a fresh epoch, exactly as
+    /// the commit of a transaction writing only to this `TCell` would. Concurrent transactions
+    /// that have read the `TCell` will fail, and threads waiting on the `TCell` using
+    /// [`AWAIT_RETRY`] are unparked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tcell::TCell, thread_key};
+    ///
+    /// let x = TCell::new(42);
+    /// x.store_atomic(43);
+    /// assert_eq!(thread_key::get().read(|tx| Ok(x.get(tx, Default::default())?)), 43);
+    /// ```
+    ///
+    /// [`AWAIT_RETRY`]: ../tx/struct.Status.html#associatedconstant.AWAIT_RETRY
+    #[inline]
+    pub fn store_atomic(&self, value: T) {
+        let epoch_lock = &self.erased.current_epoch;
+        let park_status = epoch_lock.lock();
+        unsafe {
+            commit::write_locked(Some(epoch_lock), park_status, || self.locked_write(value))
+        }
+    }
+}
+
 impl<T: 'static + Send> TCell<T> {
     #[inline]
     fn set_impl<'tcell>(
@@ -302,6 +391,64 @@ impl<T: 'static + Send> TCell<T> {
     }
 }
 
+impl<T: 'static + Borrow + Send> TCell<T> {
+    /// Sets a single field of the contained value.
+    ///
+    /// `project` selects the field to write. Only the bytes of that field are logged and copied
+    /// into the `TCell` on commit, which is considerably cheaper than [`set`] when the contained
+    /// value is large. The other fields keep whatever value they have when the transaction
+    /// commits.
+    ///
+    /// `project` is passed the current value of the `TCell` to locate the field. The location of a
+    /// field may depend on the value, as it does for the fields of an enum variant, so the write
+    /// is validated on commit, and concurrent writes to different fields of the same `TCell` still
+    /// conflict with each other.
+    ///
+    /// # Errors
+    ///
+    /// If the transaction has already written a different field of this `TCell`, both writes are
+    /// merged with the current value of the other fields. If another thread has written to this
+    /// `TCell` during the current transaction, the field is not set, and an error is returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the reference returned by `project` does not point inside the contained value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tcell::TCell, thread_key};
+    ///
+    /// let x = TCell::new(([0u8; 1024], 0usize));
+    /// thread_key::get().rw(|tx| Ok(x.set_field(tx, |x| &x.1, 42)?));
+    /// assert_eq!(x.into_inner().1, 42);
+    /// ```
+    ///
+    /// [`set`]: struct.TCell.html#method.set
+    #[inline]
+    pub fn set_field<'tcell, U, F>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        project: F,
+        value: U,
+    ) -> Result<(), SetError<U>>
+    where
+        U: Copy + Send + 'static,
+        F: FnOnce(&T) -> &U,
+    {
+        // The value may be large, so the field is located in place instead of in a copy. The
+        // transaction validates the `TCell` before using the offset.
+        let current = unsafe { &*(self.value.get() as *const T) };
+        let start = current as *const T as usize;
+        let field = project(current) as *const U as usize;
+        assert!(
+            field >= start && field + mem::size_of::<U>() <= start + mem::size_of::<T>(),
+            "`TCell::set_field` projection must return a reference into the `TCell`"
+        );
+        tx.set_field(self, field - start, value)
+    }
+}
+
 impl<T: 'static + Borrow + Clone + Send> TCell<T> {
     pub fn replace<'tcell, 'tx>(
         &'tcell self,
@@ -343,6 +490,29 @@ impl<'tx, T> Ref<'tx, T> {
         }
     }
 
+    /// Makes a new `Ref` for a component of the borrowed data.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{
+    ///     tcell::{Ref, TCell},
+    ///     thread_key,
+    /// };
+    ///
+    /// let x = TCell::new(([0u8; 1024], 42usize));
+    /// let y = thread_key::get().read(|tx| {
+    ///     let y = Ref::map(x.borrow(tx, Default::default())?, |x| &x.1);
+    ///     Ok(*y)
+    /// });
+    /// assert_eq!(y, 42);
+    /// ```
+    #[inline]
+    pub fn map<U: Borrow, F: FnOnce(&T) -> &U>(this: Self, f: F) -> Ref<'tx, U> {
+        // swym::tx::Borrow guarantees this is safe
+        Ref::new(unsafe { ptr::read(f(&*this.snapshot) as *const U as *const ManuallyDrop<U>) })
+    }
+
     #[inline]
     pub unsafe fn downcast<'tcell>(this: Self, _: &'tx impl Rw<'tcell>) -> Ref<'tcell, T> {
         Ref {
@@ -449,17 +619,43 @@ where
     }
 }
 
+/// Serializes a copy of the current value, read in its own transaction.
+///
+/// # Panics
+///
+/// Panics if there is already a running transaction on the current thread.
+#[cfg(feature = "serde")]
+impl<T: Borrow + Clone + serde::Serialize> serde::Serialize for TCell<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // the serializer may have side effects, so it can't run inside a transaction that could be
+        // retried
+        let value = crate::thread_key::get().read(|tx| {
+            let value = self.borrow(tx, Default::default())?;
+            Ok(T::clone(&value))
+        });
+        value.serialize(serializer)
+    }
+}
+
+/// Deserializes the initial value, without any synchronization.
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for TCell<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(TCell::new)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::{
-        tcell::TCell,
+        tcell::{Ref, TCell},
         thread_key,
-        tx::{Error, _TValue},
+        tx::{self, Error, _TValue},
     };
     use core::{
         mem::ManuallyDrop,
         ptr,
-        sync::atomic::{AtomicBool, Ordering},
+        sync::atomic::{AtomicBool, AtomicUsize, Ordering},
     };
     use crossbeam_utils::thread;
 
@@ -571,4 +767,203 @@ mod test {
             "failed to trigger custom undo"
         );
     }
+
+    #[test]
+    fn set_field() {
+        const ITER_COUNT: usize = 1_000;
+        let x = TCell::new(([0u8; 1000], 0usize, 0usize));
+        thread::scope(|s| {
+            s.spawn(|_| {
+                let thread_key = thread_key::get();
+                for _ in 0..ITER_COUNT {
+                    thread_key.rw(|tx| {
+                        let next = x.borrow(tx, tx::Ordering::Read)?.1 + 1;
+                        x.set_field(tx, |x| &x.1, next)?;
+                        Ok(())
+                    });
+                }
+            });
+            s.spawn(|_| {
+                let thread_key = thread_key::get();
+                for _ in 0..ITER_COUNT {
+                    thread_key.rw(|tx| {
+                        let next = x.borrow(tx, tx::Ordering::Read)?.2 + 1;
+                        x.set_field(tx, |x| &x.2, next)?;
+                        Ok(())
+                    });
+                }
+            });
+        })
+        .unwrap();
+        let x = x.into_inner();
+        assert!(x.0.iter().all(|&elem| elem == 0));
+        assert_eq!((x.1, x.2), (ITER_COUNT, ITER_COUNT));
+    }
+
+    #[test]
+    fn set_field_validated() {
+        const ITER_COUNT: usize = 1_000;
+        const THREAD_COUNT: usize = 4;
+        let x = TCell::new((0usize, 0usize));
+        thread::scope(|s| {
+            for _ in 0..THREAD_COUNT {
+                s.spawn(|_| {
+                    let thread_key = thread_key::get();
+                    for _ in 0..ITER_COUNT {
+                        thread_key.rw(|tx| {
+                            // a logged read makes the field write conflict with other writers
+                            let next = x.borrow(tx, tx::Ordering::ReadWrite)?.0 + 1;
+                            x.set_field(tx, |x| &x.0, next)?;
+                            Ok(())
+                        });
+                    }
+                });
+            }
+        })
+        .unwrap();
+        assert_eq!(x.into_inner().0, ITER_COUNT * THREAD_COUNT);
+    }
+
+    #[test]
+    fn set_field_read_after_write() {
+        let x = TCell::new((1u16, 2u32, 3u8));
+        thread_key::get().rw(|tx| {
+            x.set_field(tx, |x| &x.1, 20)?;
+            assert_eq!(x.get(tx, tx::Ordering::Read)?, (1, 20, 3));
+            x.set_field(tx, |x| &x.1, 21)?;
+            x.set_field(tx, |x| &x.2, 30)?;
+            assert_eq!(x.get(tx, tx::Ordering::Read)?, (1, 21, 30));
+            x.set(tx, (4, 5, 6))?;
+            x.set_field(tx, |x| &x.0, 40)?;
+            assert_eq!(x.get(tx, tx::Ordering::Read)?, (40, 5, 6));
+            Ok(())
+        });
+        assert_eq!(x.into_inner(), (40, 5, 6));
+    }
+
+    #[test]
+    fn set_field_leak() {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        struct Count;
+        impl Count {
+            fn new() -> Self {
+                COUNT.fetch_add(1, Ordering::Relaxed);
+                Count
+            }
+        }
+        impl Drop for Count {
+            fn drop(&mut self) {
+                COUNT.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+
+        const ITER_COUNT: usize = 1_000;
+        let x = TCell::new((Count::new(), 0usize));
+        thread::scope(|s| {
+            s.spawn(|_| {
+                let thread_key = thread_key::get();
+                for i in 0..ITER_COUNT {
+                    thread_key.rw(|tx| {
+                        x.set_field(tx, |x| &x.1, i)?;
+                        if i % 2 == 0 {
+                            x.set(tx, (Count::new(), i))?;
+                        }
+                        Ok(())
+                    });
+                }
+            });
+        })
+        .unwrap();
+        assert_eq!(x.into_inner().1, ITER_COUNT - 1);
+        assert_eq!(COUNT.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn ref_map() {
+        let x = TCell::new((String::from("hello"), 42));
+        thread_key::get().read(|tx| {
+            let snapshot = x.borrow(tx, tx::Ordering::Read)?;
+            assert_eq!(*Ref::map(snapshot, |x| &x.1), 42);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn load_atomic() {
+        const ITER_COUNT: usize = 10_000;
+        let x = TCell::new((0usize, 0usize));
+        thread::scope(|s| {
+            s.spawn(|_| {
+                let thread_key = thread_key::get();
+                for i in 1..=ITER_COUNT {
+                    thread_key.rw(|tx| Ok(x.set(tx, (i, i))?));
+                }
+            });
+            s.spawn(|_| loop {
+                let (a, b) = x.load_atomic();
+                assert_eq!(a, b);
+                if a == ITER_COUNT {
+                    break;
+                }
+            });
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn store_atomic() {
+        const ITER_COUNT: usize = 10_000;
+        let x = TCell::new((0usize, 0usize));
+        thread::scope(|s| {
+            s.spawn(|_| {
+                let thread_key = thread_key::get();
+                for _ in 0..ITER_COUNT {
+                    thread_key.rw(|tx| {
+                        let (a, b) = x.get(tx, tx::Ordering::default())?;
+                        assert_eq!(a, b);
+                        x.set(tx, (a + 1, b + 1))?;
+                        Ok(())
+                    });
+                }
+            });
+            s.spawn(|_| {
+                for i in 0..ITER_COUNT {
+                    x.store_atomic((i, i));
+                }
+            });
+        })
+        .unwrap();
+        let (a, b) = x.into_inner();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn store_atomic_unpark() {
+        let x = TCell::new(false);
+        thread::scope(|s| {
+            s.spawn(|_| {
+                thread_key::get().rw(|tx| {
+                    if x.get(tx, tx::Ordering::default())? {
+                        Ok(())
+                    } else {
+                        Err(tx::Status::AWAIT_RETRY)
+                    }
+                })
+            });
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            x.store_atomic(true);
+        })
+        .unwrap();
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let x = TCell::new(vec![String::from("hello"), String::from("world")]);
+        thread_key::get().rw(|tx| Ok(x.set(tx, vec![String::from("goodbye")])?));
+        let json = serde_json::to_string(&x).unwrap();
+        assert_eq!(json, r#"["goodbye"]"#);
+        let y: TCell<Vec<String>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(y.into_inner(), ["goodbye"]);
+    }
 }
@@ -0,0 +1,257 @@
+//! A transactional counter split into shards, [`tcounter::TCounter`].
+//!
+//! A counter stored in a single [`TCell`] serializes every writer on the lock of that `TCell`.
+//! `TCounter` spreads its value over several shards instead. Each thread adds to its own shard, so
+//! [`add`] doesn't conflict with adds from other threads, as long as there are at least as many
+//! shards as threads using the counter.
+//!
+//! The price is paid by readers. [`sum`] reads every shard, and so conflicts with any concurrent
+//! `add`. [`sum_relaxed`] uses [`Ordering::Read`] instead, which is enough for monitoring, but
+//! doesn't prevent the counter from changing before the transaction commits.
+//!
+//! # Examples
+//!
+//! ```
+//! use crossbeam_utils::thread;
+//! use swym::{tcounter::TCounter, thread_key};
+//!
+//! let hits = TCounter::new();
+//! thread::scope(|s| {
+//!     for _ in 0..4 {
+//!         s.spawn(|_| {
+//!             let thread_key = thread_key::get();
+//!             for _ in 0..100 {
+//!                 thread_key.rw(|tx| Ok(hits.add(tx, 1)?));
+//!             }
+//!         });
+//!     }
+//! })
+//! .unwrap();
+//! assert_eq!(thread_key::get().read(|tx| Ok(hits.sum(tx)?)), 400);
+//! ```
+//!
+//! [`TCell`]: ../tcell/struct.TCell.html
+//! [`add`]: struct.TCounter.html#method.add
+//! [`sum`]: struct.TCounter.html#method.sum
+//! [`sum_relaxed`]: struct.TCounter.html#method.sum_relaxed
+//! [`Ordering::Read`]: ../tx/enum.Ordering.html#variant.Read
+
+use crate::{
+    tcell::TCell,
+    tx::{Error, Ordering, Read, Rw},
+};
+use core::{
+    fmt::{self, Debug, Formatter},
+    sync::atomic::{AtomicUsize, Ordering::Relaxed},
+};
+use crossbeam_utils::CachePadded;
+
+/// Number of shards used by [`TCounter::new`](struct.TCounter.html#method.new).
+pub const DEFAULT_SHARD_COUNT: usize = 16;
+
+/// Returns a small number identifying the current thread.
+///
+/// Numbers are handed out in the order threads first touch any counter, so the first `n` such
+/// threads get distinct shards in every counter with at least `n` shards.
+#[inline]
+fn thread_index() -> usize {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    thread_local! {
+        static INDEX: usize = NEXT.fetch_add(1, Relaxed);
+    }
+    INDEX.with(|index| *index)
+}
+
+/// A transactional counter whose writers don't conflict with each other.
+pub struct TCounter {
+    shards: Box<[CachePadded<TCell<isize>>]>,
+}
+
+impl Debug for TCounter {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("TCounter")
+            .field("shard_count", &self.shards.len())
+            .field("sum", &"...")
+            .finish()
+    }
+}
+
+impl Default for TCounter {
+    #[inline]
+    fn default() -> TCounter {
+        TCounter::new()
+    }
+}
+
+impl TCounter {
+    /// Constructs a new `TCounter` with a value of zero, and [`DEFAULT_SHARD_COUNT`] shards.
+    ///
+    /// [`DEFAULT_SHARD_COUNT`]: constant.DEFAULT_SHARD_COUNT.html
+    #[inline]
+    pub fn new() -> TCounter {
+        TCounter::with_shard_count(DEFAULT_SHARD_COUNT)
+    }
+
+    /// Constructs a new `TCounter` with a value of zero, and the specified number of shards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shard_count` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::tcounter::TCounter;
+    ///
+    /// let counter = TCounter::with_shard_count(64);
+    /// assert_eq!(counter.shard_count(), 64);
+    /// ```
+    pub fn with_shard_count(shard_count: usize) -> TCounter {
+        assert!(shard_count > 0, "`TCounter` requires at least one shard");
+        TCounter {
+            shards: (0..shard_count)
+                .map(|_| CachePadded::new(TCell::new(0)))
+                .collect(),
+        }
+    }
+
+    /// Returns the number of shards the counter is split into.
+    #[inline]
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Consumes this `TCounter`, returning its value.
+    #[inline]
+    pub fn into_inner(self) -> isize {
+        self.shards
+            .into_vec()
+            .into_iter()
+            .map(|shard| CachePadded::into_inner(shard).into_inner())
+            .sum()
+    }
+
+    /// Adds `delta` to the counter.
+    ///
+    /// # Errors
+    ///
+    /// If another thread sharing the current thread's shard has modified the counter during the
+    /// current transaction, an error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tcounter::TCounter, thread_key};
+    ///
+    /// let counter = TCounter::new();
+    /// let thread_key = thread_key::get();
+    /// thread_key.rw(|tx| {
+    ///     counter.add(tx, 5)?;
+    ///     counter.add(tx, -2)?;
+    ///     Ok(())
+    /// });
+    /// assert_eq!(counter.into_inner(), 3);
+    /// ```
+    #[inline]
+    pub fn add<'tcell>(&'tcell self, tx: &mut impl Rw<'tcell>, delta: isize) -> Result<(), Error> {
+        let shard = &*self.shards[thread_index() % self.shards.len()];
+        // writing the shard validates it, so there's no need to log the read
+        let value = shard.get(tx, Ordering::Read)?;
+        Ok(shard.set(tx, value + delta)?)
+    }
+
+    /// Returns the value of the counter.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified the counter during the current transaction, an error is
+    /// returned.
+    #[inline]
+    pub fn sum<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<isize, Error> {
+        self.sum_ordered(tx, Ordering::default())
+    }
+
+    /// Returns the value of the counter, without preventing other threads from modifying it before
+    /// the current transaction commits.
+    ///
+    /// In a read write transaction, the result may be stale by the time the transaction commits.
+    /// In a read only transaction, this is the same as [`sum`].
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified the counter since the current transaction started, an error
+    /// is returned.
+    ///
+    /// [`sum`]: #method.sum
+    #[inline]
+    pub fn sum_relaxed<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<isize, Error> {
+        self.sum_ordered(tx, Ordering::Read)
+    }
+
+    #[inline]
+    fn sum_ordered<'tcell>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+        ordering: Ordering,
+    ) -> Result<isize, Error> {
+        let mut sum = 0;
+        for shard in self.shards.iter() {
+            sum += shard.get(tx, ordering)?;
+        }
+        Ok(sum)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{tcounter::TCounter, thread_key};
+    use crossbeam_utils::thread;
+
+    #[test]
+    fn concurrent() {
+        const ITER_COUNT: isize = 10_000;
+        const THREAD_COUNT: isize = 4;
+        let counter = TCounter::new();
+        thread::scope(|s| {
+            for _ in 0..THREAD_COUNT {
+                s.spawn(|_| {
+                    let thread_key = thread_key::get();
+                    for i in 0..ITER_COUNT {
+                        thread_key.rw(|tx| Ok(counter.add(tx, i)?));
+                    }
+                });
+            }
+            s.spawn(|_| {
+                let thread_key = thread_key::get();
+                let mut last = 0;
+                for _ in 0..ITER_COUNT {
+                    // no delta is negative, so the sum never goes down
+                    let sum = thread_key.read(|tx| Ok(counter.sum(tx)?));
+                    assert!(sum >= last);
+                    let relaxed = thread_key.rw(|tx| Ok(counter.sum_relaxed(tx)?));
+                    assert!(relaxed >= sum);
+                    last = relaxed;
+                }
+            });
+        })
+        .unwrap();
+        let expected = THREAD_COUNT * ITER_COUNT * (ITER_COUNT - 1) / 2;
+        assert_eq!(thread_key::get().read(|tx| Ok(counter.sum(tx)?)), expected);
+        assert_eq!(counter.into_inner(), expected);
+    }
+
+    #[test]
+    fn single_shard() {
+        let counter = TCounter::with_shard_count(1);
+        let thread_key = thread_key::get();
+        thread_key.rw(|tx| {
+            counter.add(tx, 3)?;
+            assert_eq!(counter.sum(tx)?, 3);
+            counter.add(tx, -5)?;
+            assert_eq!(counter.sum_relaxed(tx)?, -2);
+            Ok(())
+        });
+        assert_eq!(thread_key.read(|tx| Ok(counter.sum(tx)?)), -2);
+    }
+}
@@ -0,0 +1,485 @@
+//! A transactional double-ended queue, [`tdeque::TDeque`].
+//!
+//! Values can be pushed and popped at both ends. [`pop_front`] and [`pop_back`] wait for a value
+//! using [`AWAIT_RETRY`] when the deque is empty.
+//!
+//! The deque is a doubly linked list of [`TPtr`]s. Operations on the front only touch the front of
+//! the list, and operations on the back only touch the back, so they only conflict with each other
+//! when the deque is (nearly) empty. The length is likewise tracked with a separate count for each
+//! end.
+//!
+//! # Examples
+//!
+//! Work stealing, where the owner works from the back and thieves steal from the front:
+//!
+//! ```
+//! use swym::{tdeque::TDeque, thread_key};
+//!
+//! let work = TDeque::new();
+//! let thread_key = thread_key::get();
+//! thread_key.rw(|tx| {
+//!     for job in 0..4 {
+//!         work.push_back(tx, job)?;
+//!     }
+//!     Ok(())
+//! });
+//! let mine = thread_key.rw(|tx| Ok(*work.pop_back(tx)?));
+//! let stolen = thread_key.rw(|tx| Ok(*work.pop_front(tx)?));
+//! assert_eq!((mine, stolen), (3, 0));
+//! ```
+//!
+//! [`pop_front`]: struct.TDeque.html#method.pop_front
+//! [`pop_back`]: struct.TDeque.html#method.pop_back
+//! [`TPtr`]: ../tptr/struct.TPtr.html
+//! [`AWAIT_RETRY`]: ../tx/struct.Status.html#associatedconstant.AWAIT_RETRY
+
+use crate::{
+    tcell::{Ref, TCell},
+    tptr::TPtr,
+    tx::{Borrow, Error, Ordering, Read, Rw, Status},
+};
+use core::fmt::{self, Debug, Formatter};
+
+struct Node<T> {
+    prev:  TPtr<Node<T>>,
+    next:  TPtr<Node<T>>,
+    value: T,
+}
+
+/// A transactional double-ended queue.
+///
+/// Like [`TQueue`], values are popped by reference, as a [`Ref`] valid for the rest of the
+/// transaction.
+///
+/// [`TQueue`]: ../tqueue/struct.TQueue.html
+/// [`Ref`]: ../tcell/struct.Ref.html
+pub struct TDeque<T> {
+    head:      TPtr<Node<T>>,
+    tail:      TPtr<Node<T>>,
+    // values pushed minus values popped at each end, which may be negative
+    front_len: TCell<isize>,
+    back_len:  TCell<isize>,
+}
+
+impl<T> Debug for TDeque<T> {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("TDeque")
+            .field("values", &"...")
+            .finish()
+    }
+}
+
+impl<T> Default for TDeque<T> {
+    #[inline]
+    fn default() -> TDeque<T> {
+        TDeque::new()
+    }
+}
+
+impl<T> Drop for TDeque<T> {
+    fn drop(&mut self) {
+        let mut node = *self.head.borrow_mut() as *mut Node<T>;
+        while !node.is_null() {
+            let mut owned = unsafe { Box::from_raw(node) };
+            node = *owned.next.borrow_mut() as *mut Node<T>;
+        }
+    }
+}
+
+impl<T> TDeque<T> {
+    /// Constructs a new, empty `TDeque`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::tdeque::TDeque;
+    ///
+    /// static DEQUE: TDeque<String> = TDeque::new();
+    /// ```
+    #[inline]
+    pub const fn new() -> TDeque<T> {
+        TDeque {
+            head:      TPtr::null(),
+            tail:      TPtr::null(),
+            front_len: TCell::new(0),
+            back_len:  TCell::new(0),
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static> TDeque<T> {
+    /// Returns true if the deque contains no values.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified the front of the deque during the current transaction, an
+    /// error is returned.
+    #[inline]
+    pub fn is_empty<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<bool, Error> {
+        Ok(self.head.as_ptr(tx, Ordering::default())?.is_null())
+    }
+
+    /// Returns the number of values in the deque.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified the deque during the current transaction, an error is
+    /// returned.
+    #[inline]
+    pub fn len<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<usize, Error> {
+        let front = self.front_len.get(tx, Ordering::default())?;
+        let back = self.back_len.get(tx, Ordering::default())?;
+        Ok((front + back) as usize)
+    }
+
+    #[inline]
+    fn add_len<'tcell>(
+        tx: &mut impl Rw<'tcell>,
+        len: &'tcell TCell<isize>,
+        amount: isize,
+    ) -> Result<(), Error> {
+        let value = len.get(tx, Ordering::default())?;
+        Ok(len.set(tx, value + amount)?)
+    }
+
+    /// Pushes a value onto the front of the deque.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified the front of the deque during the current transaction, an
+    /// error is returned, and the value is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tdeque::TDeque, thread_key};
+    ///
+    /// let deque = TDeque::new();
+    /// thread_key::get().rw(|tx| Ok(deque.push_front(tx, 42)?));
+    /// ```
+    pub fn push_front<'tcell>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        value: T,
+    ) -> Result<(), Error> {
+        let head = self.head.as_ptr(tx, Ordering::default())?;
+        let node = Box::new(Node {
+            prev: TPtr::null(),
+            next: TPtr::new(head),
+            value,
+        });
+        let node_ptr = &*node as *const Node<T>;
+        self.head.publish_box(tx, node)?;
+        match unsafe { head.as_ref() } {
+            Some(head) => head.prev.set(tx, node_ptr)?,
+            None => self.tail.set(tx, node_ptr)?,
+        }
+        Self::add_len(tx, &self.front_len, 1)
+    }
+
+    /// Pushes a value onto the back of the deque.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified the back of the deque during the current transaction, an
+    /// error is returned, and the value is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tdeque::TDeque, thread_key};
+    ///
+    /// let deque = TDeque::new();
+    /// thread_key::get().rw(|tx| Ok(deque.push_back(tx, 42)?));
+    /// ```
+    pub fn push_back<'tcell>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        value: T,
+    ) -> Result<(), Error> {
+        let tail = self.tail.as_ptr(tx, Ordering::default())?;
+        let node = Box::new(Node {
+            prev: TPtr::new(tail),
+            next: TPtr::null(),
+            value,
+        });
+        let node_ptr = &*node as *const Node<T>;
+        self.tail.publish_box(tx, node)?;
+        match unsafe { tail.as_ref() } {
+            Some(tail) => tail.next.set(tx, node_ptr)?,
+            None => self.head.set(tx, node_ptr)?,
+        }
+        Self::add_len(tx, &self.back_len, 1)
+    }
+}
+
+impl<T: Borrow + Send + Sync + 'static> TDeque<T> {
+    /// Pops a value off of the front of the deque, waiting for a value if the deque is empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AWAIT_RETRY`] if the deque is empty, or an error if another thread has modified
+    /// the front of the deque during the current transaction.
+    ///
+    /// [`AWAIT_RETRY`]: ../tx/struct.Status.html#associatedconstant.AWAIT_RETRY
+    pub fn pop_front<'tx, 'tcell>(
+        &'tcell self,
+        tx: &'tx mut impl Rw<'tcell>,
+    ) -> Result<Ref<'tx, T>, Status>
+    where
+        'tcell: 'tx,
+    {
+        match self.try_pop_front(tx)? {
+            Some(value) => Ok(value),
+            None => Err(Status::AWAIT_RETRY),
+        }
+    }
+
+    /// Pops a value off of the back of the deque, waiting for a value if the deque is empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AWAIT_RETRY`] if the deque is empty, or an error if another thread has modified
+    /// the back of the deque during the current transaction.
+    ///
+    /// [`AWAIT_RETRY`]: ../tx/struct.Status.html#associatedconstant.AWAIT_RETRY
+    pub fn pop_back<'tx, 'tcell>(
+        &'tcell self,
+        tx: &'tx mut impl Rw<'tcell>,
+    ) -> Result<Ref<'tx, T>, Status>
+    where
+        'tcell: 'tx,
+    {
+        match self.try_pop_back(tx)? {
+            Some(value) => Ok(value),
+            None => Err(Status::AWAIT_RETRY),
+        }
+    }
+
+    /// Pops a value off of the front of the deque, returning `None` if the deque is empty.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified the front of the deque during the current transaction, an
+    /// error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tdeque::TDeque, thread_key};
+    ///
+    /// let deque = TDeque::new();
+    /// let thread_key = thread_key::get();
+    /// thread_key.rw(|tx| Ok(deque.push_back(tx, 42)?));
+    /// assert_eq!(thread_key.rw(|tx| Ok(deque.try_pop_front(tx)?.map(|v| *v))), Some(42));
+    /// assert_eq!(thread_key.rw(|tx| Ok(deque.try_pop_front(tx)?.map(|v| *v))), None);
+    /// ```
+    pub fn try_pop_front<'tx, 'tcell>(
+        &'tcell self,
+        tx: &'tx mut impl Rw<'tcell>,
+    ) -> Result<Option<Ref<'tx, T>>, Error>
+    where
+        'tcell: 'tx,
+    {
+        let head = self.head.as_ptr(tx, Ordering::default())?;
+        let node = match unsafe { head.as_ref() } {
+            Some(node) => node,
+            None => return Ok(None),
+        };
+        let next = node.next.as_ptr(tx, Ordering::default())?;
+        self.head.set(tx, next)?;
+        match unsafe { next.as_ref() } {
+            Some(next) => next.prev.set(tx, core::ptr::null())?,
+            None => self.tail.set(tx, next)?,
+        }
+        Self::add_len(tx, &self.front_len, -1)?;
+        unsafe { TPtr::privatize_as_box(tx, node) };
+        Ok(Some(Ref::from(&node.value)))
+    }
+
+    /// Pops a value off of the back of the deque, returning `None` if the deque is empty.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified the back of the deque during the current transaction, an
+    /// error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tdeque::TDeque, thread_key};
+    ///
+    /// let deque = TDeque::new();
+    /// let thread_key = thread_key::get();
+    /// thread_key.rw(|tx| Ok(deque.push_front(tx, 42)?));
+    /// assert_eq!(thread_key.rw(|tx| Ok(deque.try_pop_back(tx)?.map(|v| *v))), Some(42));
+    /// assert_eq!(thread_key.rw(|tx| Ok(deque.try_pop_back(tx)?.map(|v| *v))), None);
+    /// ```
+    pub fn try_pop_back<'tx, 'tcell>(
+        &'tcell self,
+        tx: &'tx mut impl Rw<'tcell>,
+    ) -> Result<Option<Ref<'tx, T>>, Error>
+    where
+        'tcell: 'tx,
+    {
+        let tail = self.tail.as_ptr(tx, Ordering::default())?;
+        let node = match unsafe { tail.as_ref() } {
+            Some(node) => node,
+            None => return Ok(None),
+        };
+        let prev = node.prev.as_ptr(tx, Ordering::default())?;
+        self.tail.set(tx, prev)?;
+        match unsafe { prev.as_ref() } {
+            Some(prev) => prev.next.set(tx, core::ptr::null())?,
+            None => self.head.set(tx, prev)?,
+        }
+        Self::add_len(tx, &self.back_len, -1)?;
+        unsafe { TPtr::privatize_as_box(tx, node) };
+        Ok(Some(Ref::from(&node.value)))
+    }
+
+    /// Returns an iterator over the values of the deque, from front to back.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{tdeque::TDeque, thread_key};
+    ///
+    /// let deque = TDeque::new();
+    /// let thread_key = thread_key::get();
+    /// thread_key.rw(|tx| {
+    ///     deque.push_back(tx, 1)?;
+    ///     deque.push_back(tx, 2)?;
+    ///     deque.push_front(tx, 0)?;
+    ///     Ok(())
+    /// });
+    /// let values = thread_key.read(|tx| {
+    ///     Ok(deque
+    ///         .iter(tx)
+    ///         .map(|value| value.map(|value| *value))
+    ///         .collect::<Result<Vec<_>, _>>()?)
+    /// });
+    /// assert_eq!(values, [0, 1, 2]);
+    /// ```
+    #[inline]
+    pub fn iter<'tx, 'tcell, Tx: Read<'tcell>>(
+        &'tcell self,
+        tx: &'tx Tx,
+    ) -> Iter<'tx, 'tcell, T, Tx> {
+        Iter {
+            tx,
+            next: Some(&self.head),
+        }
+    }
+}
+
+/// An iterator over the values of a [`TDeque`], from front to back.
+///
+/// An item is an error if another thread has modified the deque during the current transaction.
+/// Iteration stops after an error.
+///
+/// [`TDeque`]: struct.TDeque.html
+pub struct Iter<'tx, 'tcell, T, Tx> {
+    tx:   &'tx Tx,
+    next: Option<&'tcell TPtr<Node<T>>>,
+}
+
+impl<'tx, 'tcell, T, Tx> Debug for Iter<'tx, 'tcell, T, Tx> {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter.debug_struct("Iter").finish()
+    }
+}
+
+impl<'tx, 'tcell, T, Tx> Iterator for Iter<'tx, 'tcell, T, Tx>
+where
+    T: Borrow + Send + Sync + 'static,
+    Tx: Read<'tcell>,
+{
+    type Item = Result<Ref<'tx, T>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let link = self.next.take()?;
+        match link.as_ptr(self.tx, Ordering::default()) {
+            Ok(node) => unsafe { node.as_ref() }.map(|node| {
+                self.next = Some(&node.next);
+                Ok(Ref::from(&node.value))
+            }),
+            Err(error) => Some(Err(error)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{tdeque::TDeque, thread_key};
+    use crossbeam_utils::thread;
+
+    #[test]
+    fn both_ends() {
+        let deque = TDeque::new();
+        let thread_key = thread_key::get();
+        thread_key.rw(|tx| {
+            for i in 0..5 {
+                deque.push_back(tx, fvec![i])?;
+                deque.push_front(tx, fvec![i])?;
+            }
+            assert_eq!(deque.len(tx)?, 10);
+            for i in (0..5).rev() {
+                assert_eq!(deque.pop_front(tx)?[0], i);
+                assert_eq!(deque.pop_back(tx)?[0], i);
+            }
+            Ok(())
+        });
+        thread_key.rw(|tx| {
+            deque.push_back(tx, fvec![0])?;
+            deque.push_back(tx, fvec![1])?;
+            Ok(())
+        });
+        // popped from the other end than pushed, so the counts go negative
+        assert_eq!(thread_key.rw(|tx| Ok(deque.pop_front(tx)?[0])), 0);
+        assert_eq!(thread_key.read(|tx| Ok(deque.len(tx)?)), 1);
+        assert_eq!(thread_key.rw(|tx| Ok(deque.pop_front(tx)?[0])), 1);
+        assert!(thread_key.read(|tx| Ok(deque.is_empty(tx)?)));
+        assert_eq!(thread_key.read(|tx| Ok(deque.len(tx)?)), 0);
+    }
+
+    #[test]
+    fn concurrent() {
+        const ITER_COUNT: usize = 10_000;
+        const THREAD_COUNT: usize = 2;
+        let deque = TDeque::new();
+        thread::scope(|s| {
+            for _ in 0..THREAD_COUNT {
+                s.spawn(|_| {
+                    let thread_key = thread_key::get();
+                    for i in 0..ITER_COUNT {
+                        thread_key.rw(|tx| Ok(deque.push_back(tx, i)?));
+                    }
+                });
+                s.spawn(|_| {
+                    let thread_key = thread_key::get();
+                    for i in 0..ITER_COUNT {
+                        thread_key.rw(|tx| Ok(deque.push_front(tx, i)?));
+                    }
+                });
+                s.spawn(|_| {
+                    let thread_key = thread_key::get();
+                    for _ in 0..ITER_COUNT {
+                        thread_key.rw(|tx| Ok(*deque.pop_front(tx)?));
+                    }
+                });
+                s.spawn(|_| {
+                    let thread_key = thread_key::get();
+                    for _ in 0..ITER_COUNT {
+                        thread_key.rw(|tx| Ok(*deque.pop_back(tx)?));
+                    }
+                });
+            }
+        })
+        .unwrap();
+        let thread_key = thread_key::get();
+        assert!(thread_key.read(|tx| Ok(deque.is_empty(tx)?)));
+        assert_eq!(thread_key.read(|tx| Ok(deque.len(tx)?)), 0);
+    }
+}
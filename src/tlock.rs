@@ -0,0 +1,137 @@
+//! A mutex built on transactional memory, [`tlock::TLock`].
+//!
+//! [`RawTLock`] implements [`lock_api::RawMutex`], so `TLock` has the familiar guard based API.
+//! Threads waiting for the lock are parked using [`AWAIT_RETRY`].
+//!
+//! Because the lock state is an ordinary [`TCell`], transactions can also observe it, e.g. to wait
+//! until some lock is released before proceeding.
+//!
+//! If a lock is all that's needed, `parking_lot`'s are faster.
+//!
+//! # Examples
+//!
+//! ```
+//! use crossbeam_utils::thread;
+//! use swym::tlock::TLock;
+//!
+//! let string = TLock::new(String::new());
+//! thread::scope(|s| {
+//!     for i in 0..8 {
+//!         let string = &string;
+//!         s.spawn(move |_| string.lock().push_str(&i.to_string()));
+//!     }
+//! })
+//! .unwrap();
+//! assert_eq!(string.into_inner().len(), 8);
+//! ```
+//!
+//! [`RawTLock`]: struct.RawTLock.html
+//! [`TCell`]: ../tcell/struct.TCell.html
+//! [`AWAIT_RETRY`]: ../tx/struct.Status.html#associatedconstant.AWAIT_RETRY
+
+use crate::{
+    tcell::TCell,
+    thread_key,
+    tx::{Error, Ordering, Read, Status},
+};
+use lock_api::{GuardSend, RawMutex};
+
+/// The raw lock underlying a [`TLock`].
+///
+/// [`TLock`]: type.TLock.html
+#[derive(Debug)]
+pub struct RawTLock {
+    held: TCell<bool>,
+}
+
+impl RawTLock {
+    /// Returns true if the lock is currently held by some thread.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has acquired or released the lock during the current transaction, an
+    /// error is returned.
+    #[inline]
+    pub fn is_locked<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<bool, Error> {
+        self.held.get(tx, Ordering::default())
+    }
+}
+
+unsafe impl RawMutex for RawTLock {
+    const INIT: Self = RawTLock {
+        held: TCell::new(false),
+    };
+    type GuardMarker = GuardSend;
+
+    #[inline]
+    fn lock(&self) {
+        thread_key::get().rw(|tx| {
+            if self.held.get(tx, Ordering::default())? {
+                // sleep until `held` is modified
+                Err(Status::AWAIT_RETRY)
+            } else {
+                Ok(self.held.set(tx, true)?)
+            }
+        })
+    }
+
+    #[inline]
+    fn try_lock(&self) -> bool {
+        thread_key::get().rw(|tx| {
+            Ok(if self.held.get(tx, Ordering::default())? {
+                false
+            } else {
+                self.held.set(tx, true)?;
+                true
+            })
+        })
+    }
+
+    #[inline]
+    fn unlock(&self) {
+        thread_key::get().rw(|tx| Ok(self.held.set(tx, false)?))
+    }
+}
+
+/// A mutual exclusion primitive built on transactional memory.
+pub type TLock<T> = lock_api::Mutex<RawTLock, T>;
+
+/// An RAII guard for a locked [`TLock`].
+///
+/// [`TLock`]: type.TLock.html
+pub type TLockGuard<'a, T> = lock_api::MutexGuard<'a, RawTLock, T>;
+
+#[cfg(test)]
+mod test {
+    use crate::{thread_key, tlock::TLock};
+    use crossbeam_utils::thread;
+
+    #[test]
+    fn contended() {
+        const ITER_COUNT: usize = 10_000;
+        const THREAD_COUNT: usize = 4;
+        let count = TLock::new(0);
+        thread::scope(|s| {
+            for _ in 0..THREAD_COUNT {
+                s.spawn(|_| {
+                    for _ in 0..ITER_COUNT {
+                        *count.lock() += 1;
+                    }
+                });
+            }
+        })
+        .unwrap();
+        assert_eq!(count.into_inner(), ITER_COUNT * THREAD_COUNT);
+    }
+
+    #[test]
+    fn is_locked() {
+        let lock = TLock::new(());
+        let thread_key = thread_key::get();
+        let guard = lock.lock();
+        assert!(lock.try_lock().is_none());
+        assert!(thread_key.read(|tx| Ok(unsafe { lock.raw() }.is_locked(tx)?)));
+        drop(guard);
+        assert!(!thread_key.read(|tx| Ok(unsafe { lock.raw() }.is_locked(tx)?)));
+    }
+}
@@ -0,0 +1,304 @@
+//! A transactional memory location that is either empty or full, [`tmvar::TMVar`].
+//!
+//! [`take`] waits for the `TMVar` to be full, and [`put`] waits for it to be empty, using
+//! [`AWAIT_RETRY`]. A `TMVar` can be used as a one slot channel, or to hand off ownership of a
+//! value between threads.
+//!
+//! # Examples
+//!
+//! ```
+//! use crossbeam_utils::thread;
+//! use swym::{thread_key, tmvar::TMVar};
+//!
+//! let mvar = TMVar::new_empty();
+//! thread::scope(|s| {
+//!     s.spawn(|_| thread_key::get().rw(|tx| Ok(mvar.put(tx, "ping")?)));
+//!     let message = thread_key::get().rw(|tx| Ok(*mvar.take(tx)?));
+//!     assert_eq!(message, "ping");
+//! })
+//! .unwrap();
+//! ```
+//!
+//! [`take`]: struct.TMVar.html#method.take
+//! [`put`]: struct.TMVar.html#method.put
+//! [`AWAIT_RETRY`]: ../tx/struct.Status.html#associatedconstant.AWAIT_RETRY
+
+use crate::{
+    tcell::Ref,
+    tptr::TPtr,
+    tx::{Borrow, Error, Ordering, Read, Rw, Status},
+};
+use core::fmt::{self, Debug, Formatter};
+
+/// A transactional memory location that is either empty, or contains a value.
+///
+/// Like [`TQueue`], values are taken by reference, as a [`Ref`] valid for the rest of the
+/// transaction.
+///
+/// [`TQueue`]: ../tqueue/struct.TQueue.html
+/// [`Ref`]: ../tcell/struct.Ref.html
+pub struct TMVar<T> {
+    value: TPtr<T>,
+}
+
+impl<T> Debug for TMVar<T> {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("TMVar")
+            .field("value", &"...")
+            .finish()
+    }
+}
+
+impl<T> Default for TMVar<T> {
+    #[inline]
+    fn default() -> TMVar<T> {
+        TMVar::new_empty()
+    }
+}
+
+impl<T> Drop for TMVar<T> {
+    fn drop(&mut self) {
+        let value = *self.value.borrow_mut() as *mut T;
+        if !value.is_null() {
+            drop(unsafe { Box::from_raw(value) })
+        }
+    }
+}
+
+impl<T> TMVar<T> {
+    /// Constructs a new `TMVar` containing `value`.
+    #[inline]
+    pub fn new(value: T) -> TMVar<T> {
+        TMVar {
+            value: TPtr::new(Box::into_raw(Box::new(value))),
+        }
+    }
+
+    /// Constructs a new, empty `TMVar`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::tmvar::TMVar;
+    ///
+    /// static MVAR: TMVar<i32> = TMVar::new_empty();
+    /// ```
+    #[inline]
+    pub const fn new_empty() -> TMVar<T> {
+        TMVar {
+            value: TPtr::null(),
+        }
+    }
+
+    /// Consumes this `TMVar`, returning the underlying data, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::tmvar::TMVar;
+    ///
+    /// assert_eq!(TMVar::new(42).into_inner(), Some(42));
+    /// assert_eq!(TMVar::<i32>::new_empty().into_inner(), None);
+    /// ```
+    #[inline]
+    pub fn into_inner(mut self) -> Option<T> {
+        let value = *self.value.borrow_mut() as *mut T;
+        *self.value.borrow_mut() = core::ptr::null();
+        if value.is_null() {
+            None
+        } else {
+            Some(*unsafe { Box::from_raw(value) })
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static> TMVar<T> {
+    /// Returns true if the `TMVar` contains no value.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified the `TMVar` during the current transaction, an error is
+    /// returned.
+    #[inline]
+    pub fn is_empty<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<bool, Error> {
+        Ok(self.value.as_ptr(tx, Ordering::default())?.is_null())
+    }
+
+    /// Stores a value in the `TMVar`, waiting for it to become empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AWAIT_RETRY`] if the `TMVar` is full, or an error if another thread has modified
+    /// the `TMVar` during the current transaction. In either case the value is dropped.
+    ///
+    /// [`AWAIT_RETRY`]: ../tx/struct.Status.html#associatedconstant.AWAIT_RETRY
+    pub fn put<'tcell>(&'tcell self, tx: &mut impl Rw<'tcell>, value: T) -> Result<(), Status> {
+        match self.try_put(tx, value)? {
+            Ok(()) => Ok(()),
+            Err(_) => Err(Status::AWAIT_RETRY),
+        }
+    }
+
+    /// Stores a value in the `TMVar`, returning it if the `TMVar` is full.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified the `TMVar` during the current transaction, an error is
+    /// returned, and the value is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{thread_key, tmvar::TMVar};
+    ///
+    /// let mvar = TMVar::new(1);
+    /// let result = thread_key::get().rw(|tx| Ok(mvar.try_put(tx, 2)?));
+    /// assert_eq!(result, Err(2));
+    /// ```
+    pub fn try_put<'tcell>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        value: T,
+    ) -> Result<Result<(), T>, Error> {
+        if self.is_empty(tx)? {
+            self.value.publish_box(tx, Box::new(value))?;
+            Ok(Ok(()))
+        } else {
+            Ok(Err(value))
+        }
+    }
+}
+
+impl<T: Borrow + Send + Sync + 'static> TMVar<T> {
+    /// Removes the value from the `TMVar`, waiting for it to become full.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AWAIT_RETRY`] if the `TMVar` is empty, or an error if another thread has modified
+    /// the `TMVar` during the current transaction.
+    ///
+    /// [`AWAIT_RETRY`]: ../tx/struct.Status.html#associatedconstant.AWAIT_RETRY
+    pub fn take<'tx, 'tcell>(
+        &'tcell self,
+        tx: &'tx mut impl Rw<'tcell>,
+    ) -> Result<Ref<'tx, T>, Status>
+    where
+        'tcell: 'tx,
+    {
+        match self.try_take(tx)? {
+            Some(value) => Ok(value),
+            None => Err(Status::AWAIT_RETRY),
+        }
+    }
+
+    /// Removes the value from the `TMVar`, returning `None` if the `TMVar` is empty.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified the `TMVar` during the current transaction, an error is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{thread_key, tmvar::TMVar};
+    ///
+    /// let mvar = TMVar::new(1);
+    /// let thread_key = thread_key::get();
+    /// assert_eq!(thread_key.rw(|tx| Ok(mvar.try_take(tx)?.map(|v| *v))), Some(1));
+    /// assert_eq!(thread_key.rw(|tx| Ok(mvar.try_take(tx)?.map(|v| *v))), None);
+    /// ```
+    pub fn try_take<'tx, 'tcell>(
+        &'tcell self,
+        tx: &'tx mut impl Rw<'tcell>,
+    ) -> Result<Option<Ref<'tx, T>>, Error>
+    where
+        'tcell: 'tx,
+    {
+        let value = self.value.as_ptr(tx, Ordering::default())?;
+        match unsafe { value.as_ref() } {
+            Some(value) => {
+                self.value.set(tx, core::ptr::null())?;
+                unsafe { TPtr::privatize_as_box(tx, value) };
+                Ok(Some(Ref::from(value)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Gets a reference to the value in the `TMVar`, waiting for it to become full. The value is
+    /// not removed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AWAIT_RETRY`] if the `TMVar` is empty, or an error if another thread has modified
+    /// the `TMVar` during the current transaction.
+    ///
+    /// [`AWAIT_RETRY`]: ../tx/struct.Status.html#associatedconstant.AWAIT_RETRY
+    pub fn read<'tx, 'tcell>(
+        &'tcell self,
+        tx: &'tx impl Read<'tcell>,
+    ) -> Result<Ref<'tx, T>, Status>
+    where
+        'tcell: 'tx,
+    {
+        let value = self.value.as_ptr(tx, Ordering::default())?;
+        match unsafe { value.as_ref() } {
+            Some(value) => Ok(Ref::from(value)),
+            None => Err(Status::AWAIT_RETRY),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{thread_key, tmvar::TMVar};
+    use crossbeam_utils::thread;
+
+    #[test]
+    fn put_take() {
+        let mvar = TMVar::new_empty();
+        let thread_key = thread_key::get();
+        thread_key.rw(|tx| {
+            assert!(mvar.is_empty(tx)?);
+            mvar.put(tx, fvec![1, 2, 3])?;
+            assert_eq!(mvar.try_put(tx, fvec![4])?.map_err(|v| v.len()), Err(1));
+            assert_eq!(mvar.read(tx)?.len(), 3);
+            Ok(())
+        });
+        let len = thread_key.rw(|tx| Ok(mvar.take(tx)?.len()));
+        assert_eq!(len, 3);
+        assert!(mvar.into_inner().is_none());
+    }
+
+    #[test]
+    fn ping_pong() {
+        const ITER_COUNT: usize = 10_000;
+        let ping = TMVar::new_empty();
+        let pong = TMVar::new_empty();
+        thread::scope(|s| {
+            s.spawn(|_| {
+                let thread_key = thread_key::get();
+                for i in 0..ITER_COUNT {
+                    thread_key.rw(|tx| Ok(ping.put(tx, i)?));
+                    let j = thread_key.rw(|tx| Ok(*pong.take(tx)?));
+                    assert_eq!(i, j);
+                }
+            });
+            s.spawn(|_| {
+                let thread_key = thread_key::get();
+                for _ in 0..ITER_COUNT {
+                    thread_key.rw(|tx| {
+                        let i = *ping.take(tx)?;
+                        pong.put(tx, i)?;
+                        Ok(())
+                    });
+                }
+            });
+        })
+        .unwrap();
+        assert!(ping.into_inner().is_none());
+        assert!(pong.into_inner().is_none());
+    }
+}
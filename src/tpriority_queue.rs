@@ -0,0 +1,376 @@
+//! A transactional min-priority queue, [`tpriority_queue::TPriorityQueue`].
+//!
+//! The queue is a [skew heap] of heap allocated nodes linked by [`TPtr`]s. Pushing or popping an
+//! entry only writes the links along a single (amortized logarithmic) path from the root, so
+//! transactions touching other parts of the heap are unaffected.
+//!
+//! [`pop`] waits for an entry using [`AWAIT_RETRY`] when the queue is empty. Like every other
+//! operation, it composes with any other reads and writes in the same transaction.
+//!
+//! # Examples
+//!
+//! Scheduling work by deadline:
+//!
+//! ```
+//! use swym::{thread_key, tpriority_queue::TPriorityQueue};
+//!
+//! let jobs = TPriorityQueue::new();
+//! let thread_key = thread_key::get();
+//! thread_key.rw(|tx| {
+//!     jobs.push(tx, 30, "later")?;
+//!     jobs.push(tx, 10, "now")?;
+//!     jobs.push(tx, 20, "soon")?;
+//!     Ok(())
+//! });
+//! let (deadline, job) = thread_key.rw(|tx| {
+//!     let (deadline, job) = jobs.pop(tx)?;
+//!     Ok((*deadline, *job))
+//! });
+//! assert_eq!((deadline, job), (10, "now"));
+//! ```
+//!
+//! [skew heap]: https://en.wikipedia.org/wiki/Skew_heap
+//! [`TPtr`]: ../tptr/struct.TPtr.html
+//! [`pop`]: struct.TPriorityQueue.html#method.pop
+//! [`AWAIT_RETRY`]: ../tx/struct.Status.html#associatedconstant.AWAIT_RETRY
+
+use crate::{
+    tcell::Ref,
+    tptr::TPtr,
+    tx::{Borrow, Error, Ordering, Read, Rw, Status},
+};
+use core::fmt::{self, Debug, Formatter};
+
+struct Node<K, V> {
+    left:  TPtr<Node<K, V>>,
+    right: TPtr<Node<K, V>>,
+    key:   K,
+    value: V,
+}
+
+/// A transactional priority queue, popping the entry with the smallest key first.
+///
+/// Entries with equal keys are popped in an unspecified order.
+///
+/// Like [`TQueue`], entries are popped by reference, as [`Ref`]s valid for the rest of the
+/// transaction.
+///
+/// [`TQueue`]: ../tqueue/struct.TQueue.html
+/// [`Ref`]: ../tcell/struct.Ref.html
+pub struct TPriorityQueue<K, V> {
+    root: TPtr<Node<K, V>>,
+}
+
+impl<K, V> Debug for TPriorityQueue<K, V> {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("TPriorityQueue")
+            .field("entries", &"...")
+            .finish()
+    }
+}
+
+impl<K, V> Default for TPriorityQueue<K, V> {
+    #[inline]
+    fn default() -> TPriorityQueue<K, V> {
+        TPriorityQueue::new()
+    }
+}
+
+impl<K, V> Drop for TPriorityQueue<K, V> {
+    fn drop(&mut self) {
+        // the heap is not necessarily balanced, so avoid recursion
+        let mut nodes = vec![*self.root.borrow_mut()];
+        while let Some(node) = nodes.pop() {
+            if !node.is_null() {
+                let mut owned = unsafe { Box::from_raw(node as *mut Node<K, V>) };
+                nodes.push(*owned.left.borrow_mut());
+                nodes.push(*owned.right.borrow_mut());
+            }
+        }
+    }
+}
+
+impl<K, V> TPriorityQueue<K, V> {
+    /// Constructs a new, empty `TPriorityQueue`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::tpriority_queue::TPriorityQueue;
+    ///
+    /// static QUEUE: TPriorityQueue<u64, String> = TPriorityQueue::new();
+    /// ```
+    #[inline]
+    pub const fn new() -> TPriorityQueue<K, V> {
+        TPriorityQueue { root: TPtr::null() }
+    }
+}
+
+impl<K: Ord + Send + Sync + 'static, V: Send + Sync + 'static> TPriorityQueue<K, V> {
+    /// Returns true if the queue contains no entries.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has pushed or popped the smallest entry during the current transaction,
+    /// an error is returned.
+    #[inline]
+    pub fn is_empty<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<bool, Error> {
+        Ok(self.root.as_ptr(tx, Ordering::default())?.is_null())
+    }
+
+    /// Pushes an entry onto the queue.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified the part of the queue the entry is pushed into during the
+    /// current transaction, an error is returned, and the entry is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{thread_key, tpriority_queue::TPriorityQueue};
+    ///
+    /// let queue = TPriorityQueue::new();
+    /// thread_key::get().rw(|tx| Ok(queue.push(tx, 1, "one")?));
+    /// ```
+    pub fn push<'tcell>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        key: K,
+        value: V,
+    ) -> Result<(), Error> {
+        // A skew heap merge of the heap with a single node. Each node on the path that stays above
+        // the new node swaps its children, and the new node adopts the rest of the path as its
+        // left child.
+        let mut link = &self.root;
+        let mut current = link.as_ptr(tx, Ordering::default())?;
+        // the pointer currently stored in `link`
+        let mut stored = current;
+        loop {
+            match unsafe { current.as_ref() } {
+                Some(node) if node.key <= key => {
+                    if stored != current {
+                        link.set(tx, current)?;
+                    }
+                    let left = node.left.as_ptr(tx, Ordering::default())?;
+                    let right = node.right.as_ptr(tx, Ordering::default())?;
+                    node.right.set(tx, left)?;
+                    link = &node.left;
+                    stored = left;
+                    current = right;
+                }
+                _ => {
+                    let node = Box::new(Node {
+                        left: TPtr::new(current),
+                        right: TPtr::null(),
+                        key,
+                        value,
+                    });
+                    return Ok(link.publish_box(tx, node)?);
+                }
+            }
+        }
+    }
+
+    // Merges the heaps `a` and `b`, storing the result in `link`.
+    fn merge<'tcell>(
+        tx: &mut impl Rw<'tcell>,
+        mut link: &'tcell TPtr<Node<K, V>>,
+        mut a: *const Node<K, V>,
+        mut b: *const Node<K, V>,
+    ) -> Result<(), Error> {
+        loop {
+            let (a_node, b_node) = match unsafe { (a.as_ref(), b.as_ref()) } {
+                (Some(a_node), Some(b_node)) => (a_node, b_node),
+                (Some(_), None) => return link.set(tx, a),
+                (None, _) => return link.set(tx, b),
+            };
+            let node = if b_node.key < a_node.key {
+                core::mem::swap(&mut a, &mut b);
+                b_node
+            } else {
+                a_node
+            };
+            link.set(tx, a)?;
+            // the right subtree of `node` is merged with `b`, and becomes the new left subtree
+            let left = node.left.as_ptr(tx, Ordering::default())?;
+            let right = node.right.as_ptr(tx, Ordering::default())?;
+            node.right.set(tx, left)?;
+            link = &node.left;
+            a = right;
+        }
+    }
+}
+
+impl<K, V> TPriorityQueue<K, V>
+where
+    K: Borrow + Ord + Send + Sync + 'static,
+    V: Borrow + Send + Sync + 'static,
+{
+    /// Gets the entry with the smallest key, without removing it.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has pushed or popped the smallest entry during the current transaction,
+    /// an error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{thread_key, tpriority_queue::TPriorityQueue};
+    ///
+    /// let queue = TPriorityQueue::new();
+    /// let thread_key = thread_key::get();
+    /// thread_key.rw(|tx| {
+    ///     queue.push(tx, 2, "two")?;
+    ///     queue.push(tx, 1, "one")?;
+    ///     Ok(())
+    /// });
+    /// let smallest = thread_key.read(|tx| Ok(queue.peek(tx)?.map(|(key, _)| *key)));
+    /// assert_eq!(smallest, Some(1));
+    /// ```
+    pub fn peek<'tx, 'tcell>(
+        &'tcell self,
+        tx: &'tx impl Read<'tcell>,
+    ) -> Result<Option<(Ref<'tx, K>, Ref<'tx, V>)>, Error>
+    where
+        'tcell: 'tx,
+    {
+        let root = self.root.as_ptr(tx, Ordering::default())?;
+        Ok(unsafe { root.as_ref() }.map(|node| (Ref::from(&node.key), Ref::from(&node.value))))
+    }
+
+    /// Pops the entry with the smallest key off of the queue, waiting for an entry if the queue is
+    /// empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AWAIT_RETRY`] if the queue is empty, or an error if another thread has modified
+    /// the top of the queue during the current transaction.
+    ///
+    /// [`AWAIT_RETRY`]: ../tx/struct.Status.html#associatedconstant.AWAIT_RETRY
+    pub fn pop<'tx, 'tcell>(
+        &'tcell self,
+        tx: &'tx mut impl Rw<'tcell>,
+    ) -> Result<(Ref<'tx, K>, Ref<'tx, V>), Status>
+    where
+        'tcell: 'tx,
+    {
+        match self.try_pop(tx)? {
+            Some(entry) => Ok(entry),
+            None => Err(Status::AWAIT_RETRY),
+        }
+    }
+
+    /// Pops the entry with the smallest key off of the queue, returning `None` if the queue is
+    /// empty.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified the top of the queue during the current transaction, an
+    /// error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{thread_key, tpriority_queue::TPriorityQueue};
+    ///
+    /// let queue = TPriorityQueue::<i32, i32>::new();
+    /// let entry = thread_key::get().rw(|tx| Ok(queue.try_pop(tx)?.map(|(key, _)| *key)));
+    /// assert_eq!(entry, None);
+    /// ```
+    pub fn try_pop<'tx, 'tcell>(
+        &'tcell self,
+        tx: &'tx mut impl Rw<'tcell>,
+    ) -> Result<Option<(Ref<'tx, K>, Ref<'tx, V>)>, Error>
+    where
+        'tcell: 'tx,
+    {
+        let root = self.root.as_ptr(tx, Ordering::default())?;
+        let node = match unsafe { root.as_ref() } {
+            Some(node) => node,
+            None => return Ok(None),
+        };
+        let left = node.left.as_ptr(tx, Ordering::default())?;
+        let right = node.right.as_ptr(tx, Ordering::default())?;
+        Self::merge(tx, &self.root, left, right)?;
+        unsafe { TPtr::privatize_as_box(tx, node) };
+        Ok(Some((Ref::from(&node.key), Ref::from(&node.value))))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{tcell::TCell, thread_key, tpriority_queue::TPriorityQueue, tx::Ordering};
+    use crossbeam_utils::thread;
+
+    #[test]
+    fn sorted() {
+        const COUNT: usize = 1_000;
+        let queue = TPriorityQueue::new();
+        let thread_key = thread_key::get();
+        for i in 0..COUNT {
+            // 7919 is prime, so every key is pushed once, in a scattered order
+            let key = i * 7919 % COUNT;
+            thread_key.rw(|tx| Ok(queue.push(tx, key, fvec![key])?));
+        }
+        for i in 0..COUNT {
+            let (key, len) = thread_key.rw(|tx| {
+                assert_eq!(queue.peek(tx)?.map(|(key, _)| *key), Some(i));
+                let (key, value) = queue.pop(tx)?;
+                Ok((*key, value[0]))
+            });
+            assert_eq!((key, len), (i, i));
+        }
+        assert!(thread_key.read(|tx| Ok(queue.is_empty(tx)?)));
+    }
+
+    #[test]
+    fn leak() {
+        let queue = TPriorityQueue::new();
+        let thread_key = thread_key::get();
+        thread_key.rw(|tx| {
+            for i in 0..100 {
+                queue.push(tx, i % 7, fvec![i])?;
+            }
+            Ok(())
+        });
+        for _ in 0..50 {
+            thread_key.rw(|tx| Ok(queue.pop(tx)?.1.len()));
+        }
+    }
+
+    #[test]
+    fn scheduler() {
+        const ITER_COUNT: usize = 10_000;
+        const THREAD_COUNT: usize = 4;
+        let queue = TPriorityQueue::new();
+        let popped = TCell::new(0);
+        thread::scope(|s| {
+            for t in 0..THREAD_COUNT {
+                let (queue, popped) = (&queue, &popped);
+                s.spawn(move |_| {
+                    let thread_key = thread_key::get();
+                    for i in 0..ITER_COUNT {
+                        thread_key.rw(|tx| Ok(queue.push(tx, i, t)?));
+                    }
+                });
+                s.spawn(move |_| {
+                    let thread_key = thread_key::get();
+                    for _ in 0..ITER_COUNT {
+                        thread_key.rw(|tx| {
+                            let (_, thread) = queue.pop(tx)?;
+                            assert!(*thread < THREAD_COUNT);
+                            let count = popped.get(tx, Ordering::default())?;
+                            Ok(popped.set(tx, count + 1)?)
+                        });
+                    }
+                });
+            }
+        })
+        .unwrap();
+        assert_eq!(popped.into_inner(), ITER_COUNT * THREAD_COUNT);
+        assert!(thread_key::get().read(|tx| Ok(queue.is_empty(tx)?)));
+    }
+}
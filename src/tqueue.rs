@@ -0,0 +1,422 @@
+//! A transactional FIFO queue usable as a blocking channel, [`tqueue::TQueue`].
+//!
+//! [`recv`] waits for a value using [`AWAIT_RETRY`] when the queue is empty, and [`send`] waits for
+//! room when a bounded queue is full. Both are ordinary transactional operations, so they compose
+//! with any other reads and writes in the same transaction.
+//!
+//! Senders only touch the back of the queue, and receivers only touch the front, so they only
+//! conflict with each other when the queue is (nearly) empty. In a bounded queue, senders track the
+//! remaining capacity separately from the slots freed by receivers, and only reclaim the freed
+//! slots once their own count runs out.
+//!
+//! # Examples
+//!
+//! Dequeuing a job and marking it as in progress in a single atomic step:
+//!
+//! ```
+//! use swym::{tcell::TCell, thread_key, tqueue::TQueue};
+//!
+//! let jobs = TQueue::bounded(16);
+//! let in_progress = TCell::new(None);
+//!
+//! let thread_key = thread_key::get();
+//! thread_key.rw(|tx| Ok(jobs.send(tx, 42)?));
+//! thread_key.rw(|tx| {
+//!     let job = *jobs.recv(tx)?;
+//!     in_progress.set(tx, Some(job))?;
+//!     Ok(())
+//! });
+//! assert_eq!(in_progress.into_inner(), Some(42));
+//! ```
+//!
+//! [`recv`]: struct.TQueue.html#method.recv
+//! [`send`]: struct.TQueue.html#method.send
+//! [`AWAIT_RETRY`]: ../tx/struct.Status.html#associatedconstant.AWAIT_RETRY
+
+use crate::{
+    tcell::{Ref, TCell},
+    tptr::TPtr,
+    tx::{Borrow, Error, Ordering, Read, Rw, Status},
+};
+use core::fmt::{self, Debug, Formatter};
+
+struct Node<T> {
+    next:  TPtr<Node<T>>,
+    value: T,
+}
+
+/// A transactional FIFO queue, optionally bounded.
+///
+/// Values are received by reference, as a [`Ref`] valid for the rest of the transaction. The
+/// received value is dropped once no running transaction can observe it. Use `Clone` (or
+/// `Arc<T>`) to take a value out of the transaction.
+///
+/// [`Ref`]: ../tcell/struct.Ref.html
+pub struct TQueue<T> {
+    head:          TPtr<Node<T>>,
+    tail:          TPtr<Node<T>>,
+    bounded:       bool,
+    send_capacity: TCell<usize>,
+    recv_freed:    TCell<usize>,
+}
+
+impl<T> Debug for TQueue<T> {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("TQueue")
+            .field("bounded", &self.bounded)
+            .field("values", &"...")
+            .finish()
+    }
+}
+
+impl<T> Default for TQueue<T> {
+    #[inline]
+    fn default() -> TQueue<T> {
+        TQueue::new()
+    }
+}
+
+impl<T> Drop for TQueue<T> {
+    fn drop(&mut self) {
+        let mut node = *self.head.borrow_mut() as *mut Node<T>;
+        while !node.is_null() {
+            let mut owned = unsafe { Box::from_raw(node) };
+            node = *owned.next.borrow_mut() as *mut Node<T>;
+        }
+    }
+}
+
+impl<T> TQueue<T> {
+    /// Constructs a new, empty, unbounded `TQueue`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::tqueue::TQueue;
+    ///
+    /// static QUEUE: TQueue<i32> = TQueue::new();
+    /// ```
+    #[inline]
+    pub const fn new() -> TQueue<T> {
+        TQueue {
+            head:          TPtr::null(),
+            tail:          TPtr::null(),
+            bounded:       false,
+            send_capacity: TCell::new(0),
+            recv_freed:    TCell::new(0),
+        }
+    }
+
+    /// Constructs a new, empty `TQueue` holding at most `capacity` values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::tqueue::TQueue;
+    ///
+    /// let queue = TQueue::<String>::bounded(64);
+    /// ```
+    #[inline]
+    pub fn bounded(capacity: usize) -> TQueue<T> {
+        assert!(
+            capacity > 0,
+            "attempt to create a bounded `TQueue` with no capacity"
+        );
+        TQueue {
+            head:          TPtr::null(),
+            tail:          TPtr::null(),
+            bounded:       true,
+            send_capacity: TCell::new(capacity),
+            recv_freed:    TCell::new(0),
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static> TQueue<T> {
+    /// Returns true if the queue contains no values.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has received from the queue during the current transaction, an error is
+    /// returned.
+    #[inline]
+    pub fn is_empty<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<bool, Error> {
+        Ok(self.head.as_ptr(tx, Ordering::default())?.is_null())
+    }
+
+    /// Pushes a value onto the back of the queue, waiting for room if the queue is full.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AWAIT_RETRY`] if the queue is full, or an error if another thread has modified the
+    /// back of the queue during the current transaction. In either case the value is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{thread_key, tqueue::TQueue};
+    ///
+    /// let queue = TQueue::new();
+    /// thread_key::get().rw(|tx| {
+    ///     queue.send(tx, "hello")?;
+    ///     queue.send(tx, "world")?;
+    ///     Ok(())
+    /// });
+    /// ```
+    ///
+    /// [`AWAIT_RETRY`]: ../tx/struct.Status.html#associatedconstant.AWAIT_RETRY
+    pub fn send<'tcell>(&'tcell self, tx: &mut impl Rw<'tcell>, value: T) -> Result<(), Status> {
+        if self.reserve(tx)? {
+            Ok(self.push(tx, value)?)
+        } else {
+            Err(Status::AWAIT_RETRY)
+        }
+    }
+
+    /// Pushes a value onto the back of the queue, returning it if the queue is full.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified the back of the queue during the current transaction, an
+    /// error is returned, and the value is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{thread_key, tqueue::TQueue};
+    ///
+    /// let queue = TQueue::bounded(1);
+    /// thread_key::get().rw(|tx| {
+    ///     assert_eq!(queue.try_send(tx, 1)?, Ok(()));
+    ///     assert_eq!(queue.try_send(tx, 2)?, Err(2));
+    ///     Ok(())
+    /// });
+    /// ```
+    pub fn try_send<'tcell>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        value: T,
+    ) -> Result<Result<(), T>, Error> {
+        if self.reserve(tx)? {
+            self.push(tx, value)?;
+            Ok(Ok(()))
+        } else {
+            Ok(Err(value))
+        }
+    }
+
+    /// Takes one slot of a bounded queue's capacity, returning false if the queue is full.
+    fn reserve<'tcell>(&'tcell self, tx: &mut impl Rw<'tcell>) -> Result<bool, Error> {
+        if !self.bounded {
+            return Ok(true);
+        }
+        let capacity = self.send_capacity.get(tx, Ordering::default())?;
+        if capacity > 0 {
+            self.send_capacity.set(tx, capacity - 1)?;
+            return Ok(true);
+        }
+        // Only now conflict with receivers, by reclaiming the slots they have freed.
+        let freed = self.recv_freed.get(tx, Ordering::default())?;
+        if freed > 0 {
+            self.recv_freed.set(tx, 0)?;
+            self.send_capacity.set(tx, freed - 1)?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn push<'tcell>(&'tcell self, tx: &mut impl Rw<'tcell>, value: T) -> Result<(), Error> {
+        let node = Box::new(Node {
+            next: TPtr::null(),
+            value,
+        });
+        let node_ptr = &*node as *const Node<T>;
+        let tail = self.tail.as_ptr(tx, Ordering::default())?;
+        match unsafe { tail.as_ref() } {
+            Some(tail) => tail.next.publish_box(tx, node)?,
+            None => self.head.publish_box(tx, node)?,
+        }
+        self.tail.set(tx, node_ptr)
+    }
+}
+
+impl<T: Borrow + Send + Sync + 'static> TQueue<T> {
+    /// Pops a value off of the front of the queue, waiting for a value if the queue is empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AWAIT_RETRY`] if the queue is empty, or an error if another thread has modified
+    /// the front of the queue during the current transaction.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{thread_key, tqueue::TQueue};
+    ///
+    /// let queue = TQueue::new();
+    /// let thread_key = thread_key::get();
+    /// thread_key.rw(|tx| Ok(queue.send(tx, 42)?));
+    /// let value = thread_key.rw(|tx| Ok(*queue.recv(tx)?));
+    /// assert_eq!(value, 42);
+    /// ```
+    ///
+    /// [`AWAIT_RETRY`]: ../tx/struct.Status.html#associatedconstant.AWAIT_RETRY
+    pub fn recv<'tx, 'tcell>(
+        &'tcell self,
+        tx: &'tx mut impl Rw<'tcell>,
+    ) -> Result<Ref<'tx, T>, Status>
+    where
+        'tcell: 'tx,
+    {
+        match self.try_recv(tx)? {
+            Some(value) => Ok(value),
+            None => Err(Status::AWAIT_RETRY),
+        }
+    }
+
+    /// Pops a value off of the front of the queue, returning `None` if the queue is empty.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified the front of the queue during the current transaction, an
+    /// error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{thread_key, tqueue::TQueue};
+    ///
+    /// let queue = TQueue::<i32>::new();
+    /// let value = thread_key::get().rw(|tx| Ok(queue.try_recv(tx)?.map(|value| *value)));
+    /// assert_eq!(value, None);
+    /// ```
+    pub fn try_recv<'tx, 'tcell>(
+        &'tcell self,
+        tx: &'tx mut impl Rw<'tcell>,
+    ) -> Result<Option<Ref<'tx, T>>, Error>
+    where
+        'tcell: 'tx,
+    {
+        let head = self.head.as_ptr(tx, Ordering::default())?;
+        let node = match unsafe { head.as_ref() } {
+            Some(node) => node,
+            None => return Ok(None),
+        };
+        let next = node.next.as_ptr(tx, Ordering::default())?;
+        self.head.set(tx, next)?;
+        if next.is_null() {
+            self.tail.set(tx, next)?;
+        }
+        if self.bounded {
+            let freed = self.recv_freed.get(tx, Ordering::default())?;
+            self.recv_freed.set(tx, freed + 1)?;
+        }
+        unsafe { TPtr::privatize_as_box(tx, node) };
+        Ok(Some(Ref::from(&node.value)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{tcell::TCell, thread_key, tqueue::TQueue, tx::Ordering};
+    use crossbeam_utils::thread;
+
+    #[test]
+    fn fifo() {
+        let queue = TQueue::new();
+        let thread_key = thread_key::get();
+        thread_key.rw(|tx| {
+            for i in 0..10 {
+                queue.send(tx, i)?;
+            }
+            for i in 0..5 {
+                assert_eq!(*queue.recv(tx)?, i);
+            }
+            Ok(())
+        });
+        for i in 5..10 {
+            assert_eq!(thread_key.rw(|tx| Ok(*queue.recv(tx)?)), i);
+        }
+        assert!(thread_key.read(|tx| Ok(queue.is_empty(tx)?)));
+    }
+
+    #[test]
+    fn bounded_full() {
+        let queue = TQueue::bounded(2);
+        let thread_key = thread_key::get();
+        for _ in 0..3 {
+            thread_key.rw(|tx| {
+                assert_eq!(queue.try_send(tx, 1)?, Ok(()));
+                assert_eq!(queue.try_send(tx, 2)?, Ok(()));
+                assert_eq!(queue.try_send(tx, 3)?, Err(3));
+                Ok(())
+            });
+            thread_key.rw(|tx| {
+                assert_eq!(queue.try_recv(tx)?.map(|value| *value), Some(1));
+                assert_eq!(queue.try_recv(tx)?.map(|value| *value), Some(2));
+                assert_eq!(queue.try_recv(tx)?.map(|value| *value), None);
+                Ok(())
+            });
+        }
+    }
+
+    #[test]
+    fn leak() {
+        let queue = TQueue::new();
+        let thread_key = thread_key::get();
+        thread_key.rw(|tx| {
+            for i in 0..100 {
+                queue.send(tx, fvec![i])?;
+            }
+            Ok(())
+        });
+        for _ in 0..50 {
+            thread_key.rw(|tx| Ok(queue.recv(tx)?.len()));
+        }
+    }
+
+    #[test]
+    fn channel() {
+        const ITER_COUNT: usize = 10_000;
+        const THREAD_COUNT: usize = 4;
+        let queue = TQueue::bounded(8);
+        let received = TCell::new(0);
+        let sum = TCell::new(0);
+        thread::scope(|s| {
+            for _ in 0..THREAD_COUNT {
+                s.spawn(|_| {
+                    let thread_key = thread_key::get();
+                    for i in 0..ITER_COUNT {
+                        thread_key.rw(|tx| Ok(queue.send(tx, i)?));
+                    }
+                });
+                s.spawn(|_| {
+                    let thread_key = thread_key::get();
+                    for _ in 0..ITER_COUNT {
+                        thread_key.rw(|tx| {
+                            let value = *queue.recv(tx)?;
+                            let count = received.get(tx, Ordering::default())?;
+                            received.set(tx, count + 1)?;
+                            let total = sum.get(tx, Ordering::default())?;
+                            sum.set(tx, total + value)?;
+                            Ok(())
+                        });
+                    }
+                });
+            }
+        })
+        .unwrap();
+        assert_eq!(received.into_inner(), ITER_COUNT * THREAD_COUNT);
+        assert_eq!(
+            sum.into_inner(),
+            THREAD_COUNT * ITER_COUNT * (ITER_COUNT - 1) / 2
+        );
+        assert!(thread_key::get().read(|tx| Ok(queue.is_empty(tx)?)));
+    }
+}
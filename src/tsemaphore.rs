@@ -0,0 +1,181 @@
+//! A transactional counting semaphore, [`tsemaphore::TSemaphore`].
+//!
+//! # Examples
+//!
+//! Limiting the number of threads concurrently using a resource:
+//!
+//! ```
+//! use crossbeam_utils::thread;
+//! use swym::{thread_key, tsemaphore::TSemaphore};
+//!
+//! let permits = TSemaphore::new(2);
+//! thread::scope(|s| {
+//!     for _ in 0..8 {
+//!         s.spawn(|_| {
+//!             let thread_key = thread_key::get();
+//!             thread_key.rw(|tx| Ok(permits.acquire(tx, 1)?));
+//!             // at most two threads get here at a time
+//!             thread_key.rw(|tx| Ok(permits.release(tx, 1)?));
+//!         });
+//!     }
+//! })
+//! .unwrap();
+//! ```
+
+use crate::{
+    tcell::TCell,
+    tx::{Error, Ordering, Read, Rw, Status},
+};
+
+/// A counting semaphore whose permits are acquired and released transactionally.
+#[derive(Debug, Default)]
+pub struct TSemaphore {
+    permits: TCell<usize>,
+}
+
+impl TSemaphore {
+    /// Constructs a new `TSemaphore` with the specified number of available permits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::tsemaphore::TSemaphore;
+    ///
+    /// static SEMAPHORE: TSemaphore = TSemaphore::new(4);
+    /// ```
+    #[inline]
+    pub const fn new(permits: usize) -> TSemaphore {
+        TSemaphore {
+            permits: TCell::new(permits),
+        }
+    }
+
+    /// Consumes this `TSemaphore`, returning the number of available permits.
+    #[inline]
+    pub fn into_inner(self) -> usize {
+        self.permits.into_inner()
+    }
+
+    /// Returns the number of available permits.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has acquired or released permits during the current transaction, an error
+    /// is returned.
+    #[inline]
+    pub fn available<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<usize, Error> {
+        self.permits.get(tx, Ordering::default())
+    }
+
+    /// Acquires `n` permits, waiting until they are available.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AWAIT_RETRY`] if fewer than `n` permits are available, or an error if another
+    /// thread has acquired or released permits during the current transaction.
+    ///
+    /// [`AWAIT_RETRY`]: ../tx/struct.Status.html#associatedconstant.AWAIT_RETRY
+    #[inline]
+    pub fn acquire<'tcell>(&'tcell self, tx: &mut impl Rw<'tcell>, n: usize) -> Result<(), Status> {
+        if self.try_acquire(tx, n)? {
+            Ok(())
+        } else {
+            Err(Status::AWAIT_RETRY)
+        }
+    }
+
+    /// Acquires `n` permits if they are available, returning true on success.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has acquired or released permits during the current transaction, an error
+    /// is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{thread_key, tsemaphore::TSemaphore};
+    ///
+    /// let semaphore = TSemaphore::new(3);
+    /// thread_key::get().rw(|tx| {
+    ///     assert!(semaphore.try_acquire(tx, 2)?);
+    ///     assert!(!semaphore.try_acquire(tx, 2)?);
+    ///     Ok(())
+    /// });
+    /// assert_eq!(semaphore.into_inner(), 1);
+    /// ```
+    #[inline]
+    pub fn try_acquire<'tcell>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        n: usize,
+    ) -> Result<bool, Error> {
+        let permits = self.permits.get(tx, Ordering::default())?;
+        if permits >= n {
+            self.permits.set(tx, permits - n)?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Releases `n` permits, waking any threads waiting to acquire them.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has acquired or released permits during the current transaction, an error
+    /// is returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of available permits would overflow.
+    #[inline]
+    pub fn release<'tcell>(&'tcell self, tx: &mut impl Rw<'tcell>, n: usize) -> Result<(), Error> {
+        let permits = self.permits.get(tx, Ordering::default())?;
+        let permits = permits
+            .checked_add(n)
+            .expect("overflow in the number of `TSemaphore` permits");
+        Ok(self.permits.set(tx, permits)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{tcell::TCell, thread_key, tsemaphore::TSemaphore, tx::Ordering};
+    use crossbeam_utils::thread;
+
+    #[test]
+    fn acquire_many() {
+        const ITER_COUNT: usize = 1_000;
+        const THREAD_COUNT: usize = 4;
+        let semaphore = TSemaphore::new(3);
+        let held = TCell::new(0);
+        thread::scope(|s| {
+            for thread in 0..THREAD_COUNT {
+                let (semaphore, held) = (&semaphore, &held);
+                s.spawn(move |_| {
+                    let thread_key = thread_key::get();
+                    let n = thread % 3 + 1;
+                    for _ in 0..ITER_COUNT {
+                        thread_key.rw(|tx| {
+                            semaphore.acquire(tx, n)?;
+                            let count = held.get(tx, Ordering::default())?;
+                            assert!(count + n <= 3);
+                            held.set(tx, count + n)?;
+                            Ok(())
+                        });
+                        thread_key.rw(|tx| {
+                            let count = held.get(tx, Ordering::default())?;
+                            held.set(tx, count - n)?;
+                            semaphore.release(tx, n)?;
+                            Ok(())
+                        });
+                    }
+                });
+            }
+        })
+        .unwrap();
+        assert_eq!(semaphore.into_inner(), 3);
+        assert_eq!(held.into_inner(), 0);
+    }
+}
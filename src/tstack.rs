@@ -0,0 +1,334 @@
+//! A transactional LIFO stack, [`tstack::TStack`].
+//!
+//! [`pop`] waits for a value using [`AWAIT_RETRY`] when the stack is empty. Like every other
+//! operation, it composes with any other reads and writes in the same transaction.
+//!
+//! # Examples
+//!
+//! Moving the top of one stack to another in a single atomic step:
+//!
+//! ```
+//! use swym::{thread_key, tstack::TStack};
+//!
+//! let todo = TStack::new();
+//! let done = TStack::new();
+//!
+//! let thread_key = thread_key::get();
+//! thread_key.rw(|tx| {
+//!     todo.push(tx, "wash")?;
+//!     todo.push(tx, "dry")?;
+//!     Ok(())
+//! });
+//! thread_key.rw(|tx| {
+//!     let task = *todo.pop(tx)?;
+//!     done.push(tx, task)?;
+//!     Ok(())
+//! });
+//! let (todo, done) = thread_key.read(|tx| Ok((todo.len(tx)?, done.len(tx)?)));
+//! assert_eq!((todo, done), (1, 1));
+//! ```
+//!
+//! [`pop`]: struct.TStack.html#method.pop
+//! [`AWAIT_RETRY`]: ../tx/struct.Status.html#associatedconstant.AWAIT_RETRY
+
+use crate::{
+    tcell::{Ref, TCell},
+    tptr::TPtr,
+    tx::{Borrow, Error, Ordering, Read, Rw, Status},
+};
+use core::fmt::{self, Debug, Formatter};
+
+struct Node<T> {
+    next:  TPtr<Node<T>>,
+    value: T,
+}
+
+/// A transactional LIFO stack.
+///
+/// Like [`TQueue`], values are popped by reference, as a [`Ref`] valid for the rest of the
+/// transaction.
+///
+/// [`TQueue`]: ../tqueue/struct.TQueue.html
+/// [`Ref`]: ../tcell/struct.Ref.html
+pub struct TStack<T> {
+    head: TPtr<Node<T>>,
+    len:  TCell<usize>,
+}
+
+impl<T> Debug for TStack<T> {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("TStack")
+            .field("values", &"...")
+            .finish()
+    }
+}
+
+impl<T> Default for TStack<T> {
+    #[inline]
+    fn default() -> TStack<T> {
+        TStack::new()
+    }
+}
+
+impl<T> Drop for TStack<T> {
+    fn drop(&mut self) {
+        let mut node = *self.head.borrow_mut() as *mut Node<T>;
+        while !node.is_null() {
+            let mut owned = unsafe { Box::from_raw(node) };
+            node = *owned.next.borrow_mut() as *mut Node<T>;
+        }
+    }
+}
+
+impl<T> TStack<T> {
+    /// Constructs a new, empty `TStack`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::tstack::TStack;
+    ///
+    /// static STACK: TStack<String> = TStack::new();
+    /// ```
+    #[inline]
+    pub const fn new() -> TStack<T> {
+        TStack {
+            head: TPtr::null(),
+            len:  TCell::new(0),
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static> TStack<T> {
+    /// Returns true if the stack contains no values.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified the top of the stack during the current transaction, an
+    /// error is returned.
+    #[inline]
+    pub fn is_empty<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<bool, Error> {
+        Ok(self.head.as_ptr(tx, Ordering::default())?.is_null())
+    }
+
+    /// Returns the number of values in the stack.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified the stack during the current transaction, an error is
+    /// returned.
+    #[inline]
+    pub fn len<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<usize, Error> {
+        self.len.get(tx, Ordering::default())
+    }
+
+    /// Pushes a value onto the top of the stack.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified the top of the stack during the current transaction, an
+    /// error is returned, and the value is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{thread_key, tstack::TStack};
+    ///
+    /// let stack = TStack::new();
+    /// thread_key::get().rw(|tx| Ok(stack.push(tx, 42)?));
+    /// ```
+    pub fn push<'tcell>(&'tcell self, tx: &mut impl Rw<'tcell>, value: T) -> Result<(), Error> {
+        let next = self.head.as_ptr(tx, Ordering::default())?;
+        let node = Box::new(Node {
+            next: TPtr::new(next),
+            value,
+        });
+        self.head.publish_box(tx, node)?;
+        let len = self.len.get(tx, Ordering::default())?;
+        Ok(self.len.set(tx, len + 1)?)
+    }
+}
+
+impl<T: Borrow + Send + Sync + 'static> TStack<T> {
+    /// Pops the value off of the top of the stack, waiting for a value if the stack is empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AWAIT_RETRY`] if the stack is empty, or an error if another thread has modified
+    /// the top of the stack during the current transaction.
+    ///
+    /// [`AWAIT_RETRY`]: ../tx/struct.Status.html#associatedconstant.AWAIT_RETRY
+    pub fn pop<'tx, 'tcell>(
+        &'tcell self,
+        tx: &'tx mut impl Rw<'tcell>,
+    ) -> Result<Ref<'tx, T>, Status>
+    where
+        'tcell: 'tx,
+    {
+        match self.try_pop(tx)? {
+            Some(value) => Ok(value),
+            None => Err(Status::AWAIT_RETRY),
+        }
+    }
+
+    /// Pops the value off of the top of the stack, returning `None` if the stack is empty.
+    ///
+    /// # Errors
+    ///
+    /// If another thread has modified the top of the stack during the current transaction, an
+    /// error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{thread_key, tstack::TStack};
+    ///
+    /// let stack = TStack::new();
+    /// let thread_key = thread_key::get();
+    /// thread_key.rw(|tx| Ok(stack.push(tx, 42)?));
+    /// assert_eq!(thread_key.rw(|tx| Ok(stack.try_pop(tx)?.map(|v| *v))), Some(42));
+    /// assert_eq!(thread_key.rw(|tx| Ok(stack.try_pop(tx)?.map(|v| *v))), None);
+    /// ```
+    pub fn try_pop<'tx, 'tcell>(
+        &'tcell self,
+        tx: &'tx mut impl Rw<'tcell>,
+    ) -> Result<Option<Ref<'tx, T>>, Error>
+    where
+        'tcell: 'tx,
+    {
+        let head = self.head.as_ptr(tx, Ordering::default())?;
+        let node = match unsafe { head.as_ref() } {
+            Some(node) => node,
+            None => return Ok(None),
+        };
+        let next = node.next.as_ptr(tx, Ordering::default())?;
+        self.head.set(tx, next)?;
+        let len = self.len.get(tx, Ordering::default())?;
+        self.len.set(tx, len - 1)?;
+        unsafe { TPtr::privatize_as_box(tx, node) };
+        Ok(Some(Ref::from(&node.value)))
+    }
+
+    /// Returns an iterator over the values of the stack, from top to bottom.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swym::{thread_key, tstack::TStack};
+    ///
+    /// let stack = TStack::new();
+    /// let thread_key = thread_key::get();
+    /// thread_key.rw(|tx| {
+    ///     for i in 0..3 {
+    ///         stack.push(tx, i)?;
+    ///     }
+    ///     Ok(())
+    /// });
+    /// let values = thread_key.read(|tx| {
+    ///     Ok(stack
+    ///         .iter(tx)
+    ///         .map(|value| value.map(|value| *value))
+    ///         .collect::<Result<Vec<_>, _>>()?)
+    /// });
+    /// assert_eq!(values, [2, 1, 0]);
+    /// ```
+    #[inline]
+    pub fn iter<'tx, 'tcell, Tx: Read<'tcell>>(
+        &'tcell self,
+        tx: &'tx Tx,
+    ) -> Iter<'tx, 'tcell, T, Tx> {
+        Iter {
+            tx,
+            next: Some(&self.head),
+        }
+    }
+}
+
+/// An iterator over the values of a [`TStack`], from top to bottom.
+///
+/// An item is an error if another thread has modified the stack during the current transaction.
+/// Iteration stops after an error.
+///
+/// [`TStack`]: struct.TStack.html
+pub struct Iter<'tx, 'tcell, T, Tx> {
+    tx:   &'tx Tx,
+    next: Option<&'tcell TPtr<Node<T>>>,
+}
+
+impl<'tx, 'tcell, T, Tx> Debug for Iter<'tx, 'tcell, T, Tx> {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter.debug_struct("Iter").finish()
+    }
+}
+
+impl<'tx, 'tcell, T, Tx> Iterator for Iter<'tx, 'tcell, T, Tx>
+where
+    T: Borrow + Send + Sync + 'static,
+    Tx: Read<'tcell>,
+{
+    type Item = Result<Ref<'tx, T>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let link = self.next.take()?;
+        match link.as_ptr(self.tx, Ordering::default()) {
+            Ok(node) => unsafe { node.as_ref() }.map(|node| {
+                self.next = Some(&node.next);
+                Ok(Ref::from(&node.value))
+            }),
+            Err(error) => Some(Err(error)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{thread_key, tstack::TStack};
+    use crossbeam_utils::thread;
+
+    #[test]
+    fn lifo() {
+        let stack = TStack::new();
+        let thread_key = thread_key::get();
+        thread_key.rw(|tx| {
+            for i in 0..10 {
+                stack.push(tx, fvec![i])?;
+            }
+            assert_eq!(stack.len(tx)?, 10);
+            for i in (5..10).rev() {
+                assert_eq!(stack.pop(tx)?[0], i);
+            }
+            Ok(())
+        });
+        for i in (0..5).rev() {
+            assert_eq!(thread_key.rw(|tx| Ok(stack.pop(tx)?[0])), i);
+        }
+        assert!(thread_key.read(|tx| Ok(stack.is_empty(tx)?)));
+        assert_eq!(thread_key.read(|tx| Ok(stack.len(tx)?)), 0);
+    }
+
+    #[test]
+    fn concurrent() {
+        const ITER_COUNT: usize = 10_000;
+        const THREAD_COUNT: usize = 4;
+        let stack = TStack::new();
+        thread::scope(|s| {
+            for _ in 0..THREAD_COUNT {
+                s.spawn(|_| {
+                    let thread_key = thread_key::get();
+                    for i in 0..ITER_COUNT {
+                        thread_key.rw(|tx| Ok(stack.push(tx, i)?));
+                    }
+                });
+                s.spawn(|_| {
+                    let thread_key = thread_key::get();
+                    for _ in 0..ITER_COUNT {
+                        thread_key.rw(|tx| Ok(*stack.pop(tx)?));
+                    }
+                });
+            }
+        })
+        .unwrap();
+        assert_eq!(thread_key::get().read(|tx| Ok(stack.len(tx)?)), 0);
+    }
+}
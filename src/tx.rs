@@ -6,6 +6,7 @@ use core::{
     fmt::{self, Debug, Formatter},
     ops::{Deref, DerefMut},
 };
+use std::sync::Arc;
 
 #[derive(PartialEq, Eq)]
 enum ErrorKind {
@@ -243,6 +244,14 @@ pub trait Read<'tcell> {
         tcell: &'tcell TCell<T>,
         ordering: Ordering,
     ) -> Result<Ref<'tx, T>, Error>;
+
+    #[doc(hidden)]
+    fn get_striped<T: Copy>(
+        &self,
+        lock: &'tcell TCell<()>,
+        value: &'tcell UnsafeCell<T>,
+        ordering: Ordering,
+    ) -> Result<T, Error>;
 }
 
 /// Trait for types that represent transactions with the ability to write.
@@ -258,6 +267,22 @@ pub trait Write<'tcell> {
         src: impl _TValue<T>,
     ) -> Result<(), SetError<T>>;
 
+    #[doc(hidden)]
+    fn set_field<T: Send + 'static, U: Copy + Send + 'static>(
+        &mut self,
+        tcell: &'tcell TCell<T>,
+        offset: usize,
+        value: U,
+    ) -> Result<(), SetError<U>>;
+
+    #[doc(hidden)]
+    fn set_striped<T: Copy + Send + 'static>(
+        &mut self,
+        lock: &'tcell TCell<()>,
+        dest: &'tcell UnsafeCell<T>,
+        value: T,
+    ) -> Result<(), SetError<T>>;
+
     #[doc(hidden)]
     fn _privatize<F: FnOnce() + Copy + Send + 'static>(&mut self, privatizer: F);
 }
@@ -285,6 +310,7 @@ unsafe impl<T: 'static> _TValue<T> for T {
 pub unsafe auto trait Borrow {}
 impl<T: ?Sized> !Borrow for UnsafeCell<T> {}
 unsafe impl<T: ?Sized> Borrow for Box<T> {}
+unsafe impl<T: ?Sized> Borrow for Arc<T> {}
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(transparent)]
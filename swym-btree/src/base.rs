@@ -0,0 +1,759 @@
+// based off of https://en.wikipedia.org/wiki/B%2B_tree
+//
+// All the keys of a node live in a single `TCell`, so a lookup logs one read per level. Every
+// change to the links of a node (its children, values, or next leaf) also rewrites its keys, which
+// means links can be read with `Ordering::Read` - validating the keys validates the links.
+//
+// Nodes don't own what their links point to. Links are moved between nodes with `TPtr::set`, and
+// memory is only ever published/privatized once, when an entry or node is created/destroyed.
+
+use std::{
+    mem::{self, ManuallyDrop},
+    ops::{Bound, RangeBounds},
+    ptr,
+};
+use swym::{
+    tcell::{Ref, TCell},
+    tptr::TPtr,
+    tx::{Borrow, Error, Ordering, Read, Rw},
+};
+
+/// Maximum number of keys in a node.
+pub const CAPACITY: usize = 11;
+
+/// Minimum number of keys in a node other than the root.
+pub const MIN_LEN: usize = CAPACITY / 2;
+
+// Number of keys left in a node after it splits.
+const SPLIT: usize = (CAPACITY + 1) / 2;
+
+enum Links<K, V> {
+    Leaf {
+        values: Box<[TPtr<V>]>,
+        next:   TPtr<Node<K, V>>,
+    },
+    Internal {
+        children: Box<[TPtr<Node<K, V>>]>,
+    },
+}
+
+pub struct Node<K, V> {
+    keys:  TCell<Vec<K>>,
+    links: Links<K, V>,
+}
+
+fn slots<T>(ptrs: &[*const T], len: usize) -> Box<[TPtr<T>]> {
+    let mut slots: Vec<_> = ptrs.iter().map(|&ptr| TPtr::new(ptr)).collect();
+    slots.resize_with(len, TPtr::null);
+    slots.into_boxed_slice()
+}
+
+impl<K, V> Node<K, V> {
+    fn leaf(keys: Vec<K>, values: &[*const V], next: *const Node<K, V>) -> Box<Self> {
+        Box::new(Node {
+            keys:  TCell::new(keys),
+            links: Links::Leaf {
+                values: slots(values, CAPACITY),
+                next:   TPtr::new(next),
+            },
+        })
+    }
+
+    fn internal(keys: Vec<K>, children: &[*const Node<K, V>]) -> Box<Self> {
+        Box::new(Node {
+            keys:  TCell::new(keys),
+            links: Links::Internal {
+                children: slots(children, CAPACITY + 1),
+            },
+        })
+    }
+
+    fn is_leaf(&self) -> bool {
+        match self.links {
+            Links::Leaf { .. } => true,
+            Links::Internal { .. } => false,
+        }
+    }
+
+    fn values(&self) -> &[TPtr<V>] {
+        match &self.links {
+            Links::Leaf { values, .. } => values,
+            Links::Internal { .. } => unreachable!(),
+        }
+    }
+
+    fn next_leaf(&self) -> &TPtr<Node<K, V>> {
+        match &self.links {
+            Links::Leaf { next, .. } => next,
+            Links::Internal { .. } => unreachable!(),
+        }
+    }
+
+    fn children(&self) -> &[TPtr<Node<K, V>>] {
+        match &self.links {
+            Links::Internal { children } => children,
+            Links::Leaf { .. } => unreachable!(),
+        }
+    }
+
+    // frees the node, and everything reachable from it
+    unsafe fn free(node: *const Self) {
+        let mut node = Box::from_raw(node as *mut Self);
+        let len = node.keys.borrow_mut().len();
+        match &mut node.links {
+            Links::Leaf { values, .. } => {
+                for value in &mut values[..len] {
+                    drop(Box::from_raw(*value.borrow_mut() as *mut V))
+                }
+            }
+            Links::Internal { children } => {
+                for child in &mut children[..=len] {
+                    Node::free(*child.borrow_mut())
+                }
+            }
+        }
+    }
+}
+
+#[inline]
+fn deref<'tcell, T>(ptr: *const T) -> &'tcell T {
+    debug_assert!(!ptr.is_null());
+    unsafe { &*ptr }
+}
+
+fn read_slots<'tcell, T: Send + Sync + 'static>(
+    tx: &impl Read<'tcell>,
+    slots: &'tcell [TPtr<T>],
+    len: usize,
+) -> Result<Vec<*const T>, Error> {
+    slots[..len]
+        .iter()
+        .map(|slot| slot.as_ptr(tx, Ordering::Read))
+        .collect()
+}
+
+// Writes `new` into `slots`, skipping placeholders (null), and pointers that are already in place.
+fn write_slots<'tcell, T: Send + Sync + 'static>(
+    tx: &mut impl Rw<'tcell>,
+    slots: &'tcell [TPtr<T>],
+    old: &[*const T],
+    new: &[*const T],
+) -> Result<(), Error> {
+    for (index, &ptr) in new.iter().enumerate() {
+        if !ptr.is_null() && old.get(index) != Some(&ptr) {
+            slots[index].set(tx, ptr)?;
+        }
+    }
+    Ok(())
+}
+
+// Index of the child of an internal node which may contain `key`.
+#[inline]
+fn child_index<K: std::borrow::Borrow<Q>, Q: Ord + ?Sized>(keys: &[K], key: &Q) -> usize {
+    match keys.binary_search_by(|probe| probe.borrow().cmp(key)) {
+        Ok(index) => index + 1,
+        Err(index) => index,
+    }
+}
+
+/// The nodes visited while searching for a key.
+pub struct Path<'tcell, K, V> {
+    // internal nodes, and the index of the child that was descended into
+    parents: Vec<(&'tcell Node<K, V>, usize)>,
+    // `None` if the tree is empty
+    leaf:    Option<&'tcell Node<K, V>>,
+    index:   usize,
+}
+
+pub struct VacantLocation<'tcell, K, V> {
+    path: Path<'tcell, K, V>,
+}
+
+pub struct OccupiedLocation<'tcell, K, V> {
+    path:      Path<'tcell, K, V>,
+    pub value: &'tcell V,
+}
+
+pub enum Location<'tcell, K, V> {
+    Vacant(VacantLocation<'tcell, K, V>),
+    Occupied(OccupiedLocation<'tcell, K, V>),
+}
+
+pub struct BTreeRoot<K, V> {
+    root: TPtr<Node<K, V>>,
+}
+
+impl<K, V> BTreeRoot<K, V> {
+    pub const fn new() -> Self {
+        BTreeRoot { root: TPtr::null() }
+    }
+}
+
+impl<K, V> Drop for BTreeRoot<K, V> {
+    fn drop(&mut self) {
+        let root = *self.root.borrow_mut();
+        if !root.is_null() {
+            unsafe { Node::free(root) }
+        }
+    }
+}
+
+impl<K, V> BTreeRoot<K, V>
+where
+    K: Borrow + Clone + Ord + Send + Sync + 'static,
+    V: Send + Sync + 'static,
+{
+    #[inline]
+    fn root<'tcell>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+    ) -> Result<Option<&'tcell Node<K, V>>, Error> {
+        Ok(unsafe { self.root.as_ptr(tx, Ordering::default())?.as_ref() })
+    }
+
+    #[inline]
+    fn keys<'tcell>(tx: &impl Read<'tcell>, node: &'tcell Node<K, V>) -> Result<Vec<K>, Error> {
+        Ok(node.keys.borrow(tx, Ordering::default())?.clone())
+    }
+
+    pub fn location<'tcell, Q>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+        key: &Q,
+    ) -> Result<Location<'tcell, K, V>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut path = Path {
+            parents: Vec::new(),
+            leaf:    None,
+            index:   0,
+        };
+        let mut node = match self.root(tx)? {
+            Some(root) => root,
+            None => return Ok(Location::Vacant(VacantLocation { path })),
+        };
+        loop {
+            let keys = node.keys.borrow(tx, Ordering::default())?;
+            if node.is_leaf() {
+                path.leaf = Some(node);
+                return Ok(
+                    match keys.binary_search_by(|probe| probe.borrow().cmp(key)) {
+                        Ok(index) => {
+                            path.index = index;
+                            let value = deref(node.values()[index].as_ptr(tx, Ordering::Read)?);
+                            Location::Occupied(OccupiedLocation { path, value })
+                        }
+                        Err(index) => {
+                            path.index = index;
+                            Location::Vacant(VacantLocation { path })
+                        }
+                    },
+                );
+            }
+            let index = child_index(&keys, key);
+            path.parents.push((node, index));
+            node = deref(node.children()[index].as_ptr(tx, Ordering::Read)?);
+        }
+    }
+
+    pub fn find<'tcell, Q>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+        key: &Q,
+    ) -> Result<Option<&'tcell V>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut node = match self.root(tx)? {
+            Some(root) => root,
+            None => return Ok(None),
+        };
+        loop {
+            let keys = node.keys.borrow(tx, Ordering::default())?;
+            if node.is_leaf() {
+                return match keys.binary_search_by(|probe| probe.borrow().cmp(key)) {
+                    Ok(index) => Ok(Some(deref(
+                        node.values()[index].as_ptr(tx, Ordering::Read)?,
+                    ))),
+                    Err(_) => Ok(None),
+                };
+            }
+            let index = child_index(&keys, key);
+            node = deref(node.children()[index].as_ptr(tx, Ordering::Read)?);
+        }
+    }
+
+    pub fn contains_key<'tcell, Q>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+        key: &Q,
+    ) -> Result<bool, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        Ok(self.find(tx, key)?.is_some())
+    }
+
+    pub fn insert<'tx, 'tcell>(
+        &'tcell self,
+        tx: &'tx mut impl Rw<'tcell>,
+        key: K,
+        value: V,
+        location: VacantLocation<'tcell, K, V>,
+    ) -> Result<&'tx mut V, Error> {
+        let Path {
+            parents,
+            leaf,
+            index,
+        } = location.path;
+        let mut value = Box::new(value);
+        let value_ptr = &mut *value as *mut V;
+        let leaf = match leaf {
+            Some(leaf) => leaf,
+            None => {
+                let leaf = Node::leaf(vec![key], &[], ptr::null());
+                let leaf_ptr = &*leaf as *const Node<K, V>;
+                self.root.publish_box(tx, leaf)?;
+                deref(leaf_ptr).values()[0].publish_box(tx, value)?;
+                return Ok(unsafe { &mut *value_ptr });
+            }
+        };
+
+        let mut keys = Self::keys(tx, leaf)?;
+        let old = read_slots(tx, leaf.values(), keys.len())?;
+        let mut values = old.clone();
+        keys.insert(index, key);
+        values.insert(index, ptr::null());
+        if keys.len() <= CAPACITY {
+            write_slots(tx, leaf.values(), &old, &values)?;
+            leaf.keys.set(tx, keys)?;
+            leaf.values()[index].publish_box(tx, value)?;
+            return Ok(unsafe { &mut *value_ptr });
+        }
+
+        let right_keys = keys.split_off(SPLIT);
+        let right_values = values.split_off(SPLIT);
+        let separator = right_keys[0].clone();
+        let next = leaf.next_leaf().as_ptr(tx, Ordering::Read)?;
+        let right = Node::leaf(right_keys, &right_values, next);
+        let right_ptr = &*right as *const Node<K, V>;
+        write_slots(tx, leaf.values(), &old, &values)?;
+        leaf.next_leaf().set(tx, right_ptr)?;
+        leaf.keys.set(tx, keys)?;
+        self.insert_into_parent(tx, &parents, separator, right)?;
+        if index < SPLIT {
+            leaf.values()[index].publish_box(tx, value)?;
+        } else {
+            deref(right_ptr).values()[index - SPLIT].publish_box(tx, value)?;
+        }
+        Ok(unsafe { &mut *value_ptr })
+    }
+
+    // Adds `right` as the sibling following the last node in `parents`, splitting parents as
+    // needed.
+    fn insert_into_parent<'tcell>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        parents: &[(&'tcell Node<K, V>, usize)],
+        separator: K,
+        right: Box<Node<K, V>>,
+    ) -> Result<(), Error> {
+        let (&(parent, index), rest) = match parents.split_last() {
+            Some(last) => last,
+            None => {
+                let left = self.root.as_ptr(tx, Ordering::Read)?;
+                let root = Node::internal(vec![separator], &[left]);
+                let root_ptr = &*root as *const Node<K, V>;
+                self.root.publish_box(tx, root)?;
+                deref(root_ptr).children()[1].publish_box(tx, right)?;
+                return Ok(());
+            }
+        };
+
+        let mut keys = Self::keys(tx, parent)?;
+        let old = read_slots(tx, parent.children(), keys.len() + 1)?;
+        let mut children = old.clone();
+        keys.insert(index, separator);
+        children.insert(index + 1, ptr::null());
+        if keys.len() <= CAPACITY {
+            write_slots(tx, parent.children(), &old, &children)?;
+            parent.keys.set(tx, keys)?;
+            parent.children()[index + 1].publish_box(tx, right)?;
+            return Ok(());
+        }
+
+        let right_keys = keys.split_off(SPLIT + 1);
+        let separator = keys.pop().unwrap();
+        let right_children = children.split_off(SPLIT + 1);
+        let sibling = Node::internal(right_keys, &right_children);
+        let sibling_ptr = &*sibling as *const Node<K, V>;
+        write_slots(tx, parent.children(), &old, &children)?;
+        parent.keys.set(tx, keys)?;
+        self.insert_into_parent(tx, rest, separator, sibling)?;
+        if index < SPLIT {
+            parent.children()[index + 1].publish_box(tx, right)?;
+        } else {
+            deref(sibling_ptr).children()[index - SPLIT].publish_box(tx, right)?;
+        }
+        Ok(())
+    }
+
+    pub fn remove<'tcell>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        location: OccupiedLocation<'tcell, K, V>,
+    ) -> Result<&'tcell V, Error> {
+        let OccupiedLocation {
+            path:
+                Path {
+                    parents,
+                    leaf,
+                    index,
+                },
+            value,
+        } = location;
+        let leaf = leaf.unwrap();
+        let mut keys = Self::keys(tx, leaf)?;
+        let old = read_slots(tx, leaf.values(), keys.len())?;
+        let mut values = old.clone();
+        keys.remove(index);
+        values.remove(index);
+        write_slots(tx, leaf.values(), &old, &values)?;
+        let len = keys.len();
+        leaf.keys.set(tx, keys)?;
+        unsafe { TPtr::privatize_as_box(tx, value) };
+
+        if parents.is_empty() {
+            if len == 0 {
+                self.root.set(tx, ptr::null())?;
+                unsafe { TPtr::privatize_as_box(tx, leaf) };
+            }
+        } else if len < MIN_LEN {
+            self.rebalance(tx, &parents, leaf)?;
+        }
+        Ok(value)
+    }
+
+    // Restores the minimum length of `node`, the child of the last node in `parents`, by either
+    // moving an entry from a sibling, or merging with a sibling.
+    fn rebalance<'tcell>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        parents: &[(&'tcell Node<K, V>, usize)],
+        node: &'tcell Node<K, V>,
+    ) -> Result<(), Error> {
+        let (&(parent, index), rest) = parents.split_last().unwrap();
+        let mut parent_keys = Self::keys(tx, parent)?;
+        let parent_old = read_slots(tx, parent.children(), parent_keys.len() + 1)?;
+        // `separator` is the index of the key in parent between left and right
+        let (left, right, separator) = if index > 0 {
+            (deref(parent_old[index - 1]), node, index - 1)
+        } else {
+            (node, deref(parent_old[1]), 0)
+        };
+        let mut left_keys = Self::keys(tx, left)?;
+        let mut right_keys = Self::keys(tx, right)?;
+        let separator_key = &mut parent_keys[separator];
+        let merged = if node.is_leaf() {
+            let merged = Self::redistribute(
+                tx,
+                (left.values(), &mut left_keys),
+                (right.values(), &mut right_keys),
+                separator_key,
+                0,
+                ptr::eq(node, left),
+            )?;
+            if merged {
+                let next = right.next_leaf().as_ptr(tx, Ordering::Read)?;
+                left.next_leaf().set(tx, next)?;
+            }
+            merged
+        } else {
+            Self::redistribute(
+                tx,
+                (left.children(), &mut left_keys),
+                (right.children(), &mut right_keys),
+                separator_key,
+                1,
+                ptr::eq(node, left),
+            )?
+        };
+        left.keys.set(tx, left_keys)?;
+        if !merged {
+            right.keys.set(tx, right_keys)?;
+            parent.keys.set(tx, parent_keys)?;
+            return Ok(());
+        }
+
+        unsafe { TPtr::privatize_as_box(tx, right) };
+        let mut parent_links = parent_old.clone();
+        parent_keys.remove(separator);
+        parent_links.remove(separator + 1);
+        write_slots(tx, parent.children(), &parent_old, &parent_links)?;
+        let parent_len = parent_keys.len();
+        parent.keys.set(tx, parent_keys)?;
+        if rest.is_empty() {
+            if parent_len == 0 {
+                // the root has a single child, so that child becomes the root
+                self.root.set(tx, left)?;
+                unsafe { TPtr::privatize_as_box(tx, parent) };
+            }
+        } else if parent_len < MIN_LEN {
+            self.rebalance(tx, rest, parent)?;
+        }
+        Ok(())
+    }
+
+    // Either merges right into left, returning true, or moves a single entry from the sibling into
+    // the short node (left if `to_left`, otherwise right).
+    //
+    // Leaves have as many links as keys (`extra == 0`), and internal nodes have one more link than
+    // keys (`extra == 1`). The keys of internal nodes rotate through the separator in the parent.
+    fn redistribute<'tcell, T: Send + Sync + 'static>(
+        tx: &mut impl Rw<'tcell>,
+        (left_slots, left_keys): (&'tcell [TPtr<T>], &mut Vec<K>),
+        (right_slots, right_keys): (&'tcell [TPtr<T>], &mut Vec<K>),
+        separator: &mut K,
+        extra: usize,
+        to_left: bool,
+    ) -> Result<bool, Error> {
+        let leaf = extra == 0;
+        let left_old = read_slots(tx, left_slots, left_keys.len() + extra)?;
+        let right_old = read_slots(tx, right_slots, right_keys.len() + extra)?;
+        let mut left_links = left_old.clone();
+        let mut right_links = right_old.clone();
+
+        if left_keys.len() + right_keys.len() + extra <= CAPACITY {
+            if !leaf {
+                left_keys.push(separator.clone());
+            }
+            left_keys.append(right_keys);
+            left_links.append(&mut right_links);
+            write_slots(tx, left_slots, &left_old, &left_links)?;
+            return Ok(true);
+        }
+
+        if to_left {
+            let link = right_links.remove(0);
+            let key = right_keys.remove(0);
+            if leaf {
+                left_keys.push(key);
+                *separator = right_keys[0].clone();
+            } else {
+                left_keys.push(mem::replace(separator, key));
+            }
+            left_links.push(link);
+        } else {
+            let link = left_links.pop().unwrap();
+            let key = left_keys.pop().unwrap();
+            if leaf {
+                *separator = key.clone();
+                right_keys.insert(0, key);
+            } else {
+                right_keys.insert(0, mem::replace(separator, key));
+            }
+            right_links.insert(0, link);
+        }
+        write_slots(tx, left_slots, &left_old, &left_links)?;
+        write_slots(tx, right_slots, &right_old, &right_links)?;
+        Ok(false)
+    }
+
+    pub fn range<'tx, 'tcell, Q, R, Tx>(
+        &'tcell self,
+        tx: &'tx Tx,
+        range: R,
+    ) -> Result<Range<'tx, 'tcell, K, V, Q, R, Tx>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+        Tx: Read<'tcell>,
+    {
+        let mut node = match self.root(tx)? {
+            Some(root) => root,
+            None => {
+                return Ok(Range {
+                    tx,
+                    leaf: None,
+                    index: 0,
+                    range,
+                    phantom: std::marker::PhantomData,
+                });
+            }
+        };
+        loop {
+            let keys = node.keys.borrow(tx, Ordering::default())?;
+            let search = |key: &Q| keys.binary_search_by(|probe| probe.borrow().cmp(key));
+            if node.is_leaf() {
+                let index = match range.start_bound() {
+                    Bound::Unbounded => 0,
+                    Bound::Included(start) => search(start).unwrap_or_else(|index| index),
+                    Bound::Excluded(start) => search(start)
+                        .map(|index| index + 1)
+                        .unwrap_or_else(|index| index),
+                };
+                return Ok(Range {
+                    tx,
+                    leaf: Some((node, keys)),
+                    index,
+                    range,
+                    phantom: std::marker::PhantomData,
+                });
+            }
+            let index = match range.start_bound() {
+                Bound::Unbounded => 0,
+                Bound::Included(start) | Bound::Excluded(start) => child_index(&keys, start),
+            };
+            node = deref(node.children()[index].as_ptr(tx, Ordering::Read)?);
+        }
+    }
+
+    // checks all the B+tree properties (for debugging)
+    pub fn verify<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<(), Error> {
+        let root = match self.root(tx)? {
+            Some(root) => root,
+            None => return Ok(()),
+        };
+        let mut leaves = Vec::new();
+        let len = self.verify_node(tx, root, true, None, None, &mut leaves)?;
+        let first = leaves[0];
+        assert!(leaves.iter().all(|&(_, depth)| depth == first.1));
+
+        // the leaf links visit every leaf in order
+        let mut count = 0;
+        let mut prev: Option<K> = None;
+        let mut leaf = Some(first.0);
+        let mut visited = leaves.iter();
+        while let Some(node) = leaf {
+            assert!(ptr::eq(node, visited.next().unwrap().0));
+            let keys = node.keys.borrow(tx, Ordering::default())?;
+            for key in keys.iter() {
+                assert!(prev.as_ref().map(|prev| prev < key).unwrap_or(true));
+                prev = Some(key.clone());
+                count += 1;
+            }
+            leaf = unsafe { node.next_leaf().as_ptr(tx, Ordering::default())?.as_ref() };
+        }
+        assert!(visited.next().is_none());
+        assert_eq!(count, len);
+        Ok(())
+    }
+
+    fn verify_node<'tcell>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+        node: &'tcell Node<K, V>,
+        is_root: bool,
+        lower: Option<&K>,
+        upper: Option<&K>,
+        leaves: &mut Vec<(&'tcell Node<K, V>, usize)>,
+    ) -> Result<usize, Error> {
+        let keys = Self::keys(tx, node)?;
+        assert!(keys.len() <= CAPACITY);
+        assert!(is_root || keys.len() >= MIN_LEN);
+        assert!(!keys.is_empty() || (is_root && node.is_leaf()));
+        assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(keys
+            .iter()
+            .all(|key| lower.map(|lower| lower <= key).unwrap_or(true)));
+        assert!(keys
+            .iter()
+            .all(|key| upper.map(|upper| key < upper).unwrap_or(true)));
+        if node.is_leaf() {
+            for value in &node.values()[..keys.len()] {
+                assert!(!value.as_ptr(tx, Ordering::default())?.is_null());
+            }
+            leaves.push((node, 0));
+            return Ok(keys.len());
+        }
+        let mut len = 0;
+        let start = leaves.len();
+        for (index, child) in node.children()[..=keys.len()].iter().enumerate() {
+            let child = deref(child.as_ptr(tx, Ordering::default())?);
+            let lower = if index == 0 {
+                lower
+            } else {
+                Some(&keys[index - 1])
+            };
+            let upper = keys.get(index).or(upper);
+            len += self.verify_node(tx, child, false, lower, upper, leaves)?;
+        }
+        for leaf in &mut leaves[start..] {
+            leaf.1 += 1;
+        }
+        Ok(len)
+    }
+}
+
+/// An iterator over a range of entries of a B+tree, in ascending order.
+pub struct Range<'tx, 'tcell, K, V, Q: ?Sized, R, Tx> {
+    tx:      &'tx Tx,
+    leaf:    Option<(&'tcell Node<K, V>, Ref<'tx, Vec<K>>)>,
+    index:   usize,
+    range:   R,
+    phantom: std::marker::PhantomData<fn(&Q)>,
+}
+
+impl<'tx, 'tcell, K, V, Q, R, Tx> Range<'tx, 'tcell, K, V, Q, R, Tx>
+where
+    K: Borrow + Clone + Ord + Send + Sync + std::borrow::Borrow<Q> + 'static,
+    V: Send + Sync + 'static,
+    Q: Ord + ?Sized,
+    R: RangeBounds<Q>,
+    Tx: Read<'tcell>,
+{
+    fn next_entry(&mut self) -> Result<Option<(Ref<'tx, K>, &'tcell V)>, Error> {
+        loop {
+            let (node, keys) = match &self.leaf {
+                Some(leaf) => leaf,
+                None => return Ok(None),
+            };
+            if let Some(key) = keys.get(self.index) {
+                let in_range = match self.range.end_bound() {
+                    Bound::Unbounded => true,
+                    Bound::Included(end) => key.borrow() <= end,
+                    Bound::Excluded(end) => key.borrow() < end,
+                };
+                if !in_range {
+                    return Ok(None);
+                }
+                let value = deref(node.values()[self.index].as_ptr(self.tx, Ordering::Read)?);
+                // The keys are kept alive until the end of the transaction (not just as long as
+                // `keys`).
+                let key = Ref::new(unsafe { ptr::read(key as *const K as *const ManuallyDrop<K>) });
+                self.index += 1;
+                return Ok(Some((key, value)));
+            }
+            let next = unsafe { node.next_leaf().as_ptr(self.tx, Ordering::Read)?.as_ref() };
+            self.leaf = match next {
+                Some(next) => Some((next, next.keys.borrow(self.tx, Ordering::default())?)),
+                None => None,
+            };
+            self.index = 0;
+        }
+    }
+}
+
+impl<'tx, 'tcell, K, V, Q, R, Tx> Iterator for Range<'tx, 'tcell, K, V, Q, R, Tx>
+where
+    K: Borrow + Clone + Ord + Send + Sync + std::borrow::Borrow<Q> + 'static,
+    V: Send + Sync + 'static,
+    Q: Ord + ?Sized,
+    R: RangeBounds<Q>,
+    Tx: Read<'tcell>,
+{
+    type Item = Result<(Ref<'tx, K>, &'tcell V), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.next_entry();
+        if result.as_ref().map(Option::is_none).unwrap_or(true) {
+            self.leaf = None;
+        }
+        result.transpose()
+    }
+}
@@ -0,0 +1,483 @@
+#![deny(unused_must_use)]
+
+mod base;
+
+use crate::base::{BTreeRoot, Location, OccupiedLocation, VacantLocation};
+use std::ops::RangeBounds;
+use swym::{
+    tcell::{Ref, TCell, View},
+    thread_key,
+    tx::{Borrow, Error, Ordering, Read, SetError, Status},
+    RwTx,
+};
+
+pub struct TBTreeMapRaw<K, V> {
+    pub root: BTreeRoot<K, TCell<V>>,
+}
+
+impl<K, V> TBTreeMapRaw<K, V> {
+    pub const fn new() -> Self {
+        TBTreeMapRaw {
+            root: BTreeRoot::new(),
+        }
+    }
+}
+
+impl<K: Borrow + Clone + Ord + Send + Sync + 'static, V: Send + Sync + 'static> TBTreeMapRaw<K, V> {
+    pub fn verify<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<(), Error> {
+        self.root.verify(tx)
+    }
+
+    pub fn contains_key<'tcell, Q>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+        key: &Q,
+    ) -> Result<bool, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.root.contains_key(tx, key)
+    }
+
+    pub fn entry<'tx, 'tcell>(
+        &'tcell self,
+        tx: &'tx mut RwTx<'tcell>,
+        key: K,
+    ) -> Result<Entry<'tx, 'tcell, K, V>, Error> {
+        Ok(match self.root.location(tx, &key)? {
+            Location::Vacant(location) => Entry::Vacant(VacantEntry {
+                location,
+                tree: self,
+                tx,
+                key,
+            }),
+            Location::Occupied(location) => Entry::Occupied(OccupiedEntry {
+                location,
+                tree: self,
+                tx,
+                key,
+            }),
+        })
+    }
+}
+
+impl<K: Borrow + Clone + Ord + Send + Sync + 'static, V: Borrow + Send + Sync + 'static>
+    TBTreeMapRaw<K, V>
+{
+    pub fn get<'tx, 'tcell, Q>(
+        &'tcell self,
+        tx: &'tx impl Read<'tcell>,
+        key: &Q,
+    ) -> Result<Option<Ref<'tx, V>>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let result = match self.root.find(tx, key)? {
+            None => None,
+            Some(value) => Some(value.borrow(tx, Ordering::default())?),
+        };
+        Ok(result)
+    }
+
+    pub fn get_mut<'tx, 'tcell, Q>(
+        &'tcell self,
+        tx: &'tx mut RwTx<'tcell>,
+        key: &Q,
+    ) -> Result<Option<View<'tcell, V, &'tx mut RwTx<'tcell>>>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let result = match self.root.find(tx, key)? {
+            None => None,
+            Some(value) => Some(value.view(tx)),
+        };
+        Ok(result)
+    }
+
+    pub fn insert<'tcell>(
+        &'tcell self,
+        tx: &mut RwTx<'tcell>,
+        key: K,
+        value: V,
+    ) -> Result<Option<V>, Error>
+    where
+        V: Clone,
+    {
+        let loc = self.root.location(tx, &key)?;
+        let result = match loc {
+            Location::Vacant(vacant) => {
+                self.root.insert(tx, key, TCell::new(value), vacant)?;
+                None
+            }
+            Location::Occupied(location) => Some(location.value.replace(tx, value)?),
+        };
+        Ok(result)
+    }
+
+    pub fn remove<'tcell, Q>(
+        &'tcell self,
+        tx: &mut RwTx<'tcell>,
+        key: &Q,
+    ) -> Result<Option<Ref<'tcell, V>>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let loc = self.root.location(tx, key)?;
+        match loc {
+            Location::Vacant(..) => Ok(None),
+            Location::Occupied(location) => {
+                let v = self.root.remove(tx, location)?;
+                Ok(Some(unsafe {
+                    Ref::downcast(v.borrow(tx, Ordering::default())?, tx)
+                }))
+            }
+        }
+    }
+
+    /// Returns an iterator over the entries whose keys are in `range`, in ascending order.
+    pub fn range<'tx, 'tcell, Q, R, Tx>(
+        &'tcell self,
+        tx: &'tx Tx,
+        range: R,
+    ) -> Result<Range<'tx, 'tcell, K, V, Q, R, Tx>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+        Tx: Read<'tcell>,
+    {
+        Ok(Range {
+            tx,
+            entries: self.root.range(tx, range)?,
+        })
+    }
+}
+
+/// An iterator over a range of entries of a `TBTreeMapRaw`, in ascending order.
+///
+/// An item is an error if another thread has modified the tree during the current transaction.
+pub struct Range<'tx, 'tcell, K, V, Q: ?Sized, R, Tx> {
+    tx:      &'tx Tx,
+    entries: base::Range<'tx, 'tcell, K, TCell<V>, Q, R, Tx>,
+}
+
+impl<'tx, 'tcell, K, V, Q, R, Tx> Iterator for Range<'tx, 'tcell, K, V, Q, R, Tx>
+where
+    K: Borrow + Clone + Ord + Send + Sync + std::borrow::Borrow<Q> + 'static,
+    V: Borrow + Send + Sync + 'static,
+    Q: Ord + ?Sized,
+    R: RangeBounds<Q>,
+    Tx: Read<'tcell>,
+{
+    type Item = Result<(Ref<'tx, K>, Ref<'tx, V>), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, value) = match self.entries.next()? {
+            Ok(entry) => entry,
+            Err(error) => return Some(Err(error)),
+        };
+        Some(
+            value
+                .borrow(self.tx, Ordering::default())
+                .map(|value| (key, value)),
+        )
+    }
+}
+
+pub struct TBTreeMap<K, V> {
+    pub raw: TBTreeMapRaw<K, V>,
+}
+
+impl<K, V> TBTreeMap<K, V> {
+    pub const fn new() -> Self {
+        TBTreeMap {
+            raw: TBTreeMapRaw::new(),
+        }
+    }
+}
+
+impl<K: Borrow + Clone + Ord + Send + Sync + 'static, V: Borrow + Send + Sync + 'static>
+    TBTreeMap<K, V>
+{
+    pub fn with<'tx, 'tcell>(
+        &'tcell self,
+        tx: &'tx mut RwTx<'tcell>,
+    ) -> TBTreeMapWith<'tx, 'tcell, K, V> {
+        TBTreeMapWith {
+            tree: &self.raw,
+            tx,
+        }
+    }
+
+    pub fn atomic<F, R>(&self, mut f: F) -> R
+    where
+        F: for<'tx, 'tcell> FnMut(TBTreeMapWith<'tx, 'tcell, K, V>) -> Result<R, Status>,
+    {
+        thread_key::get().rw(move |tx| f(self.with(tx)))
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        thread_key::get().read(move |tx| Ok(self.raw.contains_key(tx, key)?))
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Ord + ?Sized,
+        V: Clone,
+    {
+        thread_key::get().read(move |tx| {
+            let r = self.raw.get(tx, key)?.map(|value| value.clone());
+            Ok(r)
+        })
+    }
+
+    pub fn insert(&self, key: K, value: V) -> Option<V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.atomic(move |mut tree| Ok(tree.insert(key.clone(), value.clone())?))
+    }
+
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Ord + ?Sized,
+        V: Clone,
+    {
+        self.atomic(move |mut tree| {
+            let value = tree.remove(key)?;
+            Ok(value.map(|value| value.clone()))
+        })
+    }
+
+    /// Returns a copy of the entries whose keys are in `range`, in ascending order.
+    pub fn range<Q, R>(&self, range: R) -> Vec<(K, V)>
+    where
+        K: std::borrow::Borrow<Q> + Clone,
+        V: Clone,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q> + Clone,
+    {
+        thread_key::get().read(move |tx| {
+            self.raw
+                .range(tx, range.clone())?
+                .map(|entry| entry.map(|(key, value)| (key.clone(), value.clone())))
+                .collect()
+        })
+    }
+}
+
+pub struct TBTreeMapWith<'tx, 'tcell, K, V> {
+    pub tree: &'tcell TBTreeMapRaw<K, V>,
+    pub tx:   &'tx mut RwTx<'tcell>,
+}
+
+impl<'tx, 'tcell, K, V> TBTreeMapWith<'tx, 'tcell, K, V>
+where
+    K: Borrow + Clone + Ord + Send + Sync + 'static,
+    V: Borrow + Send + Sync + 'static,
+{
+    pub fn get<Q>(&self, key: &Q) -> Result<Option<Ref<'_, V>>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.tree.get(self.tx, key)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> Result<bool, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.tree.contains_key(self.tx, key)
+    }
+
+    pub fn entry<'a>(&'a mut self, key: K) -> Result<Entry<'a, 'tcell, K, V>, Error> {
+        self.tree.entry(self.tx, key)
+    }
+
+    pub fn insert<'a>(&'a mut self, key: K, value: V) -> Result<Option<V>, Error>
+    where
+        V: Clone,
+    {
+        self.tree.insert(self.tx, key, value)
+    }
+
+    pub fn remove<'a, Q>(&'a mut self, key: &Q) -> Result<Option<Ref<'tx, V>>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.tree.remove(self.tx, key)
+    }
+
+    pub fn range<'a, Q, R>(
+        &'a self,
+        range: R,
+    ) -> Result<Range<'a, 'tcell, K, V, Q, R, RwTx<'tcell>>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        self.tree.range(self.tx, range)
+    }
+}
+
+pub struct VacantEntry<'tx, 'tcell, K, V> {
+    location: VacantLocation<'tcell, K, TCell<V>>,
+    tree:     &'tcell TBTreeMapRaw<K, V>,
+    tx:       &'tx mut RwTx<'tcell>,
+    key:      K,
+}
+
+impl<'tx, 'tcell, K, V> VacantEntry<'tx, 'tcell, K, V>
+where
+    K: Borrow + Clone + Ord + Send + Sync + 'static,
+    V: Borrow + Send + Sync + 'static,
+{
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    pub fn insert(self, value: V) -> Result<&'tx mut V, Error> {
+        let value = self
+            .tree
+            .root
+            .insert(self.tx, self.key, TCell::new(value), self.location)?;
+        Ok(value.borrow_mut())
+    }
+}
+
+pub struct OccupiedEntry<'tx, 'tcell, K, V> {
+    location: OccupiedLocation<'tcell, K, TCell<V>>,
+    tree:     &'tcell TBTreeMapRaw<K, V>,
+    tx:       &'tx mut RwTx<'tcell>,
+    key:      K,
+}
+
+impl<'tx, 'tcell, K, V> OccupiedEntry<'tx, 'tcell, K, V>
+where
+    K: Borrow + Clone + Ord + Send + Sync + 'static,
+    V: Borrow + Send + Sync + 'static,
+{
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn view<'a>(&'a mut self) -> View<'tcell, V, &'a mut RwTx<'tcell>> {
+        self.location.value.view(self.tx)
+    }
+
+    pub fn into_view(self) -> View<'tcell, V, &'tx mut RwTx<'tcell>> {
+        self.location.value.view(self.tx)
+    }
+
+    pub fn insert(&mut self, value: V) -> Result<V, Error>
+    where
+        V: Clone,
+    {
+        Ok(self.location.value.replace(self.tx, value)?)
+    }
+
+    pub fn remove(self) -> Result<Ref<'tx, V>, Error> {
+        let value = self.tree.root.remove(self.tx, self.location)?;
+        value.borrow(self.tx, Ordering::default())
+    }
+}
+
+pub enum Entry<'tx, 'tcell, K, V> {
+    Vacant(VacantEntry<'tx, 'tcell, K, V>),
+    Occupied(OccupiedEntry<'tx, 'tcell, K, V>),
+}
+
+impl<'tx, 'tcell, K, V> Entry<'tx, 'tcell, K, V>
+where
+    K: Borrow + Clone + Ord + Send + Sync + 'static,
+    V: Borrow + Send + Sync + 'static,
+{
+    #[inline]
+    pub fn or_insert(self, default: V) -> Result<Value<'tx, 'tcell, V>, Error> {
+        self.or_insert_with(move || default)
+    }
+
+    #[inline]
+    pub fn or_insert_with<F: FnOnce() -> V>(
+        self,
+        default: F,
+    ) -> Result<Value<'tx, 'tcell, V>, Error> {
+        Ok(match self {
+            Entry::Occupied(entry) => Value::Shared(entry.into_view()),
+            Entry::Vacant(entry) => Value::Owned(entry.insert(default())?),
+        })
+    }
+
+    #[inline]
+    pub fn or_default(self) -> Result<Value<'tx, 'tcell, V>, Error>
+    where
+        V: Default,
+    {
+        self.or_insert_with(Default::default)
+    }
+
+    #[inline]
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    #[inline]
+    pub fn and_modify<F>(self, f: F) -> Self
+    where
+        F: FnOnce(View<'tcell, V, &mut RwTx<'tcell>>),
+    {
+        match self {
+            Entry::Occupied(OccupiedEntry {
+                location,
+                tree,
+                tx,
+                key,
+            }) => {
+                f(location.value.view(tx));
+                Entry::Occupied(OccupiedEntry {
+                    location,
+                    tree,
+                    tx,
+                    key,
+                })
+            }
+            this => this,
+        }
+    }
+}
+
+pub enum Value<'tx, 'tcell, V> {
+    Owned(&'tx mut V),
+    Shared(View<'tcell, V, &'tx mut RwTx<'tcell>>),
+}
+
+impl<'tx, 'tcell, V: Send + 'static> Value<'tx, 'tcell, V> {
+    pub fn set(&mut self, value: V) -> Result<(), SetError<V>> {
+        match self {
+            Value::Owned(dest) => Ok(**dest = value),
+            Value::Shared(view) => view.set(value),
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use crossbeam_utils::thread;
+use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+use swym_btree::TBTreeMap;
+
+static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Count(usize);
+impl Clone for Count {
+    fn clone(&self) -> Self {
+        Count::new(self.0)
+    }
+}
+impl Count {
+    pub fn new(v: usize) -> Self {
+        COUNT.fetch_add(1, Relaxed);
+        Count(v)
+    }
+}
+
+impl Drop for Count {
+    fn drop(&mut self) {
+        COUNT.fetch_sub(1, Relaxed);
+    }
+}
+
+#[test]
+fn count() {
+    const ITER_COUNT: usize = 100_000;
+    thread::scope(|scope| {
+        scope.spawn(move |_| {
+            let tree = TBTreeMap::new();
+            for elem in 0..ITER_COUNT {
+                tree.atomic(|mut tree| {
+                    tree.entry(elem)?.or_insert(Count::new(0))?;
+                    Ok(())
+                })
+            }
+            swym::thread_key::get().read(|tx| {
+                tree.raw.verify(tx)?;
+                Ok(())
+            });
+        });
+    })
+    .unwrap();
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
+
+#[test]
+fn count_rev() {
+    const ITER_COUNT: usize = 100_000;
+    thread::scope(|scope| {
+        scope.spawn(move |_| {
+            let tree = TBTreeMap::new();
+            for elem in (0..ITER_COUNT).rev() {
+                tree.atomic(|mut tree| {
+                    tree.entry(elem)?.or_insert(Count::new(0))?;
+                    Ok(())
+                })
+            }
+            swym::thread_key::get().read(|tx| {
+                tree.raw.verify(tx)?;
+                Ok(())
+            });
+        });
+    })
+    .unwrap();
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
+
+#[test]
+fn count_remove() {
+    const ITER_COUNT: usize = 100_000;
+    thread::scope(|scope| {
+        scope.spawn(move |_| {
+            let tree = TBTreeMap::new();
+            for elem in 0..ITER_COUNT {
+                tree.atomic(|mut tree| {
+                    tree.insert(elem, Count::new(0))?;
+                    Ok(())
+                })
+            }
+            for elem in 0..ITER_COUNT {
+                tree.remove(&elem).unwrap();
+            }
+            swym::thread_key::get().read(|tx| {
+                tree.raw.verify(tx)?;
+                Ok(())
+            });
+            std::mem::forget(tree);
+        });
+    })
+    .unwrap();
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
+
+#[test]
+fn count_remove_rev() {
+    const ITER_COUNT: usize = 100_000;
+    thread::scope(|scope| {
+        scope.spawn(move |_| {
+            let tree = TBTreeMap::new();
+            for elem in (0..ITER_COUNT).rev() {
+                tree.atomic(|mut tree| {
+                    tree.insert(elem, Count::new(0))?;
+                    Ok(())
+                })
+            }
+            for elem in (0..ITER_COUNT).rev() {
+                tree.remove(&elem);
+            }
+            swym::thread_key::get().read(|tx| {
+                tree.raw.verify(tx)?;
+                Ok(())
+            });
+            std::mem::forget(tree);
+        });
+    })
+    .unwrap();
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
+
+#[test]
+fn range() {
+    const ITER_COUNT: usize = 10_000;
+    thread::scope(|scope| {
+        scope.spawn(move |_| {
+            let tree = TBTreeMap::new();
+            for elem in (0..ITER_COUNT).rev() {
+                tree.insert(elem, Count::new(elem));
+            }
+            let all = tree.range(..);
+            assert_eq!(all.len(), ITER_COUNT);
+            assert!(all
+                .iter()
+                .enumerate()
+                .all(|(i, (k, v))| i == *k && i == v.0));
+            drop(all);
+            let keys: Vec<_> = tree.range(10..20).into_iter().map(|(k, _)| k).collect();
+            assert_eq!(keys, (10..20).collect::<Vec<_>>());
+            let keys: Vec<_> = tree
+                .range(9990..=20000)
+                .into_iter()
+                .map(|(k, _)| k)
+                .collect();
+            assert_eq!(keys, (9990..10000).collect::<Vec<_>>());
+            let keys: Vec<_> = tree.range(..3).into_iter().map(|(k, _)| k).collect();
+            assert_eq!(keys, [0, 1, 2]);
+            assert!(tree.range(20000..).is_empty());
+            swym::thread_key::get().read(|tx| {
+                tree.raw.verify(tx)?;
+                Ok(())
+            });
+        });
+    })
+    .unwrap();
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
+
+#[test]
+fn concurrent() {
+    const ITER_COUNT: usize = 10_000;
+    const THREAD_COUNT: usize = 4;
+    let tree = TBTreeMap::new();
+    thread::scope(|scope| {
+        for thread in 0..THREAD_COUNT {
+            let tree = &tree;
+            scope.spawn(move |_| {
+                for elem in 0..ITER_COUNT {
+                    let key = elem * THREAD_COUNT + thread;
+                    tree.insert(key, Count::new(key));
+                    if elem % 2 == 1 {
+                        let removed = tree.remove(&(key - THREAD_COUNT));
+                        assert_eq!(removed.map(|count| count.0), Some(key - THREAD_COUNT));
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+    let all = tree.range(..);
+    assert_eq!(all.len(), ITER_COUNT * THREAD_COUNT / 2);
+    drop(all);
+    swym::thread_key::get().read(|tx| {
+        tree.raw.verify(tx)?;
+        Ok(())
+    });
+    drop(tree);
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
+
+#[test]
+fn remove_scattered() {
+    const ITER_COUNT: usize = 10_000;
+    thread::scope(|scope| {
+        scope.spawn(move |_| {
+            let tree = TBTreeMap::new();
+            for elem in 0..ITER_COUNT {
+                tree.insert(elem, Count::new(elem));
+            }
+            // 7919 is prime, so this visits every key once, in a scattered order
+            for elem in (0..ITER_COUNT).map(|elem| elem * 7919 % ITER_COUNT) {
+                if elem % 3 != 0 {
+                    assert_eq!(tree.remove(&elem).map(|count| count.0), Some(elem));
+                }
+                if elem % 1000 == 0 {
+                    swym::thread_key::get().read(|tx| {
+                        tree.raw.verify(tx)?;
+                        Ok(())
+                    });
+                }
+            }
+            let keys: Vec<_> = tree.range(..).into_iter().map(|(k, _)| k).collect();
+            assert_eq!(keys, (0..ITER_COUNT).step_by(3).collect::<Vec<_>>());
+            swym::thread_key::get().read(|tx| {
+                tree.raw.verify(tx)?;
+                Ok(())
+            });
+        });
+    })
+    .unwrap();
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
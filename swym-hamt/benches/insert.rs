@@ -0,0 +1,65 @@
+#![feature(test)]
+
+extern crate test;
+
+mod insert {
+    use swym_hamt::THamtMap;
+    use test::Bencher;
+
+    #[bench]
+    fn insert(b: &mut Bencher) {
+        b.iter(|| {
+            let map = THamtMap::new();
+
+            let mut num = 0 as u64;
+            for _ in 0..1_000 {
+                num = num.wrapping_mul(17).wrapping_add(255);
+                map.insert(num, !num);
+            }
+        });
+    }
+
+    #[bench]
+    fn insert_remove(b: &mut Bencher) {
+        crossbeam_utils::thread::scope(|s| {
+            s.spawn(|_| {
+                b.iter(|| {
+                    let map = THamtMap::new();
+
+                    let mut num = 0 as u64;
+                    for _ in 0..1_000 {
+                        num = num.wrapping_mul(17).wrapping_add(255);
+                        map.insert(num, !num);
+                    }
+
+                    let mut num = 0 as u64;
+                    for _ in 0..1_000 {
+                        num = num.wrapping_mul(17).wrapping_add(255);
+                        assert!(map.remove(&num).is_some());
+                    }
+                });
+            });
+        })
+        .unwrap();
+        swym::stats::print_stats();
+    }
+
+    #[bench]
+    fn snapshot_get(b: &mut Bencher) {
+        let map = THamtMap::new();
+        let mut num = 0 as u64;
+        for _ in 0..1_000 {
+            num = num.wrapping_mul(17).wrapping_add(255);
+            map.insert(num, !num);
+        }
+        b.iter(|| {
+            let snapshot = map.snapshot();
+
+            let mut num = 0 as u64;
+            for _ in 0..1_000 {
+                num = num.wrapping_mul(17).wrapping_add(255);
+                assert_eq!(snapshot.get(&num), Some(&!num));
+            }
+        });
+    }
+}
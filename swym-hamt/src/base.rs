@@ -0,0 +1,431 @@
+// persistent hash array mapped trie. nodes are immutable once shared, so updates copy the path
+// from the root to the changed leaf and share everything else with the previous version.
+
+use std::{borrow::Borrow, slice, sync::Arc};
+
+/// Number of hash bits consumed by each level of the trie.
+const BITS: u32 = 5;
+
+const MASK: u64 = (1 << BITS) - 1;
+
+pub enum Node<K, V> {
+    /// Children are stored densely, in the order of their bits in `bitmap`.
+    ///
+    /// A branch is never empty, and a branch with a single child only exists if that child is
+    /// another branch.
+    Branch {
+        bitmap:   u32,
+        children: Vec<Arc<Node<K, V>>>,
+    },
+    Leaf {
+        hash:  u64,
+        key:   K,
+        value: V,
+    },
+    /// Two or more leaves whose keys have the same full hash.
+    Collision {
+        hash:   u64,
+        leaves: Vec<Arc<Node<K, V>>>,
+    },
+}
+
+#[inline]
+fn bit(hash: u64, shift: u32) -> u32 {
+    1 << ((hash >> shift) & MASK)
+}
+
+#[inline]
+fn position(bitmap: u32, bit: u32) -> usize {
+    (bitmap & (bit - 1)).count_ones() as usize
+}
+
+impl<K, V> Node<K, V> {
+    #[inline]
+    fn hash(&self) -> u64 {
+        match self {
+            Node::Leaf { hash, .. } | Node::Collision { hash, .. } => *hash,
+            Node::Branch { .. } => unreachable!(),
+        }
+    }
+
+    #[inline]
+    fn leaf(&self) -> (&K, &V) {
+        match self {
+            Node::Leaf { key, value, .. } => (key, value),
+            _ => unreachable!(),
+        }
+    }
+
+    #[inline]
+    fn is_branch(&self) -> bool {
+        match self {
+            Node::Branch { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Builds the smallest subtree holding two nodes with different hashes.
+    fn pair(shift: u32, a: Arc<Self>, b: Arc<Self>) -> Arc<Self> {
+        let (a_bit, b_bit) = (bit(a.hash(), shift), bit(b.hash(), shift));
+        let children = if a_bit == b_bit {
+            vec![Node::pair(shift + BITS, a, b)]
+        } else if a_bit < b_bit {
+            vec![a, b]
+        } else {
+            vec![b, a]
+        };
+        Arc::new(Node::Branch {
+            bitmap: a_bit | b_bit,
+            children,
+        })
+    }
+
+    /// Returns the new subtree, and the leaf it no longer contains if the key was already present.
+    fn insert(this: &Arc<Self>, shift: u32, leaf: Arc<Self>) -> (Arc<Self>, Option<&Self>)
+    where
+        K: Eq,
+    {
+        match &**this {
+            Node::Branch { bitmap, children } => {
+                let bit = bit(leaf.hash(), shift);
+                let position = position(*bitmap, bit);
+                let mut new_children = children.clone();
+                let replaced = if bitmap & bit == 0 {
+                    new_children.insert(position, leaf);
+                    None
+                } else {
+                    let (child, replaced) = Node::insert(&children[position], shift + BITS, leaf);
+                    new_children[position] = child;
+                    replaced
+                };
+                let branch = Node::Branch {
+                    bitmap:   bitmap | bit,
+                    children: new_children,
+                };
+                (Arc::new(branch), replaced)
+            }
+            Node::Leaf { hash, key, .. } => {
+                if *hash != leaf.hash() {
+                    (Node::pair(shift, this.clone(), leaf), None)
+                } else if key == leaf.leaf().0 {
+                    (leaf, Some(&**this))
+                } else {
+                    let collision = Node::Collision {
+                        hash:   *hash,
+                        leaves: vec![this.clone(), leaf],
+                    };
+                    (Arc::new(collision), None)
+                }
+            }
+            Node::Collision { hash, leaves } => {
+                if *hash != leaf.hash() {
+                    return (Node::pair(shift, this.clone(), leaf), None);
+                }
+                let mut new_leaves = leaves.clone();
+                let replaced = match leaves.iter().position(|l| l.leaf().0 == leaf.leaf().0) {
+                    Some(index) => {
+                        new_leaves[index] = leaf;
+                        Some(&*leaves[index])
+                    }
+                    None => {
+                        new_leaves.push(leaf);
+                        None
+                    }
+                };
+                let collision = Node::Collision {
+                    hash:   *hash,
+                    leaves: new_leaves,
+                };
+                (Arc::new(collision), replaced)
+            }
+        }
+    }
+
+    /// Returns `None` if the key is not present. Otherwise returns the new subtree, if it is not
+    /// empty, and the removed leaf.
+    fn remove<'a, Q>(
+        this: &'a Arc<Self>,
+        shift: u32,
+        hash: u64,
+        key: &Q,
+    ) -> Option<(Option<Arc<Self>>, &'a Self)>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        match &**this {
+            Node::Branch { bitmap, children } => {
+                let bit = bit(hash, shift);
+                if bitmap & bit == 0 {
+                    return None;
+                }
+                let position = position(*bitmap, bit);
+                let (child, removed) = Node::remove(&children[position], shift + BITS, hash, key)?;
+                let mut children = children.clone();
+                let bitmap = match child {
+                    Some(child) => {
+                        children[position] = child;
+                        *bitmap
+                    }
+                    None => {
+                        children.remove(position);
+                        bitmap & !bit
+                    }
+                };
+                let subtree = match children.len() {
+                    0 => None,
+                    1 if !children[0].is_branch() => children.pop(),
+                    _ => Some(Arc::new(Node::Branch { bitmap, children })),
+                };
+                Some((subtree, removed))
+            }
+            Node::Leaf {
+                hash: h, key: k, ..
+            } => {
+                if *h == hash && k.borrow() == key {
+                    Some((None, &**this))
+                } else {
+                    None
+                }
+            }
+            Node::Collision { hash: h, leaves } => {
+                if *h != hash {
+                    return None;
+                }
+                let index = leaves.iter().position(|l| l.leaf().0.borrow() == key)?;
+                let mut new_leaves = leaves.clone();
+                new_leaves.remove(index);
+                let subtree = if new_leaves.len() == 1 {
+                    new_leaves.pop()
+                } else {
+                    Some(Arc::new(Node::Collision {
+                        hash:   *h,
+                        leaves: new_leaves,
+                    }))
+                };
+                Some((subtree, &*leaves[index]))
+            }
+        }
+    }
+
+    /// Returns the number of leaves in the subtree.
+    fn verify<F>(&self, shift: u32, prefix: u64, hash: &F) -> usize
+    where
+        K: Eq,
+        F: Fn(&K) -> u64,
+    {
+        let prefix_mask = 1u64.checked_shl(shift).map_or(!0, |bit| bit - 1);
+        match self {
+            Node::Branch { bitmap, children } => {
+                assert_eq!(bitmap.count_ones() as usize, children.len());
+                assert!(!children.is_empty());
+                assert!(children.len() > 1 || children[0].is_branch());
+                let mut bits = *bitmap;
+                let mut count = 0;
+                for child in children {
+                    let index = bits.trailing_zeros() as u64;
+                    bits &= bits - 1;
+                    count += child.verify(shift + BITS, prefix | (index << shift), hash);
+                }
+                count
+            }
+            Node::Leaf { hash: h, key, .. } => {
+                assert_eq!(*h, hash(key));
+                assert_eq!(h & prefix_mask, prefix);
+                1
+            }
+            Node::Collision { hash: h, leaves } => {
+                assert!(leaves.len() > 1);
+                assert_eq!(h & prefix_mask, prefix);
+                for (i, leaf) in leaves.iter().enumerate() {
+                    let (key, _) = leaf.leaf();
+                    assert_eq!(leaf.hash(), *h);
+                    assert_eq!(hash(key), *h);
+                    assert!(leaves[..i].iter().all(|other| other.leaf().0 != key));
+                }
+                leaves.len()
+            }
+        }
+    }
+}
+
+/// One immutable version of the trie.
+///
+/// Cloning only bumps the reference count of the root node.
+pub struct HamtRoot<K, V> {
+    node: Option<Arc<Node<K, V>>>,
+    len:  usize,
+}
+
+impl<K, V> Clone for HamtRoot<K, V> {
+    #[inline]
+    fn clone(&self) -> Self {
+        HamtRoot {
+            node: self.node.clone(),
+            len:  self.len,
+        }
+    }
+}
+
+impl<K, V> HamtRoot<K, V> {
+    #[inline]
+    pub const fn new() -> Self {
+        HamtRoot {
+            node: None,
+            len:  0,
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn get<Q>(&self, hash: u64, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let mut node = &**self.node.as_ref()?;
+        let mut shift = 0;
+        loop {
+            match node {
+                Node::Branch { bitmap, children } => {
+                    let bit = bit(hash, shift);
+                    if bitmap & bit == 0 {
+                        return None;
+                    }
+                    node = &children[position(*bitmap, bit)];
+                    shift += BITS;
+                }
+                Node::Leaf {
+                    hash: h,
+                    key: k,
+                    value,
+                } => {
+                    return if *h == hash && k.borrow() == key {
+                        Some(value)
+                    } else {
+                        None
+                    }
+                }
+                Node::Collision { hash: h, leaves } => {
+                    if *h != hash {
+                        return None;
+                    }
+                    return leaves
+                        .iter()
+                        .map(|leaf| leaf.leaf())
+                        .find_map(
+                            |(k, value)| {
+                                if k.borrow() == key {
+                                    Some(value)
+                                } else {
+                                    None
+                                }
+                            },
+                        );
+                }
+            }
+        }
+    }
+
+    /// Returns the new version, and the previous value if the key was already present.
+    pub fn insert(&self, hash: u64, key: K, value: V) -> (Self, Option<&V>)
+    where
+        K: Eq,
+    {
+        let leaf = Arc::new(Node::Leaf { hash, key, value });
+        match &self.node {
+            None => {
+                let root = HamtRoot {
+                    node: Some(leaf),
+                    len:  1,
+                };
+                (root, None)
+            }
+            Some(node) => {
+                let (node, replaced) = Node::insert(node, 0, leaf);
+                let root = HamtRoot {
+                    node: Some(node),
+                    len:  self.len + if replaced.is_none() { 1 } else { 0 },
+                };
+                (root, replaced.map(|leaf| leaf.leaf().1))
+            }
+        }
+    }
+
+    /// Returns the new version and the removed value, or `None` if the key is not present.
+    pub fn remove<Q>(&self, hash: u64, key: &Q) -> Option<(Self, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let (node, removed) = Node::remove(self.node.as_ref()?, 0, hash, key)?;
+        let root = HamtRoot {
+            node,
+            len: self.len - 1,
+        };
+        Some((root, removed.leaf().1))
+    }
+
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            stack:     self
+                .node
+                .iter()
+                .map(|node| slice::from_ref(node).iter())
+                .collect(),
+            remaining: self.len,
+        }
+    }
+
+    pub fn verify<F>(&self, hash: F)
+    where
+        K: Eq,
+        F: Fn(&K) -> u64,
+    {
+        let count = self.node.as_ref().map(|node| node.verify(0, 0, &hash));
+        assert_eq!(count.unwrap_or(0), self.len);
+    }
+}
+
+/// An iterator over the entries of a [`HamtSnapshot`], in an unspecified order.
+///
+/// [`HamtSnapshot`]: struct.HamtSnapshot.html
+pub struct Iter<'a, K, V> {
+    stack:     Vec<slice::Iter<'a, Arc<Node<K, V>>>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let node = match self.stack.last_mut()?.next() {
+                Some(node) => node,
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+            match &**node {
+                Node::Branch { children, .. } => self.stack.push(children.iter()),
+                Node::Collision { leaves, .. } => self.stack.push(leaves.iter()),
+                Node::Leaf { key, value, .. } => {
+                    self.remaining -= 1;
+                    return Some((key, value));
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for Iter<'a, K, V> {}
@@ -0,0 +1,413 @@
+#![deny(unused_must_use)]
+
+mod base;
+
+pub use crate::base::Iter;
+
+use crate::base::HamtRoot;
+use std::{
+    collections::hash_map::RandomState,
+    fmt::{self, Debug, Formatter},
+    hash::{BuildHasher, Hash, Hasher},
+};
+use swym::{
+    tcell::{Ref, TCell},
+    thread_key,
+    tx::{Borrow, Error, Ordering, Read, Status},
+    RwTx,
+};
+
+fn hash<S: BuildHasher, Q: Hash + ?Sized>(hash_builder: &S, key: &Q) -> u64 {
+    let mut hasher = hash_builder.build_hasher();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A persistent hash map, stored behind a single `TCell` that every write replaces.
+///
+/// Writers to the same map always conflict with each other, but readers only read one `TCell`,
+/// and [`snapshot`] is O(1).
+///
+/// [`snapshot`]: #method.snapshot
+pub struct THamtMapRaw<K, V, S = RandomState> {
+    root:         TCell<HamtRoot<K, V>>,
+    hash_builder: S,
+}
+
+impl<K, V> THamtMapRaw<K, V, RandomState> {
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+}
+
+impl<K, V> Default for THamtMapRaw<K, V, RandomState> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, S> THamtMapRaw<K, V, S> {
+    pub fn with_hasher(hash_builder: S) -> Self {
+        THamtMapRaw {
+            root: TCell::new(HamtRoot::new()),
+            hash_builder,
+        }
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+}
+
+impl<K, V, S> THamtMapRaw<K, V, S>
+where
+    K: Send + Sync + Hash + Eq + 'static,
+    V: Send + Sync + 'static,
+    S: BuildHasher,
+{
+    pub fn verify<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<(), Error> {
+        let root = self.root.borrow(tx, Ordering::default())?;
+        root.verify(|key| hash(&self.hash_builder, key));
+        Ok(())
+    }
+
+    pub fn len<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<usize, Error> {
+        Ok(self.root.borrow(tx, Ordering::default())?.len())
+    }
+
+    pub fn is_empty<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<bool, Error> {
+        Ok(self.len(tx)? == 0)
+    }
+
+    pub fn contains_key<'tcell, Q>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+        key: &Q,
+    ) -> Result<bool, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let root = self.root.borrow(tx, Ordering::default())?;
+        Ok(root.get(hash(&self.hash_builder, key), key).is_some())
+    }
+
+    /// Returns an immutable copy of the current contents of the map.
+    ///
+    /// The snapshot shares all of its nodes with the map, and can be used from any thread outside
+    /// of a transaction.
+    pub fn snapshot<'tcell>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+    ) -> Result<HamtSnapshot<K, V, S>, Error>
+    where
+        S: Clone,
+    {
+        Ok(HamtSnapshot {
+            root:         self.root.borrow(tx, Ordering::default())?.clone(),
+            hash_builder: self.hash_builder.clone(),
+        })
+    }
+
+    pub fn insert<'tcell>(
+        &'tcell self,
+        tx: &mut RwTx<'tcell>,
+        key: K,
+        value: V,
+    ) -> Result<Option<V>, Error>
+    where
+        V: Clone,
+    {
+        let hash = hash(&self.hash_builder, &key);
+        let (root, result) = {
+            let root = self.root.borrow(tx, Ordering::default())?;
+            let (root, replaced) = root.insert(hash, key, value);
+            (root, replaced.cloned())
+        };
+        self.root.set(tx, root)?;
+        Ok(result)
+    }
+
+    pub fn remove<'tcell, Q>(
+        &'tcell self,
+        tx: &mut RwTx<'tcell>,
+        key: &Q,
+    ) -> Result<Option<V>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        let hash = hash(&self.hash_builder, key);
+        let removed = {
+            let root = self.root.borrow(tx, Ordering::default())?;
+            root.remove(hash, key)
+                .map(|(root, removed)| (root, removed.clone()))
+        };
+        match removed {
+            Some((root, removed)) => {
+                self.root.set(tx, root)?;
+                Ok(Some(removed))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+impl<K, V, S> THamtMapRaw<K, V, S>
+where
+    K: Send + Sync + Hash + Eq + 'static,
+    V: Borrow + Send + Sync + 'static,
+    S: BuildHasher,
+{
+    pub fn get<'tx, 'tcell, Q>(
+        &'tcell self,
+        tx: &'tx impl Read<'tcell>,
+        key: &Q,
+    ) -> Result<Option<Ref<'tx, V>>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let root = self.root.borrow(tx, Ordering::default())?;
+        let value = root.get(hash(&self.hash_builder, key), key);
+        // the root borrowed above is a copy, but the nodes it points to are only freed by the
+        // garbage collector, after the transaction is over
+        Ok(value.map(|value| Ref::from(unsafe { &*(value as *const V) })))
+    }
+}
+
+pub struct THamtMap<K, V, S = RandomState> {
+    pub raw: THamtMapRaw<K, V, S>,
+}
+
+impl<K, V> THamtMap<K, V, RandomState> {
+    pub fn new() -> Self {
+        THamtMap {
+            raw: THamtMapRaw::new(),
+        }
+    }
+}
+
+impl<K, V> Default for THamtMap<K, V, RandomState> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, S> THamtMap<K, V, S> {
+    pub fn with_hasher(hash_builder: S) -> Self {
+        THamtMap {
+            raw: THamtMapRaw::with_hasher(hash_builder),
+        }
+    }
+}
+
+impl<K, V, S> THamtMap<K, V, S>
+where
+    K: Send + Sync + Hash + Eq + 'static,
+    V: Borrow + Send + Sync + 'static,
+    S: BuildHasher,
+{
+    pub fn with<'tx, 'tcell>(
+        &'tcell self,
+        tx: &'tx mut RwTx<'tcell>,
+    ) -> THamtMapWith<'tx, 'tcell, K, V, S> {
+        THamtMapWith { map: &self.raw, tx }
+    }
+
+    pub fn atomic<F, R>(&self, mut f: F) -> R
+    where
+        F: for<'tx, 'tcell> FnMut(THamtMapWith<'tx, 'tcell, K, V, S>) -> Result<R, Status>,
+    {
+        thread_key::get().rw(move |tx| f(self.with(tx)))
+    }
+
+    pub fn len(&self) -> usize {
+        thread_key::get().read(move |tx| Ok(self.raw.len(tx)?))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        thread_key::get().read(move |tx| Ok(self.raw.is_empty(tx)?))
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        thread_key::get().read(move |tx| Ok(self.raw.contains_key(tx, key)?))
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        thread_key::get().read(move |tx| {
+            let r = self.raw.get(tx, key)?.map(|value| value.clone());
+            Ok(r)
+        })
+    }
+
+    /// Returns an immutable copy of the current contents of the map, without blocking writers.
+    pub fn snapshot(&self) -> HamtSnapshot<K, V, S>
+    where
+        S: Clone,
+    {
+        thread_key::get().read(move |tx| Ok(self.raw.snapshot(tx)?))
+    }
+
+    pub fn insert(&self, key: K, value: V) -> Option<V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.atomic(move |mut map| Ok(map.insert(key.clone(), value.clone())?))
+    }
+
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.atomic(move |mut map| Ok(map.remove(key)?))
+    }
+}
+
+pub struct THamtMapWith<'tx, 'tcell, K, V, S = RandomState> {
+    pub map: &'tcell THamtMapRaw<K, V, S>,
+    pub tx:  &'tx mut RwTx<'tcell>,
+}
+
+impl<'tx, 'tcell, K, V, S> THamtMapWith<'tx, 'tcell, K, V, S>
+where
+    K: Send + Sync + Hash + Eq + 'static,
+    V: Borrow + Send + Sync + 'static,
+    S: BuildHasher,
+{
+    pub fn len(&self) -> Result<usize, Error> {
+        self.map.len(self.tx)
+    }
+
+    pub fn is_empty(&self) -> Result<bool, Error> {
+        self.map.is_empty(self.tx)
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Result<Option<Ref<'_, V>>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.get(self.tx, key)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> Result<bool, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.contains_key(self.tx, key)
+    }
+
+    pub fn snapshot(&self) -> Result<HamtSnapshot<K, V, S>, Error>
+    where
+        S: Clone,
+    {
+        self.map.snapshot(self.tx)
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Result<Option<V>, Error>
+    where
+        V: Clone,
+    {
+        self.map.insert(self.tx, key, value)
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Result<Option<V>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.map.remove(self.tx, key)
+    }
+}
+
+/// An immutable version of a [`THamtMap`].
+///
+/// Snapshots don't need a `ThreadKey`, can be sent to and shared between threads, and are never
+/// affected by later writes to the map they were taken from. Cloning a snapshot is O(1).
+///
+/// [`THamtMap`]: struct.THamtMap.html
+pub struct HamtSnapshot<K, V, S = RandomState> {
+    root:         HamtRoot<K, V>,
+    hash_builder: S,
+}
+
+impl<K, V, S: Clone> Clone for HamtSnapshot<K, V, S> {
+    fn clone(&self) -> Self {
+        HamtSnapshot {
+            root:         self.root.clone(),
+            hash_builder: self.hash_builder.clone(),
+        }
+    }
+}
+
+impl<K: Debug, V: Debug, S> Debug for HamtSnapshot<K, V, S> {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V, S> HamtSnapshot<K, V, S> {
+    pub fn len(&self) -> usize {
+        self.root.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    /// Returns an iterator over the entries of the snapshot, in an unspecified order.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        self.root.iter()
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> HamtSnapshot<K, V, S> {
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.root.get(hash(&self.hash_builder, key), key)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    pub fn verify(&self) {
+        self.root.verify(|key| hash(&self.hash_builder, key))
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a HamtSnapshot<K, V, S> {
+    type IntoIter = Iter<'a, K, V>;
+    type Item = (&'a K, &'a V);
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
@@ -0,0 +1,191 @@
+use crossbeam_utils::thread;
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{BuildHasherDefault, Hasher},
+    sync::atomic::{AtomicUsize, Ordering::Relaxed},
+};
+use swym_hamt::THamtMap;
+
+static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Count(usize);
+impl Clone for Count {
+    fn clone(&self) -> Self {
+        Count::new(self.0)
+    }
+}
+impl Count {
+    pub fn new(v: usize) -> Self {
+        COUNT.fetch_add(1, Relaxed);
+        Count(v)
+    }
+}
+
+impl Drop for Count {
+    fn drop(&mut self) {
+        COUNT.fetch_sub(1, Relaxed);
+    }
+}
+
+/// Keeps only the low byte of the default hash, so that most keys collide.
+#[derive(Default)]
+struct ByteHasher(DefaultHasher);
+
+impl Hasher for ByteHasher {
+    fn finish(&self) -> u64 {
+        self.0.finish() & 0xff
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(bytes)
+    }
+}
+
+#[test]
+fn count() {
+    const ITER_COUNT: usize = 100_000;
+    thread::scope(|scope| {
+        scope.spawn(move |_| {
+            let map = THamtMap::new();
+            for elem in 0..ITER_COUNT {
+                map.atomic(|mut map| {
+                    map.insert(elem, Count::new(elem))?;
+                    Ok(())
+                })
+            }
+            assert_eq!(map.len(), ITER_COUNT);
+            for elem in 0..ITER_COUNT {
+                assert_eq!(map.get(&elem).map(|count| count.0), Some(elem));
+            }
+            assert_eq!(map.get(&ITER_COUNT), None);
+            swym::thread_key::get().read(|tx| {
+                map.raw.verify(tx)?;
+                Ok(())
+            });
+        });
+    })
+    .unwrap();
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
+
+#[test]
+fn count_remove() {
+    const ITER_COUNT: usize = 100_000;
+    thread::scope(|scope| {
+        scope.spawn(move |_| {
+            let map = THamtMap::new();
+            for elem in 0..ITER_COUNT {
+                map.atomic(|mut map| {
+                    map.insert(elem, Count::new(0))?;
+                    Ok(())
+                })
+            }
+            for elem in 0..ITER_COUNT {
+                map.remove(&elem).unwrap();
+            }
+            assert!(map.is_empty());
+            assert_eq!(map.remove(&0), None);
+            swym::thread_key::get().read(|tx| {
+                map.raw.verify(tx)?;
+                Ok(())
+            });
+        });
+    })
+    .unwrap();
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
+
+#[test]
+fn collisions() {
+    const ITER_COUNT: usize = 10_000;
+    thread::scope(|scope| {
+        scope.spawn(move |_| {
+            let map = THamtMap::with_hasher(BuildHasherDefault::<ByteHasher>::default());
+            for elem in 0..ITER_COUNT {
+                assert!(map.insert(elem, Count::new(elem)).is_none());
+            }
+            for elem in (0..ITER_COUNT).step_by(2) {
+                let replaced = map.insert(elem, Count::new(elem + 1));
+                assert_eq!(replaced.map(|count| count.0), Some(elem));
+            }
+            swym::thread_key::get().read(|tx| {
+                map.raw.verify(tx)?;
+                Ok(())
+            });
+            let snapshot = map.snapshot();
+            for elem in (0..ITER_COUNT).filter(|elem| elem % 3 != 0) {
+                assert!(map.remove(&elem).is_some());
+            }
+            assert_eq!(map.len(), (ITER_COUNT + 2) / 3);
+            swym::thread_key::get().read(|tx| {
+                map.raw.verify(tx)?;
+                Ok(())
+            });
+            snapshot.verify();
+            assert_eq!(snapshot.len(), ITER_COUNT);
+            for elem in 0..ITER_COUNT {
+                let expected = elem + (elem % 2 == 0) as usize;
+                assert_eq!(snapshot.get(&elem).map(|count| count.0), Some(expected));
+            }
+        });
+    })
+    .unwrap();
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
+
+#[test]
+fn snapshot() {
+    const ITER_COUNT: usize = 10_000;
+    const THREAD_COUNT: usize = 4;
+    let map = THamtMap::new();
+    let snapshots = thread::scope(|scope| {
+        let writer = scope.spawn(|_| {
+            let mut snapshots = Vec::new();
+            for elem in 0..ITER_COUNT {
+                map.insert(elem, Count::new(elem));
+                if elem % 1_000 == 0 {
+                    snapshots.push(map.snapshot());
+                }
+            }
+            snapshots
+        });
+        for _ in 0..THREAD_COUNT {
+            scope.spawn(|_| {
+                let mut last = 0;
+                while last < ITER_COUNT {
+                    let snapshot = map.snapshot();
+                    assert!(snapshot.len() >= last);
+                    last = snapshot.len();
+                    assert_eq!(snapshot.iter().count(), last);
+                    assert!((0..last).all(|elem| snapshot.contains_key(&elem)));
+                    assert!(!snapshot.contains_key(&last));
+                }
+            });
+        }
+        writer.join().unwrap()
+    })
+    .unwrap();
+    // snapshots outlive the map, and are used without a thread key
+    drop(map);
+    thread::scope(|scope| {
+        for (i, snapshot) in snapshots.into_iter().enumerate() {
+            scope.spawn(move |_| {
+                snapshot.verify();
+                assert_eq!(snapshot.len(), i * 1_000 + 1);
+                let mut values = snapshot
+                    .iter()
+                    .map(|(_, count)| count.0)
+                    .collect::<Vec<_>>();
+                values.sort();
+                assert!(values.into_iter().eq(0..=i * 1_000));
+            });
+        }
+    })
+    .unwrap();
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
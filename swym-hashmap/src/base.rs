@@ -0,0 +1,449 @@
+// separate chaining with a bucket table that grows incrementally: while a resize is running, the
+// old table is kept alongside the new one, and inserts and removes move a few of its buckets at a
+// time. The old table is split into ranges, each with its own cursor, so inserts and removes moving
+// buckets in different ranges don't conflict with each other.
+
+use std::{borrow::Borrow, ptr};
+use swym::{
+    tarray::TArray,
+    tptr::TPtr,
+    tx::{Error, Ordering, Read, Rw},
+};
+
+/// Number of buckets in the first table. At least `SHARDS`, so no range of a table being resized is
+/// empty.
+const INITIAL_BUCKETS: usize = 16;
+
+/// Inserting into a chain at least this long checks whether the table needs to grow.
+const GROW_CHAIN_LEN: usize = 4;
+
+/// Maximum average number of nodes per bucket.
+const MAX_LOAD_FACTOR: usize = 2;
+
+/// Number of buckets of the old table moved into the new table by each insert or remove during a
+/// resize.
+const MIGRATE_BUCKETS: usize = 2;
+
+/// Number of independent counters making up the length of the map, and of ranges the old table is
+/// split into during a resize.
+///
+/// Inserts and removes only touch one shard, so they don't all conflict with each other.
+const SHARDS: usize = 16;
+
+pub struct Node<K, V> {
+    next:      TPtr<Node<K, V>>,
+    hash:      u64,
+    pub key:   K,
+    pub value: V,
+}
+
+pub struct Table<K, V> {
+    buckets: Box<[TPtr<Node<K, V>>]>,
+}
+
+impl<K, V> Table<K, V> {
+    fn new(len: usize) -> Box<Self> {
+        debug_assert!(len.is_power_of_two());
+        Box::new(Table {
+            buckets: (0..len).map(|_| TPtr::null()).collect(),
+        })
+    }
+
+    #[inline]
+    fn bucket(&self, hash: u64) -> &TPtr<Node<K, V>> {
+        &self.buckets[hash as usize & (self.buckets.len() - 1)]
+    }
+
+    // frees every node in the table, and the table
+    unsafe fn drop_raw(table: *mut Self) {
+        if table.is_null() {
+            return;
+        }
+        let mut table = Box::from_raw(table);
+        for bucket in table.buckets.iter_mut() {
+            let mut node = *bucket.borrow_mut() as *mut Node<K, V>;
+            while !node.is_null() {
+                let mut owned = Box::from_raw(node);
+                node = *owned.next.borrow_mut() as *mut Node<K, V>;
+            }
+        }
+    }
+}
+
+pub struct VacantLocation<'a, K, V> {
+    table:     Option<&'a Table<K, V>>,
+    hash:      u64,
+    chain_len: usize,
+}
+
+pub enum Location<'a, K, V> {
+    Vacant(VacantLocation<'a, K, V>),
+    Occupied {
+        link: &'a TPtr<Node<K, V>>,
+        node: &'a Node<K, V>,
+    },
+}
+
+pub struct HashRoot<K, V> {
+    table: TPtr<Table<K, V>>,
+    /// The table being moved into `table`, or null if no resize is running.
+    old:   TPtr<Table<K, V>>,
+    /// The number of buckets of each range of `old` that have been moved.
+    moved: TArray<usize>,
+    len:   TArray<isize>,
+}
+
+impl<K, V> HashRoot<K, V> {
+    pub fn new() -> Self {
+        HashRoot {
+            table: TPtr::null(),
+            old:   TPtr::null(),
+            moved: TArray::new(vec![0; SHARDS], SHARDS),
+            len:   TArray::new(vec![0; SHARDS], SHARDS),
+        }
+    }
+}
+
+impl<K, V> Drop for HashRoot<K, V> {
+    fn drop(&mut self) {
+        unsafe {
+            Table::drop_raw(*self.table.borrow_mut() as *mut Table<K, V>);
+            Table::drop_raw(*self.old.borrow_mut() as *mut Table<K, V>);
+        }
+    }
+}
+
+#[inline]
+fn shard(hash: u64) -> usize {
+    // the low bits select the bucket
+    (hash >> 48) as usize % SHARDS
+}
+
+impl<K: Send + Sync + Eq + 'static, V: Send + Sync + 'static> HashRoot<K, V> {
+    fn table<'tcell>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+    ) -> Result<Option<&'tcell Table<K, V>>, Error> {
+        let table = self.table.as_ptr(tx, Ordering::default())?;
+        Ok(unsafe { table.as_ref() })
+    }
+
+    fn old_table<'tcell>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+    ) -> Result<Option<&'tcell Table<K, V>>, Error> {
+        let old = self.old.as_ptr(tx, Ordering::default())?;
+        Ok(unsafe { old.as_ref() })
+    }
+
+    pub fn location<'tcell, Q>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+        hash: u64,
+        key: &Q,
+    ) -> Result<Location<'tcell, K, V>, Error>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let table = match self.table(tx)? {
+            Some(table) => table,
+            None => {
+                return Ok(Location::Vacant(VacantLocation {
+                    table: None,
+                    hash,
+                    chain_len: 0,
+                }))
+            }
+        };
+        match Self::search(tx, table, hash, key)? {
+            // the key may not have been moved out of the old table yet
+            Location::Vacant(vacant) => match self.old_table(tx)? {
+                Some(old) => match Self::search(tx, old, hash, key)? {
+                    Location::Vacant(_) => Ok(Location::Vacant(vacant)),
+                    occupied => Ok(occupied),
+                },
+                None => Ok(Location::Vacant(vacant)),
+            },
+            occupied => Ok(occupied),
+        }
+    }
+
+    fn search<'tcell, Q>(
+        tx: &impl Read<'tcell>,
+        table: &'tcell Table<K, V>,
+        hash: u64,
+        key: &Q,
+    ) -> Result<Location<'tcell, K, V>, Error>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let mut link = table.bucket(hash);
+        let mut chain_len = 0;
+        loop {
+            let node = link.as_ptr(tx, Ordering::default())?;
+            match unsafe { node.as_ref() } {
+                None => {
+                    return Ok(Location::Vacant(VacantLocation {
+                        table: Some(table),
+                        hash,
+                        chain_len,
+                    }))
+                }
+                Some(node) => {
+                    if node.hash == hash && node.key.borrow() == key {
+                        return Ok(Location::Occupied { link, node });
+                    }
+                    link = &node.next;
+                    chain_len += 1;
+                }
+            }
+        }
+    }
+
+    pub fn contains_key<'tcell, Q>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+        hash: u64,
+        key: &Q,
+    ) -> Result<bool, Error>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        Ok(match self.location(tx, hash, key)? {
+            Location::Vacant(..) => false,
+            Location::Occupied { .. } => true,
+        })
+    }
+
+    pub fn insert<'tx, 'tcell>(
+        &'tcell self,
+        tx: &'tx mut impl Rw<'tcell>,
+        key: K,
+        value: V,
+        location: VacantLocation<'tcell, K, V>,
+    ) -> Result<&'tx mut V, Error> {
+        let VacantLocation {
+            table,
+            hash,
+            chain_len,
+        } = location;
+        let table = match table {
+            Some(table) => table,
+            None => {
+                let table = Table::new(INITIAL_BUCKETS);
+                let table_ptr = &*table as *const Table<K, V>;
+                self.table.publish_box(tx, table)?;
+                unsafe { &*table_ptr }
+            }
+        };
+        let bucket = table.bucket(hash);
+        let mut n = Box::new(Node {
+            next: TPtr::new(bucket.as_ptr(tx, Ordering::default())?),
+            hash,
+            key,
+            value,
+        });
+        let n_ptr = &mut *n as *mut Node<K, V>;
+        bucket.publish_box(tx, n)?;
+        self.add_len(tx, hash, 1)?;
+        if chain_len + 1 >= GROW_CHAIN_LEN {
+            self.maybe_grow(tx, table)?;
+        }
+        self.migrate(tx, hash)?;
+        Ok(&mut unsafe { &mut *n_ptr }.value)
+    }
+
+    pub fn remove<'tcell>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        link: &'tcell TPtr<Node<K, V>>,
+        node: &'tcell Node<K, V>,
+    ) -> Result<&'tcell V, Error> {
+        let next = node.next.as_ptr(tx, Ordering::default())?;
+        link.set(tx, next)?;
+        unsafe { TPtr::privatize_as_box(tx, node) };
+        self.add_len(tx, node.hash, -1)?;
+        self.migrate(tx, node.hash)?;
+        Ok(&node.value)
+    }
+
+    fn add_len<'tcell>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        hash: u64,
+        delta: isize,
+    ) -> Result<(), Error> {
+        let shard = shard(hash);
+        let len = self.len.get(tx, shard, Ordering::default())?;
+        Ok(self.len.set(tx, shard, len + delta)?)
+    }
+
+    pub fn len<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<usize, Error> {
+        self.len_ordered(tx, Ordering::default())
+    }
+
+    fn len_ordered<'tcell>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+        ordering: Ordering,
+    ) -> Result<usize, Error> {
+        let mut len = 0;
+        for shard in 0..SHARDS {
+            len += self.len.get(tx, shard, ordering)?;
+        }
+        Ok(len as usize)
+    }
+
+    fn maybe_grow<'tcell>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        table: &'tcell Table<K, V>,
+    ) -> Result<(), Error> {
+        // The length is only a heuristic here. Reading it with `Ordering::Read` avoids conflicting
+        // with every other insert or remove.
+        let len = self.len_ordered(tx, Ordering::Read)?;
+        if len > table.buckets.len() * MAX_LOAD_FACTOR && self.old_table(tx)?.is_none() {
+            self.resize(tx, table, table.buckets.len() * 2)?;
+        }
+        Ok(())
+    }
+
+    /// Starts moving the nodes into a new table with `len` buckets.
+    ///
+    /// The nodes are moved by `migrate`, so no single transaction has to relink the whole map.
+    fn resize<'tcell>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        table: &'tcell Table<K, V>,
+        len: usize,
+    ) -> Result<(), Error> {
+        self.table.publish_box(tx, Table::new(len))?;
+        self.old.set(tx, table)
+    }
+
+    /// Moves the next `MIGRATE_BUCKETS` buckets of the range of the old table selected by `hash`
+    /// into the new table, and frees the old table once every range has been moved.
+    ///
+    /// Nodes are relinked, not reallocated, so references to nodes remain valid.
+    fn migrate<'tcell>(&'tcell self, tx: &mut impl Rw<'tcell>, hash: u64) -> Result<(), Error> {
+        let old = match self.old_table(tx)? {
+            Some(old) => old,
+            None => return Ok(()),
+        };
+        let table = self.table(tx)?.expect("resize running without a table");
+        let shard = shard(hash);
+        let range_len = old.buckets.len() / SHARDS;
+        let moved = self.moved.get(tx, shard, Ordering::default())?;
+        if moved == range_len {
+            return Ok(());
+        }
+        let end = (moved + MIGRATE_BUCKETS).min(range_len);
+        let start = shard * range_len;
+        for bucket in &old.buckets[start + moved..start + end] {
+            let mut node = bucket.as_ptr(tx, Ordering::default())?;
+            if node.is_null() {
+                continue;
+            }
+            bucket.set(tx, ptr::null())?;
+            while let Some(n) = unsafe { node.as_ref() } {
+                let next = n.next.as_ptr(tx, Ordering::default())?;
+                let dest = table.bucket(n.hash);
+                n.next.set(tx, dest.as_ptr(tx, Ordering::default())?)?;
+                dest.set(tx, node)?;
+                node = next;
+            }
+        }
+        self.moved.set(tx, shard, end)?;
+        if end < range_len {
+            return Ok(());
+        }
+        // the last range to be moved frees the old table
+        for other in 0..SHARDS {
+            if self.moved.get(tx, other, Ordering::default())? < range_len {
+                return Ok(());
+            }
+        }
+        for shard in 0..SHARDS {
+            self.moved.set(tx, shard, 0)?;
+        }
+        self.old.set(tx, ptr::null())?;
+        unsafe { TPtr::privatize_as_box(tx, old) };
+        Ok(())
+    }
+
+    // checks all the hash map properties (for debugging)
+    pub fn verify<'tcell, F>(&'tcell self, tx: &impl Read<'tcell>, hash: F) -> Result<(), Error>
+    where
+        F: Fn(&K) -> u64,
+    {
+        let table = match self.table(tx)? {
+            Some(table) => table,
+            None => {
+                assert_eq!(self.len(tx)?, 0);
+                return Ok(());
+            }
+        };
+        let mut count = 0;
+        match self.old_table(tx)? {
+            Some(old) => {
+                let range_len = old.buckets.len() / SHARDS;
+                for (index, bucket) in old.buckets.iter().enumerate() {
+                    let mut node = bucket.as_ptr(tx, Ordering::default())?;
+                    let moved = self.moved.get(tx, index / range_len, Ordering::default())?;
+                    if index % range_len < moved {
+                        assert!(node.is_null());
+                    }
+                    while let Some(n) = unsafe { node.as_ref() } {
+                        // the key must not also be in the new table
+                        let mut other = table.bucket(n.hash).as_ptr(tx, Ordering::default())?;
+                        while let Some(o) = unsafe { other.as_ref() } {
+                            assert!(o.key != n.key);
+                            other = o.next.as_ptr(tx, Ordering::default())?;
+                        }
+                        Self::verify_node(tx, &hash, old, bucket, n)?;
+                        count += 1;
+                        node = n.next.as_ptr(tx, Ordering::default())?;
+                    }
+                }
+            }
+            None => {
+                for shard in 0..SHARDS {
+                    assert_eq!(self.moved.get(tx, shard, Ordering::default())?, 0);
+                }
+            }
+        }
+        for bucket in table.buckets.iter() {
+            let mut node = bucket.as_ptr(tx, Ordering::default())?;
+            while let Some(n) = unsafe { node.as_ref() } {
+                Self::verify_node(tx, &hash, table, bucket, n)?;
+                count += 1;
+                node = n.next.as_ptr(tx, Ordering::default())?;
+            }
+        }
+        assert_eq!(self.len(tx)?, count);
+        Ok(())
+    }
+
+    fn verify_node<'tcell, F>(
+        tx: &impl Read<'tcell>,
+        hash: &F,
+        table: &'tcell Table<K, V>,
+        bucket: &'tcell TPtr<Node<K, V>>,
+        n: &'tcell Node<K, V>,
+    ) -> Result<(), Error>
+    where
+        F: Fn(&K) -> u64,
+    {
+        assert_eq!(n.hash, hash(&n.key));
+        assert!(ptr::eq(table.bucket(n.hash), bucket));
+        let mut other = n.next.as_ptr(tx, Ordering::default())?;
+        while let Some(o) = unsafe { other.as_ref() } {
+            assert!(o.key != n.key);
+            other = o.next.as_ptr(tx, Ordering::default())?;
+        }
+        Ok(())
+    }
+}
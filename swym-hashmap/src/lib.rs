@@ -0,0 +1,484 @@
+#![deny(unused_must_use)]
+
+mod base;
+pub mod lru;
+
+use crate::base::{HashRoot, Location, Node, VacantLocation};
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hash, Hasher},
+};
+use swym::{
+    tcell::{Ref, TCell, View},
+    thread_key,
+    tptr::TPtr,
+    tx::{Borrow, Error, Ordering, Read, SetError, Status},
+    RwTx,
+};
+
+pub struct THashMapRaw<K, V, S = RandomState> {
+    pub root:     HashRoot<K, TCell<V>>,
+    hash_builder: S,
+}
+
+impl<K, V> THashMapRaw<K, V, RandomState> {
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+}
+
+impl<K, V, S> THashMapRaw<K, V, S> {
+    pub fn with_hasher(hash_builder: S) -> Self {
+        THashMapRaw {
+            root: HashRoot::new(),
+            hash_builder,
+        }
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+}
+
+impl<K, V, S: BuildHasher> THashMapRaw<K, V, S> {
+    fn hash<Q: Hash + ?Sized>(&self, key: &Q) -> u64 {
+        let mut hasher = self.hash_builder.build_hasher();
+        key.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl<K, V, S> THashMapRaw<K, V, S>
+where
+    K: Send + Sync + Hash + Eq + 'static,
+    V: Send + Sync + 'static,
+    S: BuildHasher,
+{
+    pub fn verify<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<(), Error> {
+        self.root.verify(tx, |key| self.hash(key))
+    }
+
+    pub fn len<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<usize, Error> {
+        self.root.len(tx)
+    }
+
+    pub fn is_empty<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<bool, Error> {
+        Ok(self.len(tx)? == 0)
+    }
+
+    pub fn contains_key<'tcell, Q>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+        key: &Q,
+    ) -> Result<bool, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.root.contains_key(tx, self.hash(key), key)
+    }
+
+    pub fn entry<'tx, 'tcell>(
+        &'tcell self,
+        tx: &'tx mut RwTx<'tcell>,
+        key: K,
+    ) -> Result<Entry<'tx, 'tcell, K, V, S>, Error> {
+        Ok(match self.root.location(tx, self.hash(&key), &key)? {
+            Location::Vacant(location) => Entry::Vacant(VacantEntry {
+                location,
+                map: self,
+                tx,
+                key,
+            }),
+            Location::Occupied { link, node } => Entry::Occupied(OccupiedEntry {
+                link,
+                node,
+                map: self,
+                tx,
+                key,
+            }),
+        })
+    }
+}
+
+impl<K, V, S> THashMapRaw<K, V, S>
+where
+    K: Send + Sync + Hash + Eq + 'static,
+    V: Borrow + Send + Sync + 'static,
+    S: BuildHasher,
+{
+    pub fn get<'tx, 'tcell, Q>(
+        &'tcell self,
+        tx: &'tx impl Read<'tcell>,
+        key: &Q,
+    ) -> Result<Option<Ref<'tx, V>>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let loc = self.root.location(tx, self.hash(key), key)?;
+        let result = match loc {
+            Location::Vacant(_) => None,
+            Location::Occupied { node, .. } => Some(node.value.borrow(tx, Ordering::default())?),
+        };
+        Ok(result)
+    }
+
+    pub fn get_mut<'tx, 'tcell, Q>(
+        &'tcell self,
+        tx: &'tx mut RwTx<'tcell>,
+        key: &Q,
+    ) -> Result<Option<View<'tcell, V, &'tx mut RwTx<'tcell>>>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let loc = self.root.location(tx, self.hash(key), key)?;
+        let result = match loc {
+            Location::Vacant(_) => None,
+            Location::Occupied { node, .. } => Some(node.value.view(tx)),
+        };
+        Ok(result)
+    }
+
+    pub fn insert<'tcell>(
+        &'tcell self,
+        tx: &mut RwTx<'tcell>,
+        key: K,
+        value: V,
+    ) -> Result<Option<V>, Error>
+    where
+        V: Clone,
+    {
+        let loc = self.root.location(tx, self.hash(&key), &key)?;
+        let result = match loc {
+            Location::Vacant(vacant) => {
+                self.root.insert(tx, key, TCell::new(value), vacant)?;
+                None
+            }
+            Location::Occupied { node, .. } => Some(node.value.replace(tx, value)?),
+        };
+        Ok(result)
+    }
+
+    pub fn remove<'tcell, Q>(
+        &'tcell self,
+        tx: &mut RwTx<'tcell>,
+        key: &Q,
+    ) -> Result<Option<Ref<'tcell, V>>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let loc = self.root.location(tx, self.hash(key), key)?;
+        match loc {
+            Location::Vacant(..) => Ok(None),
+            Location::Occupied { link, node } => {
+                let v = self.root.remove(tx, link, node)?;
+                Ok(Some(unsafe {
+                    Ref::downcast(v.borrow(tx, Ordering::default())?, tx)
+                }))
+            }
+        }
+    }
+}
+
+pub struct THashMap<K, V, S = RandomState> {
+    pub raw: THashMapRaw<K, V, S>,
+}
+
+impl<K, V> THashMap<K, V, RandomState> {
+    pub fn new() -> Self {
+        THashMap {
+            raw: THashMapRaw::new(),
+        }
+    }
+}
+
+impl<K, V> Default for THashMap<K, V, RandomState> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, S> THashMap<K, V, S> {
+    pub fn with_hasher(hash_builder: S) -> Self {
+        THashMap {
+            raw: THashMapRaw::with_hasher(hash_builder),
+        }
+    }
+}
+
+impl<K, V, S> THashMap<K, V, S>
+where
+    K: Send + Sync + Hash + Eq + 'static,
+    V: Borrow + Send + Sync + 'static,
+    S: BuildHasher,
+{
+    pub fn with<'tx, 'tcell>(
+        &'tcell self,
+        tx: &'tx mut RwTx<'tcell>,
+    ) -> THashMapWith<'tx, 'tcell, K, V, S> {
+        THashMapWith { map: &self.raw, tx }
+    }
+
+    pub fn atomic<F, R>(&self, mut f: F) -> R
+    where
+        F: for<'tx, 'tcell> FnMut(THashMapWith<'tx, 'tcell, K, V, S>) -> Result<R, Status>,
+    {
+        thread_key::get().rw(move |tx| f(self.with(tx)))
+    }
+
+    pub fn len(&self) -> usize {
+        thread_key::get().read(move |tx| Ok(self.raw.len(tx)?))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        thread_key::get().read(move |tx| Ok(self.raw.is_empty(tx)?))
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        thread_key::get().read(move |tx| Ok(self.raw.contains_key(tx, key)?))
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        thread_key::get().read(move |tx| {
+            let r = self.raw.get(tx, key)?.map(|value| value.clone());
+            Ok(r)
+        })
+    }
+
+    pub fn insert(&self, key: K, value: V) -> Option<V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.atomic(move |mut map| Ok(map.insert(key.clone(), value.clone())?))
+    }
+
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.atomic(move |mut map| {
+            let value = map.remove(key)?;
+            Ok(value.map(|value| value.clone()))
+        })
+    }
+}
+
+pub struct THashMapWith<'tx, 'tcell, K, V, S = RandomState> {
+    pub map: &'tcell THashMapRaw<K, V, S>,
+    pub tx:  &'tx mut RwTx<'tcell>,
+}
+
+impl<'tx, 'tcell, K, V, S> THashMapWith<'tx, 'tcell, K, V, S>
+where
+    K: Send + Sync + Hash + Eq + 'static,
+    V: Borrow + Send + Sync + 'static,
+    S: BuildHasher,
+{
+    pub fn len(&self) -> Result<usize, Error> {
+        self.map.len(self.tx)
+    }
+
+    pub fn is_empty(&self) -> Result<bool, Error> {
+        self.map.is_empty(self.tx)
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Result<Option<Ref<'_, V>>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.get(self.tx, key)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> Result<bool, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.contains_key(self.tx, key)
+    }
+
+    pub fn entry<'a>(&'a mut self, key: K) -> Result<Entry<'a, 'tcell, K, V, S>, Error> {
+        self.map.entry(self.tx, key)
+    }
+
+    pub fn insert<'a>(&'a mut self, key: K, value: V) -> Result<Option<V>, Error>
+    where
+        V: Clone,
+    {
+        self.map.insert(self.tx, key, value)
+    }
+
+    pub fn remove<'a, Q>(&'a mut self, key: &Q) -> Result<Option<Ref<'tx, V>>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.remove(self.tx, key)
+    }
+}
+
+pub struct VacantEntry<'tx, 'tcell, K, V, S = RandomState> {
+    location: VacantLocation<'tcell, K, TCell<V>>,
+    map:      &'tcell THashMapRaw<K, V, S>,
+    tx:       &'tx mut RwTx<'tcell>,
+    key:      K,
+}
+
+impl<'tx, 'tcell, K, V, S> VacantEntry<'tx, 'tcell, K, V, S>
+where
+    K: Send + Sync + Hash + Eq + 'static,
+    V: Borrow + Send + Sync + 'static,
+{
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    pub fn insert(self, value: V) -> Result<&'tx mut V, Error> {
+        let value = self
+            .map
+            .root
+            .insert(self.tx, self.key, TCell::new(value), self.location)?;
+        Ok(value.borrow_mut())
+    }
+}
+
+pub struct OccupiedEntry<'tx, 'tcell, K, V, S = RandomState> {
+    link: &'tcell TPtr<Node<K, TCell<V>>>,
+    node: &'tcell Node<K, TCell<V>>,
+    map:  &'tcell THashMapRaw<K, V, S>,
+    tx:   &'tx mut RwTx<'tcell>,
+    key:  K,
+}
+
+impl<'tx, 'tcell, K, V, S> OccupiedEntry<'tx, 'tcell, K, V, S>
+where
+    K: Send + Sync + Hash + Eq + 'static,
+    V: Borrow + Send + Sync + 'static,
+{
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn view<'a>(&'a mut self) -> View<'tcell, V, &'a mut RwTx<'tcell>> {
+        self.node.value.view(self.tx)
+    }
+
+    pub fn into_view(self) -> View<'tcell, V, &'tx mut RwTx<'tcell>> {
+        self.node.value.view(self.tx)
+    }
+
+    pub fn insert(&mut self, value: V) -> Result<V, Error>
+    where
+        V: Clone,
+    {
+        Ok(self.node.value.replace(self.tx, value)?)
+    }
+
+    pub fn remove(self) -> Result<Ref<'tx, V>, Error> {
+        let value = self.map.root.remove(self.tx, self.link, self.node)?;
+        value.borrow(self.tx, Ordering::default())
+    }
+}
+
+pub enum Entry<'tx, 'tcell, K, V, S = RandomState> {
+    Vacant(VacantEntry<'tx, 'tcell, K, V, S>),
+    Occupied(OccupiedEntry<'tx, 'tcell, K, V, S>),
+}
+
+impl<'tx, 'tcell, K, V, S> Entry<'tx, 'tcell, K, V, S>
+where
+    K: Send + Sync + Hash + Eq + 'static,
+    V: Borrow + Send + Sync + 'static,
+{
+    #[inline]
+    pub fn or_insert(self, default: V) -> Result<Value<'tx, 'tcell, V>, Error> {
+        self.or_insert_with(move || default)
+    }
+
+    #[inline]
+    pub fn or_insert_with<F: FnOnce() -> V>(
+        self,
+        default: F,
+    ) -> Result<Value<'tx, 'tcell, V>, Error> {
+        Ok(match self {
+            Entry::Occupied(entry) => Value::Shared(entry.into_view()),
+            Entry::Vacant(entry) => Value::Owned(entry.insert(default())?),
+        })
+    }
+
+    #[inline]
+    pub fn or_default(self) -> Result<Value<'tx, 'tcell, V>, Error>
+    where
+        V: Default,
+    {
+        self.or_insert_with(Default::default)
+    }
+
+    #[inline]
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    #[inline]
+    pub fn and_modify<F>(self, f: F) -> Self
+    where
+        F: FnOnce(View<'tcell, V, &mut RwTx<'tcell>>),
+    {
+        match self {
+            Entry::Occupied(OccupiedEntry {
+                link,
+                node,
+                map,
+                tx,
+                key,
+            }) => {
+                f(node.value.view(tx));
+                Entry::Occupied(OccupiedEntry {
+                    link,
+                    node,
+                    map,
+                    tx,
+                    key,
+                })
+            }
+            this => this,
+        }
+    }
+}
+
+pub enum Value<'tx, 'tcell, V> {
+    Owned(&'tx mut V),
+    Shared(View<'tcell, V, &'tx mut RwTx<'tcell>>),
+}
+
+impl<'tx, 'tcell, V: Send + 'static> Value<'tx, 'tcell, V> {
+    pub fn set(&mut self, value: V) -> Result<(), SetError<V>> {
+        match self {
+            Value::Owned(dest) => Ok(**dest = value),
+            Value::Shared(view) => view.set(value),
+        }
+    }
+}
@@ -0,0 +1,333 @@
+// a hash index from keys to the nodes of a doubly linked list ordered by recency of use
+
+use crate::THashMapRaw;
+use std::{
+    collections::hash_map::RandomState,
+    fmt::{self, Debug, Formatter},
+    hash::{BuildHasher, Hash},
+    ptr,
+};
+use swym::{
+    tcell::Ref,
+    tptr::TPtr,
+    tx::{Borrow, Error, Ordering, Read},
+    RwTx,
+};
+
+struct Node<K, V> {
+    prev:  TPtr<Node<K, V>>,
+    next:  TPtr<Node<K, V>>,
+    key:   K,
+    value: V,
+}
+
+/// The index's pointer to a node of the recency list.
+struct Link<K, V>(*const Node<K, V>);
+
+impl<K, V> Clone for Link<K, V> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Link(self.0)
+    }
+}
+
+unsafe impl<K: Send + Sync, V: Send + Sync> Send for Link<K, V> {}
+unsafe impl<K: Send + Sync, V: Send + Sync> Sync for Link<K, V> {}
+unsafe impl<K, V> Borrow for Link<K, V> {}
+
+/// The key and value of an entry removed by [`TLruCache::insert`].
+///
+/// [`TLruCache::insert`]: struct.TLruCache.html#method.insert
+pub type Evicted<'tx, K, V> = (Ref<'tx, K>, Ref<'tx, V>);
+
+/// A transactional cache holding at most `capacity` entries, evicting the least recently used entry
+/// to make room for new ones.
+///
+/// Every lookup through [`get`] moves the entry to the front of the recency list, so concurrent
+/// lookups of different entries conflict with each other. [`get_ordered`] with `Ordering::Read`
+/// avoids this for entries that are already the most recently used, and [`peek`] doesn't touch the
+/// recency list at all.
+///
+/// [`get`]: #method.get
+/// [`get_ordered`]: #method.get_ordered
+/// [`peek`]: #method.peek
+pub struct TLruCache<K, V, S = RandomState> {
+    index:    THashMapRaw<K, Link<K, V>, S>,
+    // most recently used
+    head:     TPtr<Node<K, V>>,
+    // least recently used
+    tail:     TPtr<Node<K, V>>,
+    capacity: usize,
+}
+
+impl<K, V, S> Debug for TLruCache<K, V, S> {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("TLruCache")
+            .field("capacity", &self.capacity)
+            .field("entries", &"...")
+            .finish()
+    }
+}
+
+impl<K, V, S> Drop for TLruCache<K, V, S> {
+    fn drop(&mut self) {
+        let mut node = *self.head.borrow_mut() as *mut Node<K, V>;
+        while !node.is_null() {
+            let mut owned = unsafe { Box::from_raw(node) };
+            node = *owned.next.borrow_mut() as *mut Node<K, V>;
+        }
+    }
+}
+
+impl<K, V> TLruCache<K, V, RandomState> {
+    pub fn new(capacity: usize) -> Self {
+        Self::with_hasher(capacity, RandomState::new())
+    }
+}
+
+impl<K, V, S> TLruCache<K, V, S> {
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn with_hasher(capacity: usize, hash_builder: S) -> Self {
+        assert!(capacity > 0, "`TLruCache` requires a nonzero capacity");
+        TLruCache {
+            index: THashMapRaw::with_hasher(hash_builder),
+            head: TPtr::null(),
+            tail: TPtr::null(),
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn hasher(&self) -> &S {
+        self.index.hasher()
+    }
+}
+
+impl<K, V, S> TLruCache<K, V, S>
+where
+    K: Clone + Send + Sync + Hash + Eq + 'static,
+    V: Send + Sync + 'static,
+    S: BuildHasher,
+{
+    pub fn verify<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<(), Error> {
+        self.index.verify(tx)?;
+        let mut count = 0;
+        let mut prev = ptr::null();
+        let mut node = self.head.as_ptr(tx, Ordering::default())?;
+        while let Some(n) = unsafe { node.as_ref() } {
+            assert_eq!(n.prev.as_ptr(tx, Ordering::default())?, prev);
+            let link = self
+                .index
+                .get(tx, &n.key)?
+                .expect("list node missing from the index");
+            assert_eq!(link.0, node);
+            count += 1;
+            prev = node;
+            node = n.next.as_ptr(tx, Ordering::default())?;
+        }
+        assert_eq!(self.tail.as_ptr(tx, Ordering::default())?, prev);
+        assert_eq!(self.index.len(tx)?, count);
+        assert!(count <= self.capacity);
+        Ok(())
+    }
+
+    pub fn len<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<usize, Error> {
+        self.index.len(tx)
+    }
+
+    pub fn is_empty<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<bool, Error> {
+        self.index.is_empty(tx)
+    }
+
+    /// Returns true if the cache contains the key, without marking it as recently used.
+    pub fn contains_key<'tcell, Q>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+        key: &Q,
+    ) -> Result<bool, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.index.contains_key(tx, key)
+    }
+
+    fn node<'tcell, Q>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+        key: &Q,
+    ) -> Result<Option<&'tcell Node<K, V>>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        Ok(self.index.get(tx, key)?.map(|link| unsafe { &*link.0 }))
+    }
+
+    fn unlink<'tcell>(
+        &'tcell self,
+        tx: &mut RwTx<'tcell>,
+        node: &'tcell Node<K, V>,
+    ) -> Result<(), Error> {
+        let prev = node.prev.as_ptr(tx, Ordering::default())?;
+        let next = node.next.as_ptr(tx, Ordering::default())?;
+        match unsafe { prev.as_ref() } {
+            Some(prev) => prev.next.set(tx, next)?,
+            None => self.head.set(tx, next)?,
+        }
+        match unsafe { next.as_ref() } {
+            Some(next) => next.prev.set(tx, prev)?,
+            None => self.tail.set(tx, prev)?,
+        }
+        Ok(())
+    }
+
+    /// Makes `node`, which must not already be in the list, the most recently used node.
+    fn link_front<'tcell>(
+        &'tcell self,
+        tx: &mut RwTx<'tcell>,
+        node: &'tcell Node<K, V>,
+    ) -> Result<(), Error> {
+        let head = self.head.as_ptr(tx, Ordering::default())?;
+        node.prev.set(tx, ptr::null())?;
+        node.next.set(tx, head)?;
+        self.head.set(tx, node)?;
+        match unsafe { head.as_ref() } {
+            Some(head) => head.prev.set(tx, node),
+            None => self.tail.set(tx, node),
+        }
+    }
+
+    /// Removes `node` from the list and the index, freeing it once the transaction commits.
+    fn evict<'tcell>(
+        &'tcell self,
+        tx: &mut RwTx<'tcell>,
+        node: &'tcell Node<K, V>,
+    ) -> Result<(), Error> {
+        self.unlink(tx, node)?;
+        let _ = self.index.remove(tx, &node.key)?;
+        unsafe { TPtr::privatize_as_box(tx, node) };
+        Ok(())
+    }
+}
+
+impl<K, V, S> TLruCache<K, V, S>
+where
+    K: Borrow + Clone + Send + Sync + Hash + Eq + 'static,
+    V: Borrow + Send + Sync + 'static,
+    S: BuildHasher,
+{
+    /// Returns the value for the key without marking it as recently used.
+    pub fn peek<'tx, 'tcell, Q>(
+        &'tcell self,
+        tx: &'tx impl Read<'tcell>,
+        key: &Q,
+    ) -> Result<Option<Ref<'tx, V>>, Error>
+    where
+        'tcell: 'tx,
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        Ok(self.node(tx, key)?.map(|node| Ref::from(&node.value)))
+    }
+
+    /// Returns the value for the key, and marks it as the most recently used entry.
+    pub fn get<'tx, 'tcell, Q>(
+        &'tcell self,
+        tx: &'tx mut RwTx<'tcell>,
+        key: &Q,
+    ) -> Result<Option<Ref<'tx, V>>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_ordered(tx, key, Ordering::default())
+    }
+
+    /// Returns the value for the key, and marks it as the most recently used entry.
+    ///
+    /// `ordering` applies to the check of whether the entry already is the most recently used.
+    /// With `Ordering::Read`, lookups of the most recently used entry don't write anything, and
+    /// don't conflict with other threads reordering the cache. The recency list is still only
+    /// modified consistently.
+    pub fn get_ordered<'tx, 'tcell, Q>(
+        &'tcell self,
+        tx: &'tx mut RwTx<'tcell>,
+        key: &Q,
+        ordering: Ordering,
+    ) -> Result<Option<Ref<'tx, V>>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let node = match self.node(tx, key)? {
+            Some(node) => node,
+            None => return Ok(None),
+        };
+        if !ptr::eq(self.head.as_ptr(tx, ordering)?, node) {
+            self.unlink(tx, node)?;
+            self.link_front(tx, node)?;
+        }
+        Ok(Some(Ref::from(&node.value)))
+    }
+
+    /// Inserts an entry as the most recently used entry.
+    ///
+    /// Returns the entry that was removed to make room for it, if any. That is either the previous
+    /// entry with the same key, or the least recently used entry if the cache was full.
+    pub fn insert<'tx, 'tcell>(
+        &'tcell self,
+        tx: &'tx mut RwTx<'tcell>,
+        key: K,
+        value: V,
+    ) -> Result<Option<Evicted<'tx, K, V>>, Error> {
+        let evicted = match self.node(tx, &key)? {
+            Some(node) => Some(node),
+            None if self.index.len(tx)? >= self.capacity => unsafe {
+                self.tail.as_ptr(tx, Ordering::default())?.as_ref()
+            },
+            None => None,
+        };
+        if let Some(node) = evicted {
+            self.evict(tx, node)?;
+        }
+        let head = self.head.as_ptr(tx, Ordering::default())?;
+        let node = Box::new(Node {
+            prev: TPtr::null(),
+            next: TPtr::new(head),
+            key: key.clone(),
+            value,
+        });
+        let node_ptr = &*node as *const Node<K, V>;
+        self.head.publish_box(tx, node)?;
+        match unsafe { head.as_ref() } {
+            Some(head) => head.prev.set(tx, node_ptr)?,
+            None => self.tail.set(tx, node_ptr)?,
+        }
+        let _ = self.index.insert(tx, key, Link(node_ptr))?;
+        Ok(evicted.map(|node| (Ref::from(&node.key), Ref::from(&node.value))))
+    }
+
+    pub fn remove<'tx, 'tcell, Q>(
+        &'tcell self,
+        tx: &'tx mut RwTx<'tcell>,
+        key: &Q,
+    ) -> Result<Option<Ref<'tx, V>>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let node = match self.node(tx, key)? {
+            Some(node) => node,
+            None => return Ok(None),
+        };
+        self.evict(tx, node)?;
+        Ok(Some(Ref::from(&node.value)))
+    }
+}
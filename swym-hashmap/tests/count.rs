@@ -0,0 +1,151 @@
+use crossbeam_utils::thread;
+use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+use swym_hashmap::THashMap;
+
+static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Count(usize);
+impl Clone for Count {
+    fn clone(&self) -> Self {
+        Count::new(self.0)
+    }
+}
+impl Count {
+    pub fn new(v: usize) -> Self {
+        COUNT.fetch_add(1, Relaxed);
+        Count(v)
+    }
+}
+
+impl Drop for Count {
+    fn drop(&mut self) {
+        COUNT.fetch_sub(1, Relaxed);
+    }
+}
+
+#[test]
+fn count() {
+    const ITER_COUNT: usize = 100_000;
+    thread::scope(|scope| {
+        scope.spawn(move |_| {
+            let map = THashMap::new();
+            for elem in 0..ITER_COUNT {
+                map.atomic(|mut map| {
+                    map.entry(elem)?.or_insert(Count::new(0))?;
+                    Ok(())
+                })
+            }
+            assert_eq!(map.len(), ITER_COUNT);
+            swym::thread_key::get().read(|tx| {
+                map.raw.verify(tx)?;
+                Ok(())
+            });
+        });
+    })
+    .unwrap();
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
+
+#[test]
+fn count_remove() {
+    const ITER_COUNT: usize = 100_000;
+    thread::scope(|scope| {
+        scope.spawn(move |_| {
+            let map = THashMap::new();
+            for elem in 0..ITER_COUNT {
+                map.atomic(|mut map| {
+                    map.insert(elem, Count::new(0))?;
+                    Ok(())
+                })
+            }
+            for elem in 0..ITER_COUNT {
+                map.remove(&elem).unwrap();
+            }
+            assert!(map.is_empty());
+            swym::thread_key::get().read(|tx| {
+                map.raw.verify(tx)?;
+                Ok(())
+            });
+            std::mem::forget(map);
+        });
+    })
+    .unwrap();
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
+
+#[test]
+fn count_concurrent() {
+    const ITER_COUNT: usize = 10_000;
+    const THREAD_COUNT: usize = 4;
+    let map = THashMap::new();
+    thread::scope(|scope| {
+        for thread in 0..THREAD_COUNT {
+            let map = &map;
+            scope.spawn(move |_| {
+                for elem in 0..ITER_COUNT {
+                    let key = elem * THREAD_COUNT + thread;
+                    map.atomic(|mut map| {
+                        map.entry(key)?.or_insert(Count::new(key))?;
+                        Ok(())
+                    });
+                    if elem % 2 == 1 {
+                        let removed = map.remove(&(key - THREAD_COUNT));
+                        assert_eq!(removed.map(|count| count.0), Some(key - THREAD_COUNT));
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+    assert_eq!(map.len(), ITER_COUNT * THREAD_COUNT / 2);
+    swym::thread_key::get().read(|tx| {
+        map.raw.verify(tx)?;
+        Ok(())
+    });
+    drop(map);
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
+
+#[test]
+fn count_lookup_while_growing() {
+    const ITER_COUNT: usize = 20_000;
+    let map = THashMap::new();
+    thread::scope(|scope| {
+        let map = &map;
+        scope.spawn(move |_| {
+            for elem in 0..ITER_COUNT {
+                map.insert(elem, Count::new(elem));
+            }
+        });
+        scope.spawn(move |_| loop {
+            // keys are inserted in order, so every key below the length is present, no matter
+            // which table it is in
+            let len = swym::thread_key::get().read(|tx| {
+                let len = map.raw.len(tx)?;
+                for key in [0, len / 3, len / 2, len.saturating_sub(1)].iter() {
+                    if *key < len {
+                        assert_eq!(map.raw.get(tx, key)?.map(|count| count.0), Some(*key));
+                    }
+                }
+                assert!(!map.raw.contains_key(tx, &len)?);
+                Ok(len)
+            });
+            if len == ITER_COUNT {
+                break;
+            }
+        });
+    })
+    .unwrap();
+    assert_eq!(map.len(), ITER_COUNT);
+    swym::thread_key::get().read(|tx| {
+        map.raw.verify(tx)?;
+        Ok(())
+    });
+    drop(map);
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
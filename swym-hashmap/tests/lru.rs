@@ -0,0 +1,136 @@
+use crossbeam_utils::thread;
+use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+use swym::{thread_key, tx::Ordering};
+use swym_hashmap::lru::TLruCache;
+
+static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Count(usize);
+impl Count {
+    pub fn new(v: usize) -> Self {
+        COUNT.fetch_add(1, Relaxed);
+        Count(v)
+    }
+}
+
+impl Drop for Count {
+    fn drop(&mut self) {
+        COUNT.fetch_sub(1, Relaxed);
+    }
+}
+
+#[test]
+fn evict_order() {
+    let cache = TLruCache::new(3);
+    let thread_key = thread_key::get();
+    let evicted = thread_key.rw(|tx| {
+        let mut evicted = Vec::new();
+        for key in 0..5 {
+            evicted.push(cache.insert(tx, key, key * 10)?.map(|(k, v)| (*k, *v)));
+        }
+        Ok(evicted)
+    });
+    assert_eq!(evicted, [None, None, None, Some((0, 0)), Some((1, 10))]);
+
+    // 2 becomes the most recently used, so 3 is the next to go
+    assert_eq!(
+        thread_key.rw(|tx| Ok(cache.get(tx, &2)?.map(|v| *v))),
+        Some(20)
+    );
+    let evicted = thread_key.rw(|tx| Ok(cache.insert(tx, 5, 50)?.map(|(k, v)| (*k, *v))));
+    assert_eq!(evicted, Some((3, 30)));
+
+    // peek doesn't bump 4
+    assert_eq!(
+        thread_key.read(|tx| Ok(cache.peek(tx, &4)?.map(|v| *v))),
+        Some(40)
+    );
+    let evicted = thread_key.rw(|tx| Ok(cache.insert(tx, 6, 60)?.map(|(k, v)| (*k, *v))));
+    assert_eq!(evicted, Some((4, 40)));
+
+    // replacing a value returns the old entry, and evicts nothing else
+    let replaced = thread_key.rw(|tx| Ok(cache.insert(tx, 2, 21)?.map(|(k, v)| (*k, *v))));
+    assert_eq!(replaced, Some((2, 20)));
+    assert_eq!(
+        thread_key.rw(|tx| Ok(cache.remove(tx, &5)?.map(|v| *v))),
+        Some(50)
+    );
+    assert_eq!(
+        thread_key.rw(|tx| Ok(cache.remove(tx, &5)?.map(|v| *v))),
+        None
+    );
+    thread_key.read(|tx| {
+        assert_eq!(cache.len(tx)?, 2);
+        assert!(cache.contains_key(tx, &2)?);
+        assert!(cache.contains_key(tx, &6)?);
+        cache.verify(tx)?;
+        Ok(())
+    });
+}
+
+#[test]
+fn count() {
+    const CAPACITY: usize = 1_000;
+    const ITER_COUNT: usize = 100_000;
+    thread::scope(|scope| {
+        scope.spawn(move |_| {
+            let cache = TLruCache::new(CAPACITY);
+            let thread_key = thread_key::get();
+            for elem in 0..ITER_COUNT {
+                let evicted = thread_key.rw(|tx| {
+                    let evicted = cache.insert(tx, elem, Count::new(elem))?;
+                    Ok(evicted.map(|(key, value)| (*key, value.0)))
+                });
+                let expected = elem.checked_sub(CAPACITY);
+                assert_eq!(evicted, expected.map(|key| (key, key)));
+            }
+            thread_key.read(|tx| {
+                assert_eq!(cache.len(tx)?, CAPACITY);
+                cache.verify(tx)?;
+                Ok(())
+            });
+        });
+    })
+    .unwrap();
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
+
+#[test]
+fn concurrent() {
+    const CAPACITY: usize = 64;
+    const ITER_COUNT: usize = 10_000;
+    const THREAD_COUNT: usize = 4;
+    let cache = TLruCache::new(CAPACITY);
+    thread::scope(|scope| {
+        for thread in 0..THREAD_COUNT {
+            let cache = &cache;
+            scope.spawn(move |_| {
+                let thread_key = thread_key::get();
+                for elem in 0..ITER_COUNT {
+                    let key = (elem * 7 + thread) % (CAPACITY * 2);
+                    thread_key.rw(|tx| {
+                        let hit = cache.get_ordered(tx, &key, Ordering::Read)?.is_some();
+                        if !hit {
+                            let _ = cache.insert(tx, key, Count::new(key))?;
+                        }
+                        Ok(())
+                    });
+                    if elem % 3 == 0 {
+                        thread_key.rw(|tx| Ok(cache.remove(tx, &key)?.map(|value| value.0)));
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+    thread_key::get().read(|tx| {
+        assert!(cache.len(tx)? <= CAPACITY);
+        cache.verify(tx)?;
+        Ok(())
+    });
+    drop(cache);
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
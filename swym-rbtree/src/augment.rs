@@ -0,0 +1,504 @@
+//! Augmentations which keep a summary of every subtree, for order statistics and range
+//! aggregates.
+//!
+//! A tree augmented with [`Summary<M>`](struct.Summary.html) supports [`len`], [`rank`] and
+//! [`select`] in `O(log n)` when the [`Monoid`] `M` counts the entries, and summaries of any range
+//! of keys with [`range_summary`].
+//!
+//! Every insert and remove updates the summaries on the path to the root, so writers to an
+//! augmented tree always conflict with each other.
+//!
+//! [`len`]: ../struct.RBTreeMapRaw.html#method.len
+//! [`rank`]: ../struct.RBTreeMapRaw.html#method.rank
+//! [`select`]: ../struct.RBTreeMapRaw.html#method.select
+//! [`range_summary`]: ../struct.RBTreeMapRaw.html#method.range_summary
+//! [`Monoid`]: trait.Monoid.html
+
+use crate::{
+    base::{Augment, RBNode, RBPtr, RBRef},
+    compare::Compare,
+    key_value, KeyValue, RBTreeMap, RBTreeMapRaw, RBTreeWith,
+};
+use std::ops::{Bound, RangeBounds};
+use swym::{
+    tcell::TCell,
+    thread_key,
+    tx::{Borrow, Error, Ordering, Read, Rw},
+};
+
+/// A summary of a run of entries, which can be combined with the summaries of adjacent runs.
+pub trait Monoid<K, V>: Borrow + Clone + PartialEq + Send + Sync + 'static {
+    /// Returns the summary of no entries.
+    fn empty() -> Self;
+
+    /// Returns the summary of a single entry.
+    fn entry(key: &K, value: &V) -> Self;
+
+    /// Returns the summary of the entries summarized by `self` followed by those summarized by
+    /// `rhs`.
+    ///
+    /// This must be associative, and `empty()` must be its identity.
+    fn combine(&self, rhs: &Self) -> Self;
+}
+
+/// Marks an augmentation which doesn't depend on values.
+///
+/// Only trees with such an augmentation hand out `View`s of their values, which could otherwise
+/// be used to modify a value without updating the summaries.
+pub trait KeyOnly {}
+
+impl KeyOnly for () {}
+
+/// Monoids which know the number of entries they summarize.
+pub trait Counted {
+    fn count(&self) -> usize;
+}
+
+/// The number of entries.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Len(pub usize);
+
+impl<K, V> Monoid<K, V> for Len {
+    #[inline]
+    fn empty() -> Self {
+        Len(0)
+    }
+
+    #[inline]
+    fn entry(_: &K, _: &V) -> Self {
+        Len(1)
+    }
+
+    #[inline]
+    fn combine(&self, rhs: &Self) -> Self {
+        Len(self.0 + rhs.0)
+    }
+}
+
+impl KeyOnly for Len {}
+
+impl Counted for Len {
+    #[inline]
+    fn count(&self) -> usize {
+        self.0
+    }
+}
+
+impl<K, V, A: Monoid<K, V>, B: Monoid<K, V>> Monoid<K, V> for (A, B) {
+    #[inline]
+    fn empty() -> Self {
+        (A::empty(), B::empty())
+    }
+
+    #[inline]
+    fn entry(key: &K, value: &V) -> Self {
+        (A::entry(key, value), B::entry(key, value))
+    }
+
+    #[inline]
+    fn combine(&self, rhs: &Self) -> Self {
+        (self.0.combine(&rhs.0), self.1.combine(&rhs.1))
+    }
+}
+
+impl<A: KeyOnly, B: KeyOnly> KeyOnly for (A, B) {}
+
+impl<B> Counted for (Len, B) {
+    #[inline]
+    fn count(&self) -> usize {
+        (self.0).0
+    }
+}
+
+/// The augmentation kept in every node of a tree summarized by the monoid `M`.
+pub struct Summary<M> {
+    // the node's own entry
+    entry:   TCell<M>,
+    // the node's subtree
+    subtree: TCell<M>,
+}
+
+impl<M: KeyOnly> KeyOnly for Summary<M> {}
+
+type Node<K, V, M, C> = RBNode<K, TCell<V>, Summary<M>, C>;
+
+#[inline]
+fn subtree<'tcell, K, V, M, C>(
+    tx: &impl Read<'tcell>,
+    ptr: &'tcell RBPtr<K, TCell<V>, Summary<M>, C>,
+) -> Result<M, Error>
+where
+    K: Send + Sync + 'static,
+    V: Borrow + Send + Sync + 'static,
+    M: Monoid<K, V>,
+{
+    Ok(match ptr.as_ref(tx, Ordering::default())? {
+        RBRef::Valid(node) => node.aug.subtree.borrow(tx, Ordering::default())?.clone(),
+        RBRef::Null => M::empty(),
+    })
+}
+
+// left subtree ⊕ entry ⊕ right subtree
+fn combined<'tcell, K, V, M, C>(
+    tx: &impl Read<'tcell>,
+    node: &'tcell Node<K, V, M, C>,
+) -> Result<M, Error>
+where
+    K: Send + Sync + 'static,
+    V: Borrow + Send + Sync + 'static,
+    M: Monoid<K, V>,
+{
+    let left = subtree(tx, &node.left)?;
+    let entry = node.aug.entry.borrow(tx, Ordering::default())?;
+    let right = subtree(tx, &node.right)?;
+    Ok(left.combine(&entry).combine(&right))
+}
+
+impl<K, V, M> Augment<K, TCell<V>> for Summary<M>
+where
+    K: Send + Sync + 'static,
+    V: Borrow + Send + Sync + 'static,
+    M: Monoid<K, V>,
+{
+    fn new(key: &K, value: &mut TCell<V>) -> Self {
+        let entry = M::entry(key, value.borrow_mut());
+        Summary {
+            subtree: TCell::new(entry.clone()),
+            entry:   TCell::new(entry),
+        }
+    }
+
+    fn update<'tcell, C>(
+        tx: &mut impl Rw<'tcell>,
+        node: &'tcell Node<K, V, M, C>,
+    ) -> Result<(), Error> {
+        let subtree = combined(tx, node)?;
+        // skipping unchanged summaries avoids conflicts with readers of other parts of the tree
+        if subtree != *node.aug.subtree.borrow(tx, Ordering::default())? {
+            node.aug.subtree.set(tx, subtree)?;
+        }
+        Ok(())
+    }
+
+    fn update_mut<C>(node: &mut Node<K, V, M, C>) {
+        let subtree = |ptr: &mut RBPtr<K, TCell<V>, Self, C>| match ptr.as_mut_ref() {
+            Some(child) => child.aug.subtree.borrow_mut().clone(),
+            None => M::empty(),
+        };
+        let left = subtree(&mut node.left);
+        let right = subtree(&mut node.right);
+        let entry = node.aug.entry.borrow_mut().clone();
+        *node.aug.subtree.borrow_mut() = left.combine(&entry).combine(&right);
+    }
+
+    fn update_value<'tcell, C>(
+        tx: &mut impl Rw<'tcell>,
+        node: &'tcell Node<K, V, M, C>,
+    ) -> Result<(), Error> {
+        let entry = M::entry(&node.key, &*node.value.borrow(tx, Ordering::default())?);
+        Ok(node.aug.entry.set(tx, entry)?)
+    }
+
+    fn verify<'tcell, C>(
+        tx: &impl Read<'tcell>,
+        node: &'tcell Node<K, V, M, C>,
+    ) -> Result<(), Error> {
+        let entry = M::entry(&node.key, &*node.value.borrow(tx, Ordering::default())?);
+        assert!(entry == *node.aug.entry.borrow(tx, Ordering::default())?);
+        assert!(combined(tx, node)? == *node.aug.subtree.borrow(tx, Ordering::default())?);
+        Ok(())
+    }
+}
+
+#[inline]
+fn after_start<C: Compare<Q>, Q: ?Sized>(key: &Q, start: Bound<&Q>) -> bool {
+    match start {
+        Bound::Unbounded => true,
+        Bound::Included(start) => !C::less(key, start),
+        Bound::Excluded(start) => C::less(start, key),
+    }
+}
+
+#[inline]
+fn before_end<C: Compare<Q>, Q: ?Sized>(key: &Q, end: Bound<&Q>) -> bool {
+    match end {
+        Bound::Unbounded => true,
+        Bound::Included(end) => !C::less(end, key),
+        Bound::Excluded(end) => C::less(key, end),
+    }
+}
+
+impl<K, V, M, C> RBTreeMapRaw<K, V, Summary<M>, C>
+where
+    K: Send + Sync + 'static,
+    V: Borrow + Send + Sync + 'static,
+    M: Monoid<K, V>,
+    C: Compare<K>,
+{
+    /// Returns the summary of all the entries.
+    pub fn summary<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<M, Error> {
+        Ok(match self.root.root(tx)? {
+            RBRef::Valid(root) => root.aug.subtree.borrow(tx, Ordering::default())?.clone(),
+            RBRef::Null => M::empty(),
+        })
+    }
+
+    /// Returns the summary of the entries whose keys are in `range`.
+    pub fn range_summary<'tcell, Q, R>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+        range: R,
+    ) -> Result<M, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+        R: RangeBounds<Q>,
+    {
+        let (start, end) = (range.start_bound(), range.end_bound());
+        // find the highest node in the range, where the paths to its two ends split
+        let mut node = self.root.root(tx)?;
+        while let RBRef::Valid(this) = node {
+            node = if !after_start::<C, _>(this.key.borrow(), start) {
+                this.right.as_ref(tx, Ordering::default())?
+            } else if !before_end::<C, _>(this.key.borrow(), end) {
+                this.left.as_ref(tx, Ordering::default())?
+            } else {
+                let left = Self::suffix_summary(tx, &this.left, start)?;
+                let entry = this.aug.entry.borrow(tx, Ordering::default())?;
+                let right = Self::prefix_summary(tx, &this.right, end)?;
+                return Ok(left.combine(&entry).combine(&right));
+            };
+        }
+        Ok(M::empty())
+    }
+
+    // summary of the entries of the subtree that come after `start`
+    fn suffix_summary<'tcell, Q>(
+        tx: &impl Read<'tcell>,
+        ptr: &'tcell RBPtr<K, TCell<V>, Summary<M>, C>,
+        start: Bound<&Q>,
+    ) -> Result<M, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let mut result = M::empty();
+        let mut node = ptr.as_ref(tx, Ordering::default())?;
+        // walking down from the right, so summaries are prepended
+        while let RBRef::Valid(this) = node {
+            node = if after_start::<C, _>(this.key.borrow(), start) {
+                let entry = this.aug.entry.borrow(tx, Ordering::default())?;
+                let right = subtree(tx, &this.right)?;
+                result = entry.combine(&right).combine(&result);
+                this.left.as_ref(tx, Ordering::default())?
+            } else {
+                this.right.as_ref(tx, Ordering::default())?
+            };
+        }
+        Ok(result)
+    }
+
+    // summary of the entries of the subtree that come before `end`
+    fn prefix_summary<'tcell, Q>(
+        tx: &impl Read<'tcell>,
+        ptr: &'tcell RBPtr<K, TCell<V>, Summary<M>, C>,
+        end: Bound<&Q>,
+    ) -> Result<M, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let mut result = M::empty();
+        let mut node = ptr.as_ref(tx, Ordering::default())?;
+        // walking down from the left, so summaries are appended
+        while let RBRef::Valid(this) = node {
+            node = if before_end::<C, _>(this.key.borrow(), end) {
+                let left = subtree(tx, &this.left)?;
+                let entry = this.aug.entry.borrow(tx, Ordering::default())?;
+                result = result.combine(&left).combine(&entry);
+                this.right.as_ref(tx, Ordering::default())?
+            } else {
+                this.left.as_ref(tx, Ordering::default())?
+            };
+        }
+        Ok(result)
+    }
+}
+
+impl<K, V, M, C> RBTreeMapRaw<K, V, Summary<M>, C>
+where
+    K: Send + Sync + 'static,
+    V: Borrow + Send + Sync + 'static,
+    M: Monoid<K, V> + Counted,
+    C: Compare<K>,
+{
+    pub fn len<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<usize, Error> {
+        Ok(self.summary(tx)?.count())
+    }
+
+    pub fn is_empty<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<bool, Error> {
+        Ok(self.len(tx)? == 0)
+    }
+
+    /// Returns the number of keys less than `key`.
+    pub fn rank<'tcell, Q>(&'tcell self, tx: &impl Read<'tcell>, key: &Q) -> Result<usize, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let mut rank = 0;
+        let mut node = self.root.root(tx)?;
+        while let RBRef::Valid(this) = node {
+            node = if !C::less(this.key.borrow(), key) {
+                this.left.as_ref(tx, Ordering::default())?
+            } else {
+                rank += subtree(tx, &this.left)?.count() + 1;
+                this.right.as_ref(tx, Ordering::default())?
+            };
+        }
+        Ok(rank)
+    }
+
+    /// Returns the entry with `index` keys less than its key.
+    pub fn select<'tx, 'tcell>(
+        &'tcell self,
+        tx: &'tx impl Read<'tcell>,
+        mut index: usize,
+    ) -> Result<Option<KeyValue<'tx, K, V>>, Error>
+    where
+        K: Borrow,
+        'tcell: 'tx,
+    {
+        let mut node = self.root.root(tx)?;
+        while let RBRef::Valid(this) = node {
+            let left = subtree(tx, &this.left)?.count();
+            node = if index < left {
+                this.left.as_ref(tx, Ordering::default())?
+            } else if index == left {
+                return Ok(Some(key_value(tx, this)?));
+            } else {
+                index -= left + 1;
+                this.right.as_ref(tx, Ordering::default())?
+            };
+        }
+        Ok(None)
+    }
+}
+
+impl<'tx, 'tcell, K, V, M, C> RBTreeWith<'tx, 'tcell, K, V, Summary<M>, C>
+where
+    K: Send + Sync + 'static,
+    V: Borrow + Send + Sync + 'static,
+    M: Monoid<K, V>,
+    C: Compare<K>,
+{
+    pub fn summary(&self) -> Result<M, Error> {
+        self.tree.summary(self.tx)
+    }
+
+    pub fn range_summary<Q, R>(&self, range: R) -> Result<M, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+        R: RangeBounds<Q>,
+    {
+        self.tree.range_summary(self.tx, range)
+    }
+}
+
+impl<'tx, 'tcell, K, V, M, C> RBTreeWith<'tx, 'tcell, K, V, Summary<M>, C>
+where
+    K: Send + Sync + 'static,
+    V: Borrow + Send + Sync + 'static,
+    M: Monoid<K, V> + Counted,
+    C: Compare<K>,
+{
+    pub fn len(&self) -> Result<usize, Error> {
+        self.tree.len(self.tx)
+    }
+
+    pub fn is_empty(&self) -> Result<bool, Error> {
+        self.tree.is_empty(self.tx)
+    }
+
+    pub fn rank<Q>(&self, key: &Q) -> Result<usize, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.tree.rank(self.tx, key)
+    }
+
+    pub fn select(&self, index: usize) -> Result<Option<KeyValue<'_, K, V>>, Error>
+    where
+        K: Borrow,
+    {
+        self.tree.select(self.tx, index)
+    }
+}
+
+impl<K, V, M, C> RBTreeMap<K, V, Summary<M>, C>
+where
+    K: Send + Sync + 'static,
+    V: Borrow + Send + Sync + 'static,
+    M: Monoid<K, V>,
+    C: Compare<K>,
+{
+    pub fn summary(&self) -> M {
+        thread_key::get().read(move |tx| self.raw.summary(tx))
+    }
+
+    pub fn range_summary<Q, R>(&self, range: R) -> M
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+        R: RangeBounds<Q> + Clone,
+    {
+        thread_key::get().read(move |tx| self.raw.range_summary(tx, range.clone()))
+    }
+}
+
+impl<K, V, M, C> RBTreeMap<K, V, Summary<M>, C>
+where
+    K: Send + Sync + 'static,
+    V: Borrow + Send + Sync + 'static,
+    M: Monoid<K, V> + Counted,
+    C: Compare<K>,
+{
+    pub fn len(&self) -> usize {
+        thread_key::get().read(move |tx| self.raw.len(tx))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of keys less than `key`.
+    pub fn rank<Q>(&self, key: &Q) -> usize
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        thread_key::get().read(move |tx| self.raw.rank(tx, key))
+    }
+
+    /// Returns a copy of the entry with `index` keys less than its key.
+    pub fn select(&self, index: usize) -> Option<(K, V)>
+    where
+        K: Borrow + Clone,
+        V: Clone,
+    {
+        thread_key::get().read(move |tx| {
+            let r = self.raw.select(tx, index)?;
+            Ok(r.map(|(key, value)| (key.clone(), value.clone())))
+        })
+    }
+}
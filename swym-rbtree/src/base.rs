@@ -1,7 +1,13 @@
 // based off of https://en.wikipedia.org/wiki/Red%E2%80%93black_tree and linux kernel
 // excuse the mess
 
-use std::ptr;
+use crate::compare::Compare;
+use std::{
+    marker::PhantomData,
+    mem,
+    ops::{Bound, Deref, DerefMut, RangeBounds},
+    ptr,
+};
 use swym::{
     tcell::TCell,
     tptr::TPtr,
@@ -15,22 +21,22 @@ pub enum Color {
     Red = 1,
 }
 
-struct PtrColor<K, V> {
-    raw: *const RBNode<K, V>,
+struct PtrColor<K, V, A, C> {
+    raw: *const RBNode<K, V, A, C>,
 }
 
-impl<K, V> Clone for PtrColor<K, V> {
+impl<K, V, A, C> Clone for PtrColor<K, V, A, C> {
     #[inline]
     fn clone(&self) -> Self {
         *self
     }
 }
-impl<K, V> Copy for PtrColor<K, V> {}
+impl<K, V, A, C> Copy for PtrColor<K, V, A, C> {}
 
-unsafe impl<K, V> Send for PtrColor<K, V> {}
-unsafe impl<K, V> Sync for PtrColor<K, V> {}
+unsafe impl<K, V, A, C> Send for PtrColor<K, V, A, C> {}
+unsafe impl<K, V, A, C> Sync for PtrColor<K, V, A, C> {}
 
-impl<K, V> PtrColor<K, V> {
+impl<K, V, A, C> PtrColor<K, V, A, C> {
     #[inline]
     fn color(self) -> Color {
         if self.raw as usize & 1 != Color::Black as _ {
@@ -41,7 +47,7 @@ impl<K, V> PtrColor<K, V> {
     }
 
     #[inline]
-    fn ptr(self) -> *const RBNode<K, V> {
+    fn ptr(self) -> *const RBNode<K, V, A, C> {
         (self.raw as usize & !1) as _
     }
 }
@@ -57,12 +63,14 @@ impl<T> Clone for Ptr<T> {
 }
 impl<T> Copy for Ptr<T> {}
 
+type RefColor<'tcell, K, V, A, C> = (RBRef<'tcell, K, V, A, C>, Color);
+
 /// packed pointer/color
-pub struct RBPtrColor<K, V> {
-    raw: TCell<Ptr<RBNode<K, V>>>,
+pub struct RBPtrColor<K, V, A, C> {
+    raw: TCell<Ptr<RBNode<K, V, A, C>>>,
 }
 
-impl<K, V> RBPtrColor<K, V> {
+impl<K, V, A, C> RBPtrColor<K, V, A, C> {
     #[inline]
     pub const fn null_black() -> Self {
         RBPtrColor {
@@ -75,33 +83,33 @@ impl<K, V> RBPtrColor<K, V> {
         &'tcell self,
         tx: &impl Read<'tcell>,
         ordering: Ordering,
-    ) -> Result<PtrColor<K, V>, Error> {
+    ) -> Result<PtrColor<K, V, A, C>, Error> {
         self.raw
             .get(tx, ordering)
             .map(|raw| PtrColor { raw: raw.0 })
     }
 
     #[inline]
-    pub fn set_mut<'tcell>(&mut self, parent: &RBNode<K, V>, color: Color) {
+    pub fn set_mut<'tcell>(&mut self, parent: &RBNode<K, V, A, C>, color: Color) {
         *self.raw.borrow_mut() =
-            Ptr(((parent as *const _ as usize) | color as usize) as *const RBNode<K, V>);
+            Ptr(((parent as *const _ as usize) | color as usize) as *const RBNode<K, V, A, C>);
     }
 
     #[inline]
-    pub fn set_red_parent_mut<'tcell>(&mut self, parent: &RBNode<K, V>) {
+    pub fn set_red_parent_mut<'tcell>(&mut self, parent: &RBNode<K, V, A, C>) {
         self.set_mut(parent, Color::Red)
     }
 
     #[inline]
-    pub fn set_black_parent_mut<'tcell>(&mut self, parent: &RBNode<K, V>) {
+    pub fn set_black_parent_mut<'tcell>(&mut self, parent: &RBNode<K, V, A, C>) {
         self.set_mut(parent, Color::Black)
     }
 
     #[inline]
-    pub fn parent_mut<'tcell>(&mut self) -> RBRef<'tcell, K, V> {
-        let raw = self.raw.borrow_mut().0 as *mut RBNode<K, V>;
+    pub fn parent_mut<'tcell>(&mut self) -> RBRef<'tcell, K, V, A, C> {
+        let raw = self.raw.borrow_mut().0 as *mut RBNode<K, V, A, C>;
         debug_assert!(raw as usize & 1 == Color::Red as _);
-        let raw = (raw as usize ^ 1) as *mut RBNode<K, V>;
+        let raw = (raw as usize ^ 1) as *mut RBNode<K, V, A, C>;
         if raw.is_null() {
             Null
         } else {
@@ -114,7 +122,7 @@ impl<K, V> RBPtrColor<K, V> {
         &'tcell self,
         tx: &impl Read<'tcell>,
         ordering: Ordering,
-    ) -> Result<(RBRef<'tcell, K, V>, Color), Error> {
+    ) -> Result<RefColor<'tcell, K, V, A, C>, Error> {
         self._as_ptr_color(tx, ordering).map(|ptr_color| {
             let ptr = ptr_color.ptr();
             let color = ptr_color.color();
@@ -144,11 +152,11 @@ impl<K, V> RBPtrColor<K, V> {
         &'tcell self,
         tx: &impl Read<'tcell>,
         ordering: Ordering,
-    ) -> Result<RBRef<'tcell, K, V>, Error> {
+    ) -> Result<RBRef<'tcell, K, V, A, C>, Error> {
         self.raw.get(tx, ordering).map(|ptr| {
             let ptr = ptr.0;
             debug_assert!(ptr as usize & 1 == Color::Black as _);
-            let ptr = (ptr as usize ^ Color::Black as usize) as *const RBNode<K, V>;
+            let ptr = (ptr as usize ^ Color::Black as usize) as *const RBNode<K, V, A, C>;
             if ptr.is_null() {
                 Null
             } else {
@@ -162,11 +170,11 @@ impl<K, V> RBPtrColor<K, V> {
         &'tcell self,
         tx: &impl Read<'tcell>,
         ordering: Ordering,
-    ) -> Result<RBRef<'tcell, K, V>, Error> {
+    ) -> Result<RBRef<'tcell, K, V, A, C>, Error> {
         self.raw.get(tx, ordering).map(|ptr| {
             let ptr = ptr.0;
             debug_assert!(ptr as usize & 1 == Color::Red as _);
-            let ptr = (ptr as usize ^ Color::Red as usize) as *const RBNode<K, V>;
+            let ptr = (ptr as usize ^ Color::Red as usize) as *const RBNode<K, V, A, C>;
             if ptr.is_null() {
                 Null
             } else {
@@ -176,12 +184,14 @@ impl<K, V> RBPtrColor<K, V> {
     }
 }
 
-impl<K: Send + Sync + 'static, V: Send + Sync + 'static> RBPtrColor<K, V> {
+impl<K: Send + Sync + 'static, V: Send + Sync + 'static, A: Augment<K, V>, C: 'static>
+    RBPtrColor<K, V, A, C>
+{
     #[inline]
     pub fn set<'tcell>(
         &'tcell self,
         tx: &mut impl Write<'tcell>,
-        value: RBRef<'tcell, K, V>,
+        value: RBRef<'tcell, K, V, A, C>,
         color: Color,
     ) -> Result<(), Error> {
         Ok(self
@@ -191,19 +201,19 @@ impl<K: Send + Sync + 'static, V: Send + Sync + 'static> RBPtrColor<K, V> {
 }
 
 /// handy wrapper around TPtr
-pub struct RBPtr<K, V> {
-    pub raw: TPtr<RBNode<K, V>>,
+pub struct RBPtr<K, V, A, C> {
+    pub raw: TPtr<RBNode<K, V, A, C>>,
 }
 
-impl<K, V> RBPtr<K, V> {
+impl<K, V, A, C> RBPtr<K, V, A, C> {
     #[inline]
     pub const fn null() -> Self {
         RBPtr { raw: TPtr::null() }
     }
 
     #[inline]
-    pub fn as_mut_ref(&mut self) -> Option<&mut RBNode<K, V>> {
-        let raw = *self.raw.borrow_mut() as *mut RBNode<K, V>;
+    pub fn as_mut_ref(&mut self) -> Option<&mut RBNode<K, V, A, C>> {
+        let raw = *self.raw.borrow_mut() as *mut RBNode<K, V, A, C>;
         if raw.is_null() {
             None
         } else {
@@ -216,7 +226,7 @@ impl<K, V> RBPtr<K, V> {
         &'tcell self,
         tx: &impl Read<'tcell>,
         ordering: Ordering,
-    ) -> Result<RBRef<'tcell, K, V>, Error> {
+    ) -> Result<RBRef<'tcell, K, V, A, C>, Error> {
         self.raw.as_ptr(tx, ordering).map(|ptr| {
             if ptr.is_null() {
                 Null
@@ -227,18 +237,20 @@ impl<K, V> RBPtr<K, V> {
     }
 }
 
-impl<K: Send + Sync + 'static, V: Send + Sync + 'static> RBPtr<K, V> {
+impl<K: Send + Sync + 'static, V: Send + Sync + 'static, A: Augment<K, V>, C: 'static>
+    RBPtr<K, V, A, C>
+{
     #[inline]
     pub fn set<'tcell>(
         &'tcell self,
         tx: &mut impl Write<'tcell>,
-        value: RBRef<'tcell, K, V>,
+        value: RBRef<'tcell, K, V, A, C>,
     ) -> Result<(), Error> {
         Ok(self.raw.set(tx, value._as_ptr())?)
     }
 
     #[inline]
-    pub fn set_mut(&mut self, value: &RBNode<K, V>) {
+    pub fn set_mut(&mut self, value: &RBNode<K, V, A, C>) {
         *self.raw.borrow_mut() = value;
     }
 
@@ -246,29 +258,98 @@ impl<K: Send + Sync + 'static, V: Send + Sync + 'static> RBPtr<K, V> {
     pub fn publish_box<'tcell>(
         &'tcell self,
         tx: &mut impl Write<'tcell>,
-        value: Box<RBNode<K, V>>,
+        value: Box<RBNode<K, V, A, C>>,
     ) -> Result<(), Error> {
         Ok(self.raw.publish_box(tx, value)?)
     }
 }
 
-pub enum RBRef<'tcell, K, V> {
-    Valid(&'tcell RBNode<K, V>),
+/// A node which is not yet part of the tree, along with how ownership of it is handed over.
+pub trait NewNode<K, V, A, C>: DerefMut<Target = RBNode<K, V, A, C>> {
+    fn publish<'tcell>(
+        self,
+        tx: &mut impl Write<'tcell>,
+        ptr: &'tcell RBPtr<K, V, A, C>,
+    ) -> Result<(), Error>;
+}
+
+impl<K: Send + Sync + 'static, V: Send + Sync + 'static, A: Augment<K, V>, C: 'static>
+    NewNode<K, V, A, C> for Box<RBNode<K, V, A, C>>
+{
+    #[inline]
+    fn publish<'tcell>(
+        self,
+        tx: &mut impl Write<'tcell>,
+        ptr: &'tcell RBPtr<K, V, A, C>,
+    ) -> Result<(), Error> {
+        ptr.publish_box(tx, self)
+    }
+}
+
+/// A node owned outside of the transaction inserting it.
+///
+/// If the transaction fails, the node is not freed, so every attempt of a transaction can insert
+/// the same node. Once the transaction succeeds, the node belongs to the tree, and the owner must
+/// leak it.
+pub struct PendingNode<'a, K, V, A, C>(&'a mut RBNode<K, V, A, C>);
+
+impl<'a, K, V, A, C> PendingNode<'a, K, V, A, C> {
+    /// Unlinks the node, which may be left over from a failed attempt.
+    #[inline]
+    pub fn new(node: &'a mut RBNode<K, V, A, C>) -> Self {
+        node.left = RBPtr::null();
+        node.right = RBPtr::null();
+        node.parent_color = RBPtrColor::null_black();
+        PendingNode(node)
+    }
+}
+
+impl<'a, K, V, A, C> Deref for PendingNode<'a, K, V, A, C> {
+    type Target = RBNode<K, V, A, C>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.0
+    }
+}
+
+impl<'a, K, V, A, C> DerefMut for PendingNode<'a, K, V, A, C> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0
+    }
+}
+
+impl<'a, K: Send + Sync + 'static, V: Send + Sync + 'static, A: Augment<K, V>, C: 'static>
+    NewNode<K, V, A, C> for PendingNode<'a, K, V, A, C>
+{
+    #[inline]
+    fn publish<'tcell>(
+        self,
+        tx: &mut impl Write<'tcell>,
+        ptr: &'tcell RBPtr<K, V, A, C>,
+    ) -> Result<(), Error> {
+        ptr.raw.set(tx, self.0)
+    }
+}
+
+pub enum RBRef<'tcell, K, V, A, C> {
+    Valid(&'tcell RBNode<K, V, A, C>),
     Null,
 }
 
-impl<'tcell, K, V> Clone for RBRef<'tcell, K, V> {
+impl<'tcell, K, V, A, C> Clone for RBRef<'tcell, K, V, A, C> {
     #[inline]
     fn clone(&self) -> Self {
         *self
     }
 }
 
-impl<'tcell, K, V> Copy for RBRef<'tcell, K, V> {}
+impl<'tcell, K, V, A, C> Copy for RBRef<'tcell, K, V, A, C> {}
 
-impl<'tcell, K, V> RBRef<'tcell, K, V> {
+impl<'tcell, K, V, A, C> RBRef<'tcell, K, V, A, C> {
     #[inline]
-    fn _as_ptr(self) -> *const RBNode<K, V> {
+    fn _as_ptr(self) -> *const RBNode<K, V, A, C> {
         match self {
             Valid(x) => x as _,
             Null => ptr::null(),
@@ -276,7 +357,15 @@ impl<'tcell, K, V> RBRef<'tcell, K, V> {
     }
 
     #[inline]
-    unsafe fn unwrap(self) -> &'tcell RBNode<K, V> {
+    pub fn into_option(self) -> Option<&'tcell RBNode<K, V, A, C>> {
+        match self {
+            Valid(r) => Some(r),
+            Null => None,
+        }
+    }
+
+    #[inline]
+    unsafe fn unwrap(self) -> &'tcell RBNode<K, V, A, C> {
         match self {
             Valid(r) => r,
             Null => {
@@ -287,39 +376,102 @@ impl<'tcell, K, V> RBRef<'tcell, K, V> {
     }
 }
 
-impl<'tcell, K, V> PartialEq for RBRef<'tcell, K, V> {
+impl<'tcell, K, V, A, C> PartialEq for RBRef<'tcell, K, V, A, C> {
     #[inline]
     fn eq(&self, rhs: &Self) -> bool {
         ptr::eq(self._as_ptr(), rhs._as_ptr())
     }
 }
 
-impl<'tcell, K, V> Eq for RBRef<'tcell, K, V> {}
+impl<'tcell, K, V, A, C> Eq for RBRef<'tcell, K, V, A, C> {}
 
-impl<'tcell, 'a, K, V> PartialEq<&'a RBNode<K, V>> for RBRef<'tcell, K, V> {
+impl<'tcell, 'a, K, V, A, C> PartialEq<&'a RBNode<K, V, A, C>> for RBRef<'tcell, K, V, A, C> {
     #[inline]
-    fn eq(&self, rhs: &&'a RBNode<K, V>) -> bool {
+    fn eq(&self, rhs: &&'a RBNode<K, V, A, C>) -> bool {
         ptr::eq(self._as_ptr(), *rhs)
     }
 }
 
-impl<'tcell, 'a, K, V> PartialEq<RBRef<'tcell, K, V>> for &'a RBNode<K, V> {
+impl<'tcell, 'a, K, V, A, C> PartialEq<RBRef<'tcell, K, V, A, C>> for &'a RBNode<K, V, A, C> {
     #[inline]
-    fn eq(&self, rhs: &RBRef<'tcell, K, V>) -> bool {
+    fn eq(&self, rhs: &RBRef<'tcell, K, V, A, C>) -> bool {
         ptr::eq(*self, rhs._as_ptr())
     }
 }
 
+/// Data kept in every node, summarizing the subtree rooted at that node.
+///
+/// Zero sized augmentations are never updated.
+pub trait Augment<K, V>: Sized + Send + Sync + 'static {
+    /// Returns the augmentation of a new node, which has no children yet.
+    fn new(key: &K, value: &mut V) -> Self;
+
+    /// Recomputes the augmentation of `node` from the augmentations of its children.
+    fn update<'tcell, C>(
+        tx: &mut impl Rw<'tcell>,
+        node: &'tcell RBNode<K, V, Self, C>,
+    ) -> Result<(), Error>;
+
+    /// Recomputes the augmentation of `node`, which isn't shared yet, from its children.
+    fn update_mut<C>(node: &mut RBNode<K, V, Self, C>);
+
+    /// Recomputes the part of the augmentation of `node` that depends on its value.
+    fn update_value<'tcell, C>(
+        tx: &mut impl Rw<'tcell>,
+        node: &'tcell RBNode<K, V, Self, C>,
+    ) -> Result<(), Error>;
+
+    // checks that the augmentation of `node` is up to date (for debugging)
+    fn verify<'tcell, C>(
+        tx: &impl Read<'tcell>,
+        node: &'tcell RBNode<K, V, Self, C>,
+    ) -> Result<(), Error>;
+}
+
+impl<K, V> Augment<K, V> for () {
+    #[inline]
+    fn new(_: &K, _: &mut V) -> Self {}
+
+    #[inline]
+    fn update<'tcell, C>(
+        _: &mut impl Rw<'tcell>,
+        _: &'tcell RBNode<K, V, Self, C>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    #[inline]
+    fn update_mut<C>(_: &mut RBNode<K, V, Self, C>) {}
+
+    #[inline]
+    fn update_value<'tcell, C>(
+        _: &mut impl Rw<'tcell>,
+        _: &'tcell RBNode<K, V, Self, C>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    #[inline]
+    fn verify<'tcell, C>(
+        _: &impl Read<'tcell>,
+        _: &'tcell RBNode<K, V, Self, C>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
 #[repr(C)]
-pub struct RBNode<K, V> {
-    pub left:         RBPtr<K, V>,
-    pub right:        RBPtr<K, V>,
-    pub parent_color: RBPtrColor<K, V>,
+pub struct RBNode<K, V, A, C> {
+    pub left:         RBPtr<K, V, A, C>,
+    pub right:        RBPtr<K, V, A, C>,
+    pub parent_color: RBPtrColor<K, V, A, C>,
     pub key:          K, // effectively immutable
     pub value:        V, // can be wrapped in TCell by user
+    pub aug:          A,
+    compare:          PhantomData<fn() -> C>,
 }
 
-impl<K, V> RBNode<K, V> {
+impl<K, V, A, C> RBNode<K, V, A, C> {
     pub unsafe fn destroy(&mut self) {
         self.left.as_mut_ref().map(|left| left.destroy());
         self.right.as_mut_ref().map(|right| right.destroy());
@@ -327,21 +479,41 @@ impl<K, V> RBNode<K, V> {
     }
 }
 
-impl<K: Ord + Send + Sync + 'static, V: Send + Sync + 'static> RBNode<K, V> {
+impl<K, V, A, C> RBNode<K, V, A, C>
+where
+    K: Send + Sync + 'static,
+    V: Send + Sync + 'static,
+    A: Augment<K, V>,
+    C: Compare<K>,
+{
+    pub fn new(key: K, mut value: V) -> Self {
+        let aug = A::new(&key, &mut value);
+        RBNode {
+            left: RBPtr::null(),
+            right: RBPtr::null(),
+            parent_color: RBPtrColor::null_black(),
+            key,
+            value,
+            aug,
+            compare: PhantomData,
+        }
+    }
+
     #[inline(always)]
     fn find_impl<'tcell, Q>(
         &'tcell self,
         tx: &impl Read<'tcell>,
         key: &Q,
         ordering: Ordering,
-    ) -> Result<Location<'tcell, K, V>, Error>
+    ) -> Result<Location<'tcell, K, V, A, C>, Error>
     where
         K: std::borrow::Borrow<Q>,
-        Q: Ord + ?Sized,
+        Q: ?Sized,
+        C: Compare<Q>,
     {
         let mut this = self;
         Ok(loop {
-            match key.cmp(this.key.borrow()) {
+            match C::compare(key, this.key.borrow()) {
                 std::cmp::Ordering::Less => {
                     if let Valid(left) = this.left.as_ref(tx, ordering)? {
                         this = left
@@ -361,12 +533,68 @@ impl<K: Ord + Send + Sync + 'static, V: Send + Sync + 'static> RBNode<K, V> {
         })
     }
 
-    pub fn insert_fixup<'tcell>(
-        mut self: Box<Self>,
+    /// Returns the leftmost node of the subtree rooted at `self`.
+    #[inline]
+    fn first<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<&'tcell Self, Error> {
+        let mut this = self;
+        while let Valid(left) = this.left.as_ref(tx, Ordering::default())? {
+            this = left;
+        }
+        Ok(this)
+    }
+
+    /// Returns the rightmost node of the subtree rooted at `self`.
+    #[inline]
+    fn last<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<&'tcell Self, Error> {
+        let mut this = self;
+        while let Valid(right) = this.right.as_ref(tx, Ordering::default())? {
+            this = right;
+        }
+        Ok(this)
+    }
+
+    /// Returns the node following `self` in key order.
+    pub fn next<'tcell>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+    ) -> Result<RBRef<'tcell, K, V, A, C>, Error> {
+        if let Valid(right) = self.right.as_ref(tx, Ordering::default())? {
+            return Ok(Valid(right.first(tx)?));
+        }
+        // climb until we come up out of a left subtree
+        let mut this = self;
+        loop {
+            match this.parent_color.as_ref_color(tx, Ordering::default())?.0 {
+                Valid(parent) if C::less(&parent.key, &this.key) => this = parent,
+                parent => return Ok(parent),
+            }
+        }
+    }
+
+    /// Returns the node preceding `self` in key order.
+    pub fn prev<'tcell>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+    ) -> Result<RBRef<'tcell, K, V, A, C>, Error> {
+        if let Valid(left) = self.left.as_ref(tx, Ordering::default())? {
+            return Ok(Valid(left.last(tx)?));
+        }
+        // climb until we come up out of a right subtree
+        let mut this = self;
+        loop {
+            match this.parent_color.as_ref_color(tx, Ordering::default())?.0 {
+                Valid(parent) if C::less(&this.key, &parent.key) => this = parent,
+                parent => return Ok(parent),
+            }
+        }
+    }
+
+    pub fn insert_fixup<'tcell, N: NewNode<K, V, A, C>>(
+        mut node: N,
         tx: &mut impl Rw<'tcell>,
         parent: &'tcell Self,
         insert_on_left: bool,
-    ) -> Result<RepairResult<'tcell, Self>, Error> {
+    ) -> Result<RepairResult<'tcell, Self, N>, Error> {
         // we inline the Valid(parent) case from below in order to optimize for the &mut case which
         // requires less tx ops.
         let (gp, p_color) = parent.parent_color.as_ref_color(tx, Ordering::default())?;
@@ -378,13 +606,13 @@ impl<K: Ord + Send + Sync + 'static, V: Send + Sync + 'static> RBNode<K, V> {
 
         let (mut this, mut left, mut right) = if p_color == Color::Black {
             // we have a parent, and it's black, so there's no repair to do, just insert
-            self.parent_color.set_red_parent_mut(parent);
-            side.publish_box(tx, self)?;
+            node.parent_color.set_red_parent_mut(parent);
+            node.publish(tx, side)?;
             return Ok(RepairResult::RootUnchanged);
         } else {
             // safe because parent is red, and the root must be black
             let gp = unsafe { gp.unwrap() };
-            let uncle_on_left = gp.key < parent.key;
+            let uncle_on_left = C::less(&gp.key, &parent.key);
             let uncle = if uncle_on_left {
                 gp.left.as_ref(tx, Ordering::default())?
             } else {
@@ -395,8 +623,8 @@ impl<K: Ord + Send + Sync + 'static, V: Send + Sync + 'static> RBNode<K, V> {
                     if uncle.parent_color.color(tx, Ordering::default())? == Color::Red =>
                 {
                     // insert then begin recursion
-                    self.parent_color.set_red_parent_mut(parent);
-                    side.publish_box(tx, self)?;
+                    node.parent_color.set_red_parent_mut(parent);
+                    node.publish(tx, side)?;
                     // toggle parent and uncle to black, and gp to red.
                     // now start the process over again for gp
                     let (left, right) = if uncle_on_left {
@@ -409,7 +637,14 @@ impl<K: Ord + Send + Sync + 'static, V: Send + Sync + 'static> RBNode<K, V> {
                     (gp, left, right)
                 }
                 _ => {
-                    return self.insert_case4_mut(tx, parent, gp, uncle_on_left, insert_on_left);
+                    return Self::insert_case4_mut(
+                        node,
+                        tx,
+                        parent,
+                        gp,
+                        uncle_on_left,
+                        insert_on_left,
+                    );
                 }
             }
         };
@@ -436,7 +671,7 @@ impl<K: Ord + Send + Sync + 'static, V: Send + Sync + 'static> RBNode<K, V> {
                     } else {
                         // safe because parent is red, and the root must be black
                         let gp = unsafe { gp.unwrap() };
-                        let uncle_on_left = gp.key < parent.key;
+                        let uncle_on_left = C::less(&gp.key, &parent.key);
                         let uncle = if uncle_on_left {
                             gp.left.as_ref(tx, Ordering::default())?
                         } else {
@@ -513,7 +748,7 @@ impl<K: Ord + Send + Sync + 'static, V: Send + Sync + 'static> RBNode<K, V> {
         &'tcell self,
         tx: &mut impl Rw<'tcell>,
         right: &'tcell Self,
-        right_left: RBRef<'tcell, K, V>,
+        right_left: RBRef<'tcell, K, V, A, C>,
     ) -> Result<Option<&'tcell Self>, Error> {
         // read then immediate unconditional write is ReadWrite
         let parent = self.parent_color.black_parent(tx, Ordering::Read)?;
@@ -526,7 +761,7 @@ impl<K: Ord + Send + Sync + 'static, V: Send + Sync + 'static> RBNode<K, V> {
             right_left.parent_color.set(tx, Valid(self), Color::Black)?;
         }
         Ok(if let Valid(parent) = parent {
-            if self.key < parent.key {
+            if C::less(&self.key, &parent.key) {
                 parent.left.set(tx, Valid(right))?
             } else {
                 parent.right.set(tx, Valid(right))?
@@ -541,7 +776,7 @@ impl<K: Ord + Send + Sync + 'static, V: Send + Sync + 'static> RBNode<K, V> {
         &'tcell self,
         tx: &mut impl Rw<'tcell>,
         left: &'tcell Self,
-        left_right: RBRef<'tcell, K, V>,
+        left_right: RBRef<'tcell, K, V, A, C>,
     ) -> Result<Option<&'tcell Self>, Error> {
         // read then immediate unconditional write is ReadWrite
         let parent = self.parent_color.black_parent(tx, Ordering::Read)?;
@@ -554,7 +789,7 @@ impl<K: Ord + Send + Sync + 'static, V: Send + Sync + 'static> RBNode<K, V> {
             left_right.parent_color.set(tx, Valid(self), Color::Black)?;
         }
         Ok(if let Valid(parent) = parent {
-            if self.key < parent.key {
+            if C::less(&self.key, &parent.key) {
                 parent.left.set(tx, Valid(left))?
             } else {
                 parent.right.set(tx, Valid(left))?
@@ -603,43 +838,43 @@ impl<K: Ord + Send + Sync + 'static, V: Send + Sync + 'static> RBNode<K, V> {
         }
     }
 
-    fn insert_case4_mut<'tcell>(
-        mut self: Box<Self>,
+    fn insert_case4_mut<'tcell, N: NewNode<K, V, A, C>>(
+        mut node: N,
         tx: &mut impl Rw<'tcell>,
         parent: &'tcell Self,
         gp: &'tcell Self,
         uncle_on_left: bool,
         insert_on_left: bool,
-    ) -> Result<RepairResult<'tcell, Self>, Error> {
-        let self_ref = unsafe { &*(&*self as *const Self) };
+    ) -> Result<RepairResult<'tcell, Self, N>, Error> {
+        let node_ref = unsafe { &*(&*node as *const Self) };
         Ok(if uncle_on_left {
             if insert_on_left {
                 // parent.rotate_right(tx)?;
                 // gp.rotate_left(tx)?;
 
-                self.right.set_mut(parent);
-                self.left.set_mut(gp);
-                parent.parent_color.set(tx, Valid(self_ref), Color::Red)?;
+                node.right.set_mut(parent);
+                node.left.set_mut(gp);
+                parent.parent_color.set(tx, Valid(node_ref), Color::Red)?;
 
                 // read followed immediately by write, ReadWrite
                 let parent = gp.parent_color.black_parent(tx, Ordering::Read)?;
-                gp.parent_color.set(tx, Valid(self_ref), Color::Red)?;
+                gp.parent_color.set(tx, Valid(node_ref), Color::Red)?;
 
                 gp.right.set(tx, Null)?;
                 if let Valid(parent) = parent {
-                    self.parent_color.set_black_parent_mut(parent);
-                    if gp.key < parent.key {
-                        parent.left.publish_box(tx, self)?
+                    node.parent_color.set_black_parent_mut(parent);
+                    if C::less(&gp.key, &parent.key) {
+                        node.publish(tx, &parent.left)?
                     } else {
-                        parent.right.publish_box(tx, self)?
+                        node.publish(tx, &parent.right)?
                     }
                     RepairResult::RootUnchanged
                 } else {
-                    RepairResult::PublishRoot(self)
+                    RepairResult::PublishRoot(node)
                 }
             } else {
-                self.parent_color.set_red_parent_mut(parent);
-                parent.right.publish_box(tx, self)?;
+                node.parent_color.set_red_parent_mut(parent);
+                node.publish(tx, &parent.right)?;
 
                 let parent_left = parent.left.as_ref(tx, Ordering::Read)?;
                 match gp.rotate_left_red_helper(tx, parent, parent_left)? {
@@ -652,29 +887,29 @@ impl<K: Ord + Send + Sync + 'static, V: Send + Sync + 'static> RBNode<K, V> {
                 // parent.rotate_left(tx)?;
                 // gp.rotate_right(tx)?;
 
-                self.left.set_mut(parent);
-                self.right.set_mut(gp);
-                parent.parent_color.set(tx, Valid(self_ref), Color::Red)?;
+                node.left.set_mut(parent);
+                node.right.set_mut(gp);
+                parent.parent_color.set(tx, Valid(node_ref), Color::Red)?;
 
                 // read followed immediately by write, ReadWrite
                 let parent = gp.parent_color.black_parent(tx, Ordering::Read)?;
-                gp.parent_color.set(tx, Valid(self_ref), Color::Red)?;
+                gp.parent_color.set(tx, Valid(node_ref), Color::Red)?;
 
                 gp.left.set(tx, Null)?;
                 if let Valid(parent) = parent {
-                    self.parent_color.set_black_parent_mut(parent);
-                    if gp.key < parent.key {
-                        parent.left.publish_box(tx, self)?
+                    node.parent_color.set_black_parent_mut(parent);
+                    if C::less(&gp.key, &parent.key) {
+                        node.publish(tx, &parent.left)?
                     } else {
-                        parent.right.publish_box(tx, self)?
+                        node.publish(tx, &parent.right)?
                     }
                     RepairResult::RootUnchanged
                 } else {
-                    RepairResult::PublishRoot(self)
+                    RepairResult::PublishRoot(node)
                 }
             } else {
-                self.parent_color.set_red_parent_mut(parent);
-                parent.left.publish_box(tx, self)?;
+                node.parent_color.set_red_parent_mut(parent);
+                node.publish(tx, &parent.left)?;
 
                 // gp.rotate_right(tx)?;
                 let parent_left = parent.right.as_ref(tx, Ordering::Read)?;
@@ -689,15 +924,17 @@ impl<K: Ord + Send + Sync + 'static, V: Send + Sync + 'static> RBNode<K, V> {
     pub fn remove_nofix<'tcell>(
         &'tcell self,
         tx: &mut impl Rw<'tcell>,
-    ) -> Result<(Option<RBRef<'tcell, K, V>>, RBRef<'tcell, K, V>), Error> {
+    ) -> Result<RemoveResult<'tcell, K, V, A, C>, Error> {
         let mut new_root = None;
         let mut rebalance = Null;
+        // the lowest node whose subtree lost a node
+        let changed;
         let node_right = self.right.as_ref(tx, Ordering::default())?;
         let node_left = self.left.as_ref(tx, Ordering::default())?;
         if node_left == Null {
             let (parent, color) = self.parent_color.as_ref_color(tx, Ordering::default())?;
             if let Valid(parent) = parent {
-                if self.key < parent.key {
+                if C::less(&self.key, &parent.key) {
                     &parent.left
                 } else {
                     &parent.right
@@ -711,12 +948,13 @@ impl<K: Ord + Send + Sync + 'static, V: Send + Sync + 'static> RBNode<K, V> {
             } else if color == Color::Black {
                 rebalance = parent;
             }
+            changed = parent;
         } else if node_right == Null {
             let node_left = unsafe { node_left.unwrap() };
             let (parent, color) = self.parent_color.as_ref_color(tx, Ordering::default())?;
             node_left.parent_color.set(tx, parent, color)?;
             if let Valid(parent) = parent {
-                if self.key < parent.key {
+                if C::less(&self.key, &parent.key) {
                     &parent.left
                 } else {
                     &parent.right
@@ -725,6 +963,7 @@ impl<K: Ord + Send + Sync + 'static, V: Send + Sync + 'static> RBNode<K, V> {
             } else {
                 new_root = Some(Valid(node_left));
             }
+            changed = parent;
         } else {
             let mut parent;
             let child2;
@@ -760,7 +999,12 @@ impl<K: Ord + Send + Sync + 'static, V: Send + Sync + 'static> RBNode<K, V> {
 
             let pc = self.parent_color.as_ref_color(tx, Ordering::default())?;
             if let Valid(p) = pc.0 {
-                if self.key < p.key { &p.left } else { &p.right }.set(tx, Valid(successor))?;
+                if C::less(&self.key, &p.key) {
+                    &p.left
+                } else {
+                    &p.right
+                }
+                .set(tx, Valid(successor))?;
             } else {
                 new_root = Some(Valid(successor));
             }
@@ -775,9 +1019,14 @@ impl<K: Ord + Send + Sync + 'static, V: Send + Sync + 'static> RBNode<K, V> {
                     rebalance = Valid(parent);
                 }
             }
+            changed = Valid(parent);
         }
         unsafe { TPtr::privatize_as_box(tx, self) };
-        Ok((new_root, rebalance))
+        Ok(RemoveResult {
+            new_root,
+            rebalance,
+            changed,
+        })
     }
 
     // algorithm from here:
@@ -789,7 +1038,7 @@ impl<K: Ord + Send + Sync + 'static, V: Send + Sync + 'static> RBNode<K, V> {
         tx: &mut impl Rw<'tcell>,
     ) -> Result<Option<&'tcell Self>, Error> {
         let mut result = None;
-        let mut node: RBRef<'_, K, V> = Null;
+        let mut node: RBRef<'_, K, V, A, C> = Null;
         let mut parent = self;
         loop {
             debug_assert!(
@@ -886,7 +1135,7 @@ impl<K: Ord + Send + Sync + 'static, V: Send + Sync + 'static> RBNode<K, V> {
 
                 sibling.parent_color.set(tx, gp, p_color)?;
                 if let Valid(gp) = gp {
-                    if sibling.key < gp.key {
+                    if C::less(&sibling.key, &gp.key) {
                         &gp.left
                     } else {
                         &gp.right
@@ -972,7 +1221,7 @@ impl<K: Ord + Send + Sync + 'static, V: Send + Sync + 'static> RBNode<K, V> {
                 parent.parent_color.set(tx, Valid(sibling), Color::Black)?;
                 sibling.parent_color.set(tx, gp, p_color)?;
                 if let Valid(gp) = gp {
-                    if sibling.key < gp.key {
+                    if C::less(&sibling.key, &gp.key) {
                         &gp.left
                     } else {
                         &gp.right
@@ -987,11 +1236,24 @@ impl<K: Ord + Send + Sync + 'static, V: Send + Sync + 'static> RBNode<K, V> {
         Ok(result)
     }
 
+    /// Frees the subtree rooted at `self` once the transaction commits, without unlinking it.
+    fn privatize_subtree<'tcell>(&'tcell self, tx: &mut impl Rw<'tcell>) -> Result<(), Error> {
+        if let Valid(left) = self.left.as_ref(tx, Ordering::default())? {
+            left.privatize_subtree(tx)?;
+        }
+        if let Valid(right) = self.right.as_ref(tx, Ordering::default())? {
+            right.privatize_subtree(tx)?;
+        }
+        unsafe { TPtr::privatize_as_box(tx, self) };
+        Ok(())
+    }
+
     // checks all the RBTree properties (for debugging)
-    pub fn verify<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<Verify<K>, Error>
-    where
-        K: Clone,
-    {
+    pub fn verify<'tcell>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+    ) -> Result<Verify<'tcell, K>, Error> {
+        A::verify(tx, self)?;
         let (_, color) = self.parent_color.as_ref_color(tx, Ordering::default())?;
         if color == Color::Red {
             let left = self.left.as_ref(tx, Ordering::default())?;
@@ -1025,14 +1287,20 @@ impl<K: Ord + Send + Sync + 'static, V: Send + Sync + 'static> RBNode<K, V> {
                 }
             };
             assert_eq!(left_verify.black_depth, right_verify.black_depth);
-            assert!(left_verify.max.map(|m| m < self.key).unwrap_or(true));
-            assert!(right_verify.min.map(|m| self.key < m).unwrap_or(true));
+            assert!(left_verify
+                .max
+                .map(|m| C::less(m, &self.key))
+                .unwrap_or(true));
+            assert!(right_verify
+                .min
+                .map(|m| C::less(&self.key, m))
+                .unwrap_or(true));
             let result = Verify {
                 black_depth: left_verify.black_depth,
                 min_depth:   left_verify.min_depth.min(right_verify.min_depth) + 1,
                 max_depth:   left_verify.max_depth.max(right_verify.max_depth) + 1,
-                min:         Some(left_verify.min.unwrap_or(self.key.clone())),
-                max:         Some(right_verify.max.unwrap_or(self.key.clone())),
+                min:         Some(left_verify.min.unwrap_or(&self.key)),
+                max:         Some(right_verify.max.unwrap_or(&self.key)),
             };
             assert!(result.min_depth * 2 >= result.max_depth);
             Ok(result)
@@ -1066,14 +1334,20 @@ impl<K: Ord + Send + Sync + 'static, V: Send + Sync + 'static> RBNode<K, V> {
                 }
             };
             assert_eq!(left_verify.black_depth, right_verify.black_depth);
-            assert!(left_verify.max.map(|m| m < self.key).unwrap_or(true));
-            assert!(right_verify.min.map(|m| self.key < m).unwrap_or(true));
+            assert!(left_verify
+                .max
+                .map(|m| C::less(m, &self.key))
+                .unwrap_or(true));
+            assert!(right_verify
+                .min
+                .map(|m| C::less(&self.key, m))
+                .unwrap_or(true));
             let result = Verify {
                 black_depth: left_verify.black_depth + 1,
                 min_depth:   left_verify.min_depth.min(right_verify.min_depth) + 1,
                 max_depth:   left_verify.max_depth.max(right_verify.max_depth) + 1,
-                min:         Some(left_verify.min.unwrap_or(self.key.clone())),
-                max:         Some(right_verify.max.unwrap_or(self.key.clone())),
+                min:         Some(left_verify.min.unwrap_or(&self.key)),
+                max:         Some(right_verify.max.unwrap_or(&self.key)),
             };
             assert!(result.min_depth * 2 >= result.max_depth);
             Ok(result)
@@ -1081,36 +1355,42 @@ impl<K: Ord + Send + Sync + 'static, V: Send + Sync + 'static> RBNode<K, V> {
     }
 }
 
-pub struct Verify<K> {
+pub struct Verify<'a, K> {
     black_depth: usize,
     min_depth:   usize,
     max_depth:   usize,
-    min:         Option<K>,
-    max:         Option<K>,
+    min:         Option<&'a K>,
+    max:         Option<&'a K>,
 }
 
-pub enum VacantLocation<'a, K, V> {
+pub enum VacantLocation<'a, K, V, A, C> {
     Empty,
-    Left { parent: &'a RBNode<K, V> },
-    Right { parent: &'a RBNode<K, V> },
+    Left { parent: &'a RBNode<K, V, A, C> },
+    Right { parent: &'a RBNode<K, V, A, C> },
+}
+
+pub enum Location<'a, K, V, A, C> {
+    Vacant(VacantLocation<'a, K, V, A, C>),
+    Occupied { node: &'a RBNode<K, V, A, C> },
 }
 
-pub enum Location<'a, K, V> {
-    Vacant(VacantLocation<'a, K, V>),
-    Occupied { node: &'a RBNode<K, V> },
+pub struct RemoveResult<'a, K, V, A, C> {
+    new_root:  Option<RBRef<'a, K, V, A, C>>,
+    rebalance: RBRef<'a, K, V, A, C>,
+    changed:   RBRef<'a, K, V, A, C>,
 }
 
-pub enum RepairResult<'a, T> {
+pub enum RepairResult<'a, T, N = Box<T>> {
     NewRoot(&'a T),
-    PublishRoot(Box<T>),
+    PublishRoot(N),
     RootUnchanged,
 }
 
-pub struct RBRoot<K, V> {
-    root: RBPtr<K, V>,
+pub struct RBRoot<K, V, A, C> {
+    root: RBPtr<K, V, A, C>,
 }
 
-impl<K, V> RBRoot<K, V> {
+impl<K, V, A, C> RBRoot<K, V, A, C> {
     pub const fn new() -> Self {
         RBRoot {
             root: RBPtr::null(),
@@ -1118,7 +1398,7 @@ impl<K, V> RBRoot<K, V> {
     }
 }
 
-impl<K, V> Drop for RBRoot<K, V> {
+impl<K, V, A, C> Drop for RBRoot<K, V, A, C> {
     fn drop(&mut self) {
         unsafe {
             self.root.as_mut_ref().map(|root| root.destroy());
@@ -1126,8 +1406,63 @@ impl<K, V> Drop for RBRoot<K, V> {
     }
 }
 
-impl<K: Send + Sync + Ord + 'static, V: Send + Sync + 'static> RBRoot<K, V> {
-    fn root<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<RBRef<'tcell, K, V>, Error> {
+impl<K, V, A, C> RBRoot<K, V, A, C>
+where
+    K: Send + Sync + 'static,
+    V: Send + Sync + 'static,
+    A: Augment<K, V>,
+    C: Compare<K>,
+{
+    /// Builds a balanced tree from entries in strictly ascending order of their keys, without a
+    /// transaction.
+    pub fn from_sorted(mut entries: impl ExactSizeIterator<Item = (K, V)>) -> Self {
+        let len = entries.len();
+        // every level is full but the deepest one, whose nodes are red
+        let mut red_depth = 0;
+        while (2 << red_depth) - 1 <= len {
+            red_depth += 1;
+        }
+        let mut root = RBPtr::null();
+        if let Some(node) = Self::build(&mut entries, len, 0, red_depth) {
+            root.set_mut(Box::leak(node));
+        }
+        RBRoot { root }
+    }
+
+    fn build(
+        entries: &mut impl Iterator<Item = (K, V)>,
+        len: usize,
+        depth: usize,
+        red_depth: usize,
+    ) -> Option<Box<RBNode<K, V, A, C>>> {
+        if len == 0 {
+            return None;
+        }
+        let left = Self::build(entries, (len - 1) / 2, depth + 1, red_depth);
+        let (key, value) = entries.next().expect("iterator shorter than its `len`");
+        let mut node = Box::new(RBNode::new(key, value));
+        let right = Self::build(entries, len / 2, depth + 1, red_depth);
+        let color = if depth + 1 == red_depth {
+            Color::Red
+        } else {
+            Color::Black
+        };
+        if let Some(mut left) = left {
+            left.parent_color.set_mut(&node, color);
+            node.left.set_mut(Box::leak(left));
+        }
+        if let Some(mut right) = right {
+            right.parent_color.set_mut(&node, color);
+            node.right.set_mut(Box::leak(right));
+        }
+        A::update_mut(&mut node);
+        Some(node)
+    }
+
+    pub fn root<'tcell>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+    ) -> Result<RBRef<'tcell, K, V, A, C>, Error> {
         self.root.as_ref(tx, Ordering::default())
     }
 
@@ -1135,10 +1470,11 @@ impl<K: Send + Sync + Ord + 'static, V: Send + Sync + 'static> RBRoot<K, V> {
         &'tcell self,
         tx: &impl Read<'tcell>,
         key: &Q,
-    ) -> Result<Location<'tcell, K, V>, Error>
+    ) -> Result<Location<'tcell, K, V, A, C>, Error>
     where
         K: std::borrow::Borrow<Q>,
-        Q: Ord + ?Sized,
+        Q: ?Sized,
+        C: Compare<Q>,
     {
         let root = self.root(tx)?;
         let loc = if let Valid(root) = root {
@@ -1153,10 +1489,11 @@ impl<K: Send + Sync + Ord + 'static, V: Send + Sync + 'static> RBRoot<K, V> {
         &'tcell self,
         tx: &impl Read<'tcell>,
         key: &Q,
-    ) -> Result<Location<'tcell, K, V>, Error>
+    ) -> Result<Location<'tcell, K, V, A, C>, Error>
     where
         K: std::borrow::Borrow<Q>,
-        Q: Ord + ?Sized,
+        Q: ?Sized,
+        C: Compare<Q>,
     {
         let root = self.root(tx)?;
         let loc = if let Valid(root) = root {
@@ -1173,10 +1510,11 @@ impl<K: Send + Sync + Ord + 'static, V: Send + Sync + 'static> RBRoot<K, V> {
         tx: &impl Read<'tcell>,
         key: &Q,
         ordering: Ordering,
-    ) -> Result<Location<'tcell, K, V>, Error>
+    ) -> Result<Location<'tcell, K, V, A, C>, Error>
     where
         K: std::borrow::Borrow<Q>,
-        Q: Ord + ?Sized,
+        Q: ?Sized,
+        C: Compare<Q>,
     {
         match ordering {
             Ordering::Read => self.location_read(tx, key),
@@ -1192,7 +1530,8 @@ impl<K: Send + Sync + Ord + 'static, V: Send + Sync + 'static> RBRoot<K, V> {
     ) -> Result<bool, Error>
     where
         K: std::borrow::Borrow<Q>,
-        Q: Ord + ?Sized,
+        Q: ?Sized,
+        C: Compare<Q>,
     {
         Ok(match self.location(tx, key, Ordering::default())? {
             Location::Vacant(..) => false,
@@ -1200,58 +1539,287 @@ impl<K: Send + Sync + Ord + 'static, V: Send + Sync + 'static> RBRoot<K, V> {
         })
     }
 
+    pub fn first<'tcell>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+    ) -> Result<RBRef<'tcell, K, V, A, C>, Error> {
+        Ok(match self.root(tx)? {
+            Valid(root) => Valid(root.first(tx)?),
+            Null => Null,
+        })
+    }
+
+    pub fn last<'tcell>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+    ) -> Result<RBRef<'tcell, K, V, A, C>, Error> {
+        Ok(match self.root(tx)? {
+            Valid(root) => Valid(root.last(tx)?),
+            Null => Null,
+        })
+    }
+
+    /// Returns the node with the greatest key less than or equal to `key`.
+    pub fn floor<'tcell, Q>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+        key: &Q,
+    ) -> Result<RBRef<'tcell, K, V, A, C>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        Ok(match self.location(tx, key, Ordering::default())? {
+            Location::Occupied { node } => Valid(node),
+            Location::Vacant(VacantLocation::Empty) => Null,
+            Location::Vacant(VacantLocation::Left { parent }) => parent.prev(tx)?,
+            Location::Vacant(VacantLocation::Right { parent }) => Valid(parent),
+        })
+    }
+
+    /// Returns the node with the least key greater than or equal to `key`.
+    pub fn ceiling<'tcell, Q>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+        key: &Q,
+    ) -> Result<RBRef<'tcell, K, V, A, C>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        Ok(match self.location(tx, key, Ordering::default())? {
+            Location::Occupied { node } => Valid(node),
+            Location::Vacant(VacantLocation::Empty) => Null,
+            Location::Vacant(VacantLocation::Left { parent }) => Valid(parent),
+            Location::Vacant(VacantLocation::Right { parent }) => parent.next(tx)?,
+        })
+    }
+
+    /// Returns the first node whose key is not before `start`.
+    fn lower_bound<'tcell, Q>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+        start: Bound<&Q>,
+    ) -> Result<RBRef<'tcell, K, V, A, C>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let mut result = Null;
+        let mut node = self.root(tx)?;
+        while let Valid(this) = node {
+            let after_start = match start {
+                Bound::Unbounded => true,
+                Bound::Included(start) => !C::less(this.key.borrow(), start),
+                Bound::Excluded(start) => C::less(start, this.key.borrow()),
+            };
+            node = if after_start {
+                result = node;
+                this.left.as_ref(tx, Ordering::default())?
+            } else {
+                this.right.as_ref(tx, Ordering::default())?
+            };
+        }
+        Ok(result)
+    }
+
+    /// Returns the vacant location between two adjacent nodes.
+    pub fn location_between<'tcell>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+        prev: RBRef<'tcell, K, V, A, C>,
+        next: RBRef<'tcell, K, V, A, C>,
+    ) -> Result<VacantLocation<'tcell, K, V, A, C>, Error> {
+        if let Valid(prev) = prev {
+            if prev.right.as_ref(tx, Ordering::default())? == Null {
+                return Ok(VacantLocation::Right { parent: prev });
+            }
+        }
+        // prev has a right subtree, so next is its leftmost node
+        Ok(match next {
+            Valid(next) => {
+                debug_assert!(next.left.as_ref(tx, Ordering::default())? == Null);
+                VacantLocation::Left { parent: next }
+            }
+            Null => VacantLocation::Empty,
+        })
+    }
+
+    pub fn range<'tx, 'tcell, Q, R, Tx>(
+        &'tcell self,
+        tx: &'tx Tx,
+        range: R,
+    ) -> RangeResult<'tx, 'tcell, K, V, A, C, Q, R, Tx>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+        R: RangeBounds<Q>,
+        Tx: Read<'tcell>,
+    {
+        let next = self.lower_bound(tx, range.start_bound())?;
+        Ok(Range {
+            tx,
+            next,
+            range,
+            phantom: PhantomData,
+        })
+    }
+
     pub fn insert<'tx, 'tcell>(
         &'tcell self,
         tx: &'tx mut impl Rw<'tcell>,
         key: K,
         value: V,
-        location: VacantLocation<'tcell, K, V>,
+        location: VacantLocation<'tcell, K, V, A, C>,
     ) -> Result<&'tx mut V, Error> {
-        let mut n = Box::new(RBNode {
-            left: RBPtr::null(),
-            right: RBPtr::null(),
-            parent_color: RBPtrColor::null_black(),
-            key,
-            value: value,
-        });
-        let n_ptr = &mut *n as *mut RBNode<K, V>;
+        let mut n = Box::new(RBNode::new(key, value));
+        let n_ptr = &mut *n as *mut RBNode<K, V, A, C>;
+        self.insert_node(tx, n, location)?;
+        Ok(&mut unsafe { &mut *n_ptr }.value)
+    }
+
+    pub fn insert_node<'tcell, N: NewNode<K, V, A, C>>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        node: N,
+        location: VacantLocation<'tcell, K, V, A, C>,
+    ) -> Result<(), Error> {
+        let node_ref = unsafe { &*(&*node as *const RBNode<K, V, A, C>) };
         let (parent, on_left) = match location {
-            VacantLocation::Empty => {
-                self.root.publish_box(tx, n)?;
-                return Ok(&mut unsafe { &mut *n_ptr }.value);
-            }
+            VacantLocation::Empty => return node.publish(tx, &self.root),
             VacantLocation::Left { parent } => (parent, true),
             VacantLocation::Right { parent } => (parent, false),
         };
-        let new_root = n.insert_fixup(tx, parent, on_left)?;
-        match new_root {
+        match RBNode::insert_fixup(node, tx, parent, on_left)? {
             RepairResult::NewRoot(new_root) => self.root.set(tx, Valid(new_root))?,
-            RepairResult::PublishRoot(root) => self.root.publish_box(tx, root)?,
+            RepairResult::PublishRoot(root) => root.publish(tx, &self.root)?,
             RepairResult::RootUnchanged => {}
         }
-        Ok(&mut unsafe { &mut *n_ptr }.value)
+        self.update_path(tx, node_ref)
+    }
+
+    /// Puts `node` in the place of `old`, which must have the same key, and frees `old` once the
+    /// transaction succeeds.
+    pub fn replace_node<'tcell, N: NewNode<K, V, A, C>>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        old: &'tcell RBNode<K, V, A, C>,
+        mut node: N,
+    ) -> Result<(), Error> {
+        debug_assert!(C::equal(&node.key, &old.key));
+        let node_ref = unsafe { &*(&*node as *const RBNode<K, V, A, C>) };
+        // read then immediate unconditional write is ReadWrite
+        if let Valid(left) = old.left.as_ref(tx, Ordering::default())? {
+            node.left.set_mut(left);
+            let color = left.parent_color.color(tx, Ordering::Read)?;
+            left.parent_color.set(tx, Valid(node_ref), color)?;
+        }
+        if let Valid(right) = old.right.as_ref(tx, Ordering::default())? {
+            node.right.set_mut(right);
+            let color = right.parent_color.color(tx, Ordering::Read)?;
+            right.parent_color.set(tx, Valid(node_ref), color)?;
+        }
+        let (parent, color) = old.parent_color.as_ref_color(tx, Ordering::default())?;
+        let side = if let Valid(parent) = parent {
+            node.parent_color.set_mut(parent, color);
+            if C::less(&old.key, &parent.key) {
+                &parent.left
+            } else {
+                &parent.right
+            }
+        } else {
+            debug_assert!(color == Color::Black);
+            &self.root
+        };
+        node.publish(tx, side)?;
+        unsafe { TPtr::privatize_as_box(tx, old) };
+        self.update_path(tx, node_ref)
+    }
+
+    /// Brings the augmentations up to date after the subtree rooted at `node` has changed.
+    ///
+    /// Rebalancing only moves nodes onto the path from the changed node to the root, or next to
+    /// it, so only the nodes on that path and their children are updated.
+    pub fn update_path<'tcell>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        node: &'tcell RBNode<K, V, A, C>,
+    ) -> Result<(), Error> {
+        if mem::size_of::<A>() == 0 {
+            return Ok(());
+        }
+        let mut prev = Null;
+        let mut node = node;
+        loop {
+            for child in &[&node.left, &node.right] {
+                if let Valid(child) = child.as_ref(tx, Ordering::default())? {
+                    if prev != child {
+                        A::update(tx, child)?;
+                    }
+                }
+            }
+            A::update(tx, node)?;
+            match node.parent_color.as_ref_color(tx, Ordering::default())?.0 {
+                Valid(parent) => {
+                    prev = Valid(node);
+                    node = parent;
+                }
+                Null => return Ok(()),
+            }
+        }
+    }
+
+    /// Brings the augmentations up to date after the value of `node` has changed.
+    pub fn update_value<'tcell>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        node: &'tcell RBNode<K, V, A, C>,
+    ) -> Result<(), Error> {
+        if mem::size_of::<A>() == 0 {
+            return Ok(());
+        }
+        A::update_value(tx, node)?;
+        self.update_path(tx, node)
     }
 
     pub fn remove<'tcell>(
         &'tcell self,
         tx: &mut impl Rw<'tcell>,
-        node: &'tcell RBNode<K, V>,
+        node: &'tcell RBNode<K, V, A, C>,
     ) -> Result<&'tcell V, Error> {
         let value = &node.value;
-        let (mut new_root, rebalance) = node.remove_nofix(tx)?;
+        let RemoveResult {
+            mut new_root,
+            rebalance,
+            changed,
+        } = node.remove_nofix(tx)?;
         if let Valid(rebalance) = rebalance {
             new_root = rebalance.remove_fixup(tx)?.map(|n| Valid(n)).or(new_root);
         }
         if let Some(new_root) = new_root {
             self.root.set(tx, new_root)?;
         };
+        if let Valid(changed) = changed {
+            self.update_path(tx, changed)?;
+        }
         Ok(value)
     }
 
-    pub fn verify<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<(), Error>
-    where
-        K: Clone,
-    {
+    /// Removes all the nodes at once, freeing them once the transaction commits.
+    pub fn clear<'tcell>(&'tcell self, tx: &mut impl Rw<'tcell>) -> Result<(), Error> {
+        if let Valid(root) = self.root(tx)? {
+            // detaching the root conflicts with every transaction that reached the other nodes
+            self.root.set(tx, Null)?;
+            root.privatize_subtree(tx)?;
+        }
+        Ok(())
+    }
+
+    pub fn verify<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<(), Error> {
         let root = self.root(tx)?;
         if let Valid(root) = root {
             root.verify(tx)?;
@@ -1261,3 +1829,53 @@ impl<K: Send + Sync + Ord + 'static, V: Send + Sync + 'static> RBRoot<K, V> {
         }
     }
 }
+
+type RangeResult<'tx, 'tcell, K, V, A, C, Q, R, Tx> =
+    Result<Range<'tx, 'tcell, K, V, A, C, Q, R, Tx>, Error>;
+
+/// An iterator over a range of nodes of a red-black tree, in ascending order.
+pub struct Range<'tx, 'tcell, K, V, A, C, Q: ?Sized, R, Tx> {
+    tx:      &'tx Tx,
+    next:    RBRef<'tcell, K, V, A, C>,
+    range:   R,
+    phantom: PhantomData<fn(&Q)>,
+}
+
+impl<'tx, 'tcell, K, V, A, C, Q, R, Tx> Iterator for Range<'tx, 'tcell, K, V, A, C, Q, R, Tx>
+where
+    K: Send + Sync + std::borrow::Borrow<Q> + 'static,
+    V: Send + Sync + 'static,
+    A: Augment<K, V>,
+    C: Compare<K> + Compare<Q>,
+    Q: ?Sized,
+    R: RangeBounds<Q>,
+    Tx: Read<'tcell>,
+{
+    type Item = Result<&'tcell RBNode<K, V, A, C>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = match self.next {
+            Valid(node) => node,
+            Null => return None,
+        };
+        let in_range = match self.range.end_bound() {
+            Bound::Unbounded => true,
+            Bound::Included(end) => !C::less(end, node.key.borrow()),
+            Bound::Excluded(end) => C::less(node.key.borrow(), end),
+        };
+        if !in_range {
+            self.next = Null;
+            return None;
+        }
+        match node.next(self.tx) {
+            Ok(next) => {
+                self.next = next;
+                Some(Ok(node))
+            }
+            Err(error) => {
+                self.next = Null;
+                Some(Err(error))
+            }
+        }
+    }
+}
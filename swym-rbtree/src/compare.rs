@@ -0,0 +1,49 @@
+//! Orderings of the keys of a tree.
+//!
+//! A tree orders its keys with a comparator type parameter instead of their `Ord` implementation,
+//! so that keys with several orderings don't need newtype wrappers.
+//!
+//! Lookups by a borrowed form `Q` of the key require the comparator to order `Q` as well, which is
+//! the case for `Natural` whenever `Q: Ord`.
+
+use std::{cmp::Ordering, marker::PhantomData};
+
+/// An ordering of values of type `T`.
+///
+/// Comparators are never constructed, so the ordering can't depend on any state. It must be a
+/// total order, and must agree with the orderings of the borrowed forms of keys used for lookups.
+pub trait Compare<T: ?Sized>: 'static {
+    fn compare(lhs: &T, rhs: &T) -> Ordering;
+
+    #[inline]
+    fn less(lhs: &T, rhs: &T) -> bool {
+        Self::compare(lhs, rhs) == Ordering::Less
+    }
+
+    #[inline]
+    fn equal(lhs: &T, rhs: &T) -> bool {
+        Self::compare(lhs, rhs) == Ordering::Equal
+    }
+}
+
+/// The `Ord` implementation of the keys.
+#[derive(Debug)]
+pub enum Natural {}
+
+impl<T: Ord + ?Sized> Compare<T> for Natural {
+    #[inline]
+    fn compare(lhs: &T, rhs: &T) -> Ordering {
+        lhs.cmp(rhs)
+    }
+}
+
+/// The reverse of the ordering `C`.
+#[derive(Debug)]
+pub struct Reverse<C = Natural>(PhantomData<C>);
+
+impl<T: ?Sized, C: Compare<T>> Compare<T> for Reverse<C> {
+    #[inline]
+    fn compare(lhs: &T, rhs: &T) -> Ordering {
+        C::compare(rhs, lhs)
+    }
+}
@@ -1,9 +1,25 @@
 #![feature(core_intrinsics)]
 #![deny(unused_must_use)]
 
+pub mod augment;
 mod base;
+pub mod compare;
+pub mod scan;
+pub mod set;
+#[cfg(feature = "serde")]
+mod serialize;
 
-use crate::base::{Location, RBNode, RBRoot, VacantLocation};
+use crate::{
+    augment::KeyOnly,
+    base::{Augment, Location, PendingNode, RBNode, RBRef, RBRoot, VacantLocation},
+    compare::{Compare, Natural},
+};
+use std::{
+    iter::FromIterator,
+    mem,
+    ops::{RangeBounds, RangeFull},
+    ptr,
+};
 use swym::{
     tcell::{Ref, TCell, View},
     thread_key,
@@ -11,8 +27,12 @@ use swym::{
     RwTx,
 };
 
-pub struct RBTreeMapRaw<K, V> {
-    pub root: RBRoot<K, TCell<V>>,
+type Node<K, V, A, C> = RBNode<K, TCell<V>, A, C>;
+type RangeResult<'tx, 'tcell, K, V, A, C, Q, R, Tx> =
+    Result<Range<'tx, 'tcell, K, V, A, C, Q, R, Tx>, Error>;
+
+pub struct RBTreeMapRaw<K, V, A = (), C = Natural> {
+    pub root: RBRoot<K, TCell<V>, A, C>,
 }
 
 impl<K, V> RBTreeMapRaw<K, V> {
@@ -23,17 +43,41 @@ impl<K, V> RBTreeMapRaw<K, V> {
     }
 }
 
-impl<K: Clone + Send + Sync + Ord + 'static, V: Send + Sync + 'static> RBTreeMapRaw<K, V> {
+impl<K, V, C> RBTreeMapRaw<K, V, (), C> {
+    /// Constructs a tree which orders its keys by `C` instead of their `Ord` implementation.
+    pub const fn with_comparator() -> Self {
+        RBTreeMapRaw {
+            root: RBRoot::new(),
+        }
+    }
+}
+
+impl<K, V, A, C> RBTreeMapRaw<K, V, A, C> {
+    /// Constructs a tree which keeps the augmentation `A` in every node, such as an
+    /// [`augment::Summary`](augment/struct.Summary.html), and orders its keys by `C`.
+    pub const fn new_augmented() -> Self {
+        RBTreeMapRaw {
+            root: RBRoot::new(),
+        }
+    }
+}
+
+impl<K, V, A, C> RBTreeMapRaw<K, V, A, C>
+where
+    K: Send + Sync + 'static,
+    V: Send + Sync + 'static,
+    A: Augment<K, TCell<V>>,
+    C: Compare<K>,
+{
     pub fn verify<'tcell, Q>(&'tcell self, tx: &impl Read<'tcell>) -> Result<(), Error>
     where
         K: std::borrow::Borrow<Q>,
-        Q: Ord + ?Sized,
+        Q: ?Sized,
+        C: Compare<Q>,
     {
         self.root.verify(tx)
     }
-}
 
-impl<K: Send + Sync + Ord + 'static, V: Send + Sync + 'static> RBTreeMapRaw<K, V> {
     pub fn contains_key<'tcell, Q>(
         &'tcell self,
         tx: &impl Read<'tcell>,
@@ -41,7 +85,8 @@ impl<K: Send + Sync + Ord + 'static, V: Send + Sync + 'static> RBTreeMapRaw<K, V
     ) -> Result<bool, Error>
     where
         K: std::borrow::Borrow<Q>,
-        Q: Ord + ?Sized,
+        Q: ?Sized,
+        C: Compare<Q>,
     {
         self.root.contains_key(tx, key)
     }
@@ -50,7 +95,10 @@ impl<K: Send + Sync + Ord + 'static, V: Send + Sync + 'static> RBTreeMapRaw<K, V
         &'tcell self,
         tx: &'tx mut RwTx<'tcell>,
         key: K,
-    ) -> Result<Entry<'tx, 'tcell, K, V>, Error> {
+    ) -> Result<Entry<'tx, 'tcell, K, V, A, C>, Error>
+    where
+        A: KeyOnly,
+    {
         Ok(match self.root.location(tx, &key, Ordering::default())? {
             Location::Vacant(location) => Entry::Vacant(VacantEntry {
                 location,
@@ -68,7 +116,13 @@ impl<K: Send + Sync + Ord + 'static, V: Send + Sync + 'static> RBTreeMapRaw<K, V
     }
 }
 
-impl<K: Send + Sync + Ord + 'static, V: Borrow + Send + Sync + 'static> RBTreeMapRaw<K, V> {
+impl<
+        K: Send + Sync + 'static,
+        V: Borrow + Send + Sync + 'static,
+        A: Augment<K, TCell<V>>,
+        C: Compare<K>,
+    > RBTreeMapRaw<K, V, A, C>
+{
     pub fn get<'tx, 'tcell, Q>(
         &'tcell self,
         tx: &'tx impl Read<'tcell>,
@@ -76,7 +130,8 @@ impl<K: Send + Sync + Ord + 'static, V: Borrow + Send + Sync + 'static> RBTreeMa
     ) -> Result<Option<Ref<'tx, V>>, Error>
     where
         K: std::borrow::Borrow<Q>,
-        Q: Ord + ?Sized,
+        Q: ?Sized,
+        C: Compare<Q>,
     {
         let loc = self.root.location(tx, &key, Ordering::default())?;
         let result = match loc {
@@ -93,7 +148,9 @@ impl<K: Send + Sync + Ord + 'static, V: Borrow + Send + Sync + 'static> RBTreeMa
     ) -> Result<Option<View<'tcell, V, &'tx mut RwTx<'tcell>>>, Error>
     where
         K: std::borrow::Borrow<Q>,
-        Q: Ord + ?Sized,
+        Q: ?Sized,
+        C: Compare<Q>,
+        A: KeyOnly,
     {
         let loc = self.root.location(tx, &key, Ordering::default())?;
         let result = match loc {
@@ -104,7 +161,13 @@ impl<K: Send + Sync + Ord + 'static, V: Borrow + Send + Sync + 'static> RBTreeMa
     }
 }
 
-impl<K: Send + Sync + Ord + 'static, V: Borrow + Clone + Send + Sync + 'static> RBTreeMapRaw<K, V> {
+impl<
+        K: Send + Sync + 'static,
+        V: Borrow + Clone + Send + Sync + 'static,
+        A: Augment<K, TCell<V>>,
+        C: Compare<K>,
+    > RBTreeMapRaw<K, V, A, C>
+{
     pub fn insert<'tcell>(
         &'tcell self,
         tx: &mut RwTx<'tcell>,
@@ -117,13 +180,66 @@ impl<K: Send + Sync + Ord + 'static, V: Borrow + Clone + Send + Sync + 'static>
                 self.root.insert(tx, key, TCell::new(value), vacant)?;
                 None
             }
-            Location::Occupied { node } => Some(node.value.replace(tx, value)?),
+            Location::Occupied { node } => {
+                let value = node.value.replace(tx, value)?;
+                self.root.update_value(tx, node)?;
+                Some(value)
+            }
         };
         Ok(result)
     }
 }
 
-impl<K: Send + Sync + Ord + 'static, V: Borrow + Send + Sync + 'static> RBTreeMapRaw<K, V> {
+impl<
+        K: Send + Sync + 'static,
+        V: Borrow + Send + Sync + 'static,
+        A: Augment<K, TCell<V>>,
+        C: Compare<K>,
+    > RBTreeMapRaw<K, V, A, C>
+{
+    /// Inserts an entry, dropping the previous value for the key, if any.
+    ///
+    /// Returns true if the key was already present. Unlike `insert`, this doesn't need to copy the
+    /// previous value out of the tree.
+    pub fn set<'tcell>(
+        &'tcell self,
+        tx: &mut RwTx<'tcell>,
+        key: K,
+        value: V,
+    ) -> Result<bool, Error> {
+        let loc = self.root.location(tx, &key, Ordering::Read)?;
+        Ok(match loc {
+            Location::Vacant(vacant) => {
+                self.root.insert(tx, key, TCell::new(value), vacant)?;
+                false
+            }
+            Location::Occupied { node } => {
+                node.value.set(tx, value)?;
+                self.root.update_value(tx, node)?;
+                true
+            }
+        })
+    }
+
+    // inserts a node owned by the caller, returning the node it replaced
+    fn insert_pending<'tcell>(
+        &'tcell self,
+        tx: &mut RwTx<'tcell>,
+        node: PendingNode<'_, K, TCell<V>, A, C>,
+    ) -> Result<Option<&'tcell Node<K, V, A, C>>, Error> {
+        let loc = self.root.location(tx, &node.key, Ordering::Read)?;
+        Ok(match loc {
+            Location::Vacant(vacant) => {
+                self.root.insert_node(tx, node, vacant)?;
+                None
+            }
+            Location::Occupied { node: old } => {
+                self.root.replace_node(tx, old, node)?;
+                Some(old)
+            }
+        })
+    }
+
     pub fn remove<'tcell, Q>(
         &'tcell self,
         tx: &mut RwTx<'tcell>,
@@ -131,7 +247,7 @@ impl<K: Send + Sync + Ord + 'static, V: Borrow + Send + Sync + 'static> RBTreeMa
     ) -> Result<Option<Ref<'tcell, V>>, Error>
     where
         K: std::borrow::Borrow<Q>,
-        Q: Ord,
+        C: Compare<Q>,
     {
         let loc = self.root.location(tx, &key, Ordering::Read)?;
         match loc {
@@ -146,8 +262,384 @@ impl<K: Send + Sync + Ord + 'static, V: Borrow + Send + Sync + 'static> RBTreeMa
     }
 }
 
-pub struct RBTreeMap<K, V> {
-    pub raw: RBTreeMapRaw<K, V>,
+impl<K, V, A, C> RBTreeMapRaw<K, V, A, C>
+where
+    K: Borrow + Send + Sync + 'static,
+    V: Borrow + Send + Sync + 'static,
+    A: Augment<K, TCell<V>>,
+    C: Compare<K>,
+{
+    pub fn first_key_value<'tx, 'tcell>(
+        &'tcell self,
+        tx: &'tx impl Read<'tcell>,
+    ) -> Result<Option<KeyValue<'tx, K, V>>, Error>
+    where
+        'tcell: 'tx,
+    {
+        match self.root.first(tx)?.into_option() {
+            Some(node) => Ok(Some(key_value(tx, node)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn last_key_value<'tx, 'tcell>(
+        &'tcell self,
+        tx: &'tx impl Read<'tcell>,
+    ) -> Result<Option<KeyValue<'tx, K, V>>, Error>
+    where
+        'tcell: 'tx,
+    {
+        match self.root.last(tx)?.into_option() {
+            Some(node) => Ok(Some(key_value(tx, node)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the entry with the greatest key less than or equal to `key`.
+    pub fn floor<'tx, 'tcell, Q>(
+        &'tcell self,
+        tx: &'tx impl Read<'tcell>,
+        key: &Q,
+    ) -> Result<Option<KeyValue<'tx, K, V>>, Error>
+    where
+        'tcell: 'tx,
+        K: std::borrow::Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        match self.root.floor(tx, key)?.into_option() {
+            Some(node) => Ok(Some(key_value(tx, node)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the entry with the least key greater than or equal to `key`.
+    pub fn ceiling<'tx, 'tcell, Q>(
+        &'tcell self,
+        tx: &'tx impl Read<'tcell>,
+        key: &Q,
+    ) -> Result<Option<KeyValue<'tx, K, V>>, Error>
+    where
+        'tcell: 'tx,
+        K: std::borrow::Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        match self.root.ceiling(tx, key)?.into_option() {
+            Some(node) => Ok(Some(key_value(tx, node)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Removes and returns the entry with the least key.
+    pub fn pop_first<'tcell>(
+        &'tcell self,
+        tx: &mut RwTx<'tcell>,
+    ) -> Result<Option<KeyValue<'tcell, K, V>>, Error> {
+        match self.root.first(tx)?.into_option() {
+            Some(node) => Ok(Some(self.pop(tx, node)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Removes and returns the entry with the greatest key.
+    pub fn pop_last<'tcell>(
+        &'tcell self,
+        tx: &mut RwTx<'tcell>,
+    ) -> Result<Option<KeyValue<'tcell, K, V>>, Error> {
+        match self.root.last(tx)?.into_option() {
+            Some(node) => Ok(Some(self.pop(tx, node)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Removes the entries for which `f` returns false, in a single walk over the tree.
+    pub fn retain<'tcell, F>(&'tcell self, tx: &mut RwTx<'tcell>, mut f: F) -> Result<(), Error>
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        self.drain_filter_impl(tx, |key, value| !f(key, value), |_| {})
+    }
+
+    /// Removes and returns the entries for which `f` returns true, in ascending order, in a single
+    /// walk over the tree.
+    pub fn drain_filter<'tcell, F>(
+        &'tcell self,
+        tx: &mut RwTx<'tcell>,
+        f: F,
+    ) -> Result<Vec<KeyValue<'tcell, K, V>>, Error>
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        let mut drained = Vec::new();
+        self.drain_filter_impl(tx, f, |entry| drained.push(entry))?;
+        Ok(drained)
+    }
+
+    fn drain_filter_impl<'tcell, F, D>(
+        &'tcell self,
+        tx: &mut RwTx<'tcell>,
+        mut f: F,
+        mut drained: D,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(&K, &V) -> bool,
+        D: FnMut(KeyValue<'tcell, K, V>),
+    {
+        let mut node = self.root.first(tx)?;
+        while let RBRef::Valid(this) = node {
+            // nodes are only relinked by rebalancing, so the next node stays valid
+            node = this.next(tx)?;
+            if f(&this.key, &*this.value.borrow(tx, Ordering::default())?) {
+                drained(self.pop(tx, this)?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes all the entries.
+    pub fn clear<'tcell>(&'tcell self, tx: &mut RwTx<'tcell>) -> Result<(), Error> {
+        self.root.clear(tx)
+    }
+
+    fn pop<'tcell>(
+        &'tcell self,
+        tx: &mut RwTx<'tcell>,
+        node: &'tcell RBNode<K, TCell<V>, A, C>,
+    ) -> Result<KeyValue<'tcell, K, V>, Error> {
+        let value = self.root.remove(tx, node)?;
+        let value = unsafe { Ref::downcast(value.borrow(tx, Ordering::default())?, tx) };
+        Ok((Ref::from(&node.key), value))
+    }
+
+    /// Returns an iterator over the entries of the tree, in ascending order.
+    pub fn iter<'tx, 'tcell, Tx>(
+        &'tcell self,
+        tx: &'tx Tx,
+    ) -> Result<Iter<'tx, 'tcell, K, V, A, C, Tx>, Error>
+    where
+        Tx: Read<'tcell>,
+    {
+        self.range(tx, ..)
+    }
+
+    /// Returns an iterator over the entries whose keys are in `range`, in ascending order.
+    pub fn range<'tx, 'tcell, Q, R, Tx>(
+        &'tcell self,
+        tx: &'tx Tx,
+        range: R,
+    ) -> RangeResult<'tx, 'tcell, K, V, A, C, Q, R, Tx>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+        R: RangeBounds<Q>,
+        Tx: Read<'tcell>,
+    {
+        Ok(Range {
+            tx,
+            nodes: self.root.range(tx, range)?,
+        })
+    }
+
+    /// Returns an iterator over the keys of the tree, in ascending order.
+    pub fn keys<'tx, 'tcell, Tx>(
+        &'tcell self,
+        tx: &'tx Tx,
+    ) -> Result<Keys<'tx, 'tcell, K, V, A, C, Tx>, Error>
+    where
+        Tx: Read<'tcell>,
+    {
+        Ok(Keys {
+            nodes: self.root.range(tx, ..)?,
+        })
+    }
+
+    /// Returns an iterator over the values of the tree, in ascending order of their keys.
+    pub fn values<'tx, 'tcell, Tx>(
+        &'tcell self,
+        tx: &'tx Tx,
+    ) -> Result<Values<'tx, 'tcell, K, V, A, C, Tx>, Error>
+    where
+        Tx: Read<'tcell>,
+    {
+        Ok(Values {
+            tx,
+            nodes: self.root.range(tx, ..)?,
+        })
+    }
+}
+
+impl<K, V, A, C> RBTreeMapRaw<K, V, A, C>
+where
+    K: Borrow + Clone + Send + Sync + 'static,
+    V: Borrow + Clone + Send + Sync + 'static,
+    A: Augment<K, TCell<V>>,
+    C: Compare<K>,
+{
+    /// Moves the entries with keys greater than or equal to `key` into `other`, replacing the
+    /// entries of `other` with the same keys.
+    ///
+    /// Other threads may still be reading the moved entries, so `other` receives copies of them,
+    /// and the originals are freed like removed entries.
+    pub fn split_off<'tcell, Q>(
+        &'tcell self,
+        tx: &mut RwTx<'tcell>,
+        key: &Q,
+        other: &'tcell Self,
+    ) -> Result<(), Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        if ptr::eq(self, other) {
+            return Ok(());
+        }
+        while let Some(node) = self.root.last(tx)?.into_option() {
+            if C::less(node.key.borrow(), key) {
+                break;
+            }
+            self.move_entry(tx, node, other)?;
+        }
+        Ok(())
+    }
+
+    /// Moves all the entries of `other` into `self`, replacing the entries with the same keys.
+    ///
+    /// Like `split_off`, this copies the entries.
+    pub fn append<'tcell>(
+        &'tcell self,
+        tx: &mut RwTx<'tcell>,
+        other: &'tcell Self,
+    ) -> Result<(), Error> {
+        if ptr::eq(self, other) {
+            return Ok(());
+        }
+        while let Some(node) = other.root.first(tx)?.into_option() {
+            other.move_entry(tx, node, self)?;
+        }
+        Ok(())
+    }
+
+    fn move_entry<'tcell>(
+        &'tcell self,
+        tx: &mut RwTx<'tcell>,
+        node: &'tcell Node<K, V, A, C>,
+        other: &'tcell Self,
+    ) -> Result<(), Error> {
+        let value = self.root.remove(tx, node)?;
+        let value = value.borrow(tx, Ordering::default())?.clone();
+        other.set(tx, node.key.clone(), value)?;
+        Ok(())
+    }
+}
+
+/// A key and value borrowed from a `RBTreeMapRaw`.
+pub type KeyValue<'tx, K, V> = (Ref<'tx, K>, Ref<'tx, V>);
+
+fn key_value<'tx, 'tcell, K, V, A, C>(
+    tx: &'tx impl Read<'tcell>,
+    node: &'tcell RBNode<K, TCell<V>, A, C>,
+) -> Result<KeyValue<'tx, K, V>, Error>
+where
+    'tcell: 'tx,
+    K: Borrow,
+    V: Borrow + Send + Sync + 'static,
+{
+    let value = node.value.borrow(tx, Ordering::default())?;
+    Ok((Ref::from(&node.key), value))
+}
+
+/// An iterator over a range of entries of a `RBTreeMapRaw`, in ascending order.
+///
+/// An item is an error if another thread has modified the tree during the current transaction.
+pub struct Range<'tx, 'tcell, K, V, A, C, Q: ?Sized, R, Tx> {
+    tx:    &'tx Tx,
+    nodes: base::Range<'tx, 'tcell, K, TCell<V>, A, C, Q, R, Tx>,
+}
+
+impl<'tx, 'tcell, K, V, A, C, Q, R, Tx> Iterator for Range<'tx, 'tcell, K, V, A, C, Q, R, Tx>
+where
+    'tcell: 'tx,
+    K: Borrow + Send + Sync + std::borrow::Borrow<Q> + 'static,
+    V: Borrow + Send + Sync + 'static,
+    A: Augment<K, TCell<V>>,
+    C: Compare<K>,
+    Q: ?Sized,
+    C: Compare<Q>,
+    R: RangeBounds<Q>,
+    Tx: Read<'tcell>,
+{
+    type Item = Result<KeyValue<'tx, K, V>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = match self.nodes.next()? {
+            Ok(node) => node,
+            Err(error) => return Some(Err(error)),
+        };
+        Some(
+            node.value
+                .borrow(self.tx, Ordering::default())
+                .map(|value| (Ref::from(&node.key), value)),
+        )
+    }
+}
+
+/// An iterator over the entries of a `RBTreeMapRaw`, in ascending order.
+pub type Iter<'tx, 'tcell, K, V, A, C, Tx> = Range<'tx, 'tcell, K, V, A, C, K, RangeFull, Tx>;
+
+/// An iterator over the keys of a `RBTreeMapRaw`, in ascending order.
+///
+/// An item is an error if another thread has modified the tree during the current transaction.
+pub struct Keys<'tx, 'tcell, K, V, A, C, Tx> {
+    nodes: base::Range<'tx, 'tcell, K, TCell<V>, A, C, K, RangeFull, Tx>,
+}
+
+impl<'tx, 'tcell, K, V, A, C, Tx> Iterator for Keys<'tx, 'tcell, K, V, A, C, Tx>
+where
+    'tcell: 'tx,
+    K: Borrow + Send + Sync + 'static,
+    V: Send + Sync + 'static,
+    A: Augment<K, TCell<V>>,
+    C: Compare<K>,
+    Tx: Read<'tcell>,
+{
+    type Item = Result<Ref<'tx, K>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.nodes.next()?.map(|node| Ref::from(&node.key)))
+    }
+}
+
+/// An iterator over the values of a `RBTreeMapRaw`, in ascending order of their keys.
+///
+/// An item is an error if another thread has modified the tree during the current transaction.
+pub struct Values<'tx, 'tcell, K, V, A, C, Tx> {
+    tx:    &'tx Tx,
+    nodes: base::Range<'tx, 'tcell, K, TCell<V>, A, C, K, RangeFull, Tx>,
+}
+
+impl<'tx, 'tcell, K, V, A, C, Tx> Iterator for Values<'tx, 'tcell, K, V, A, C, Tx>
+where
+    K: Send + Sync + 'static,
+    V: Borrow + Send + Sync + 'static,
+    A: Augment<K, TCell<V>>,
+    C: Compare<K>,
+    Tx: Read<'tcell>,
+{
+    type Item = Result<Ref<'tx, V>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(match self.nodes.next()? {
+            Ok(node) => node.value.borrow(self.tx, Ordering::default()),
+            Err(error) => Err(error),
+        })
+    }
+}
+
+pub struct RBTreeMap<K, V, A = (), C = Natural> {
+    pub raw: RBTreeMapRaw<K, V, A, C>,
 }
 
 impl<K, V> RBTreeMap<K, V> {
@@ -158,11 +650,36 @@ impl<K, V> RBTreeMap<K, V> {
     }
 }
 
-impl<K: Send + Sync + Ord + 'static, V: Borrow + Send + Sync + 'static> RBTreeMap<K, V> {
+impl<K, V, C> RBTreeMap<K, V, (), C> {
+    /// Constructs a tree which orders its keys by `C` instead of their `Ord` implementation.
+    pub const fn with_comparator() -> Self {
+        RBTreeMap {
+            raw: RBTreeMapRaw::with_comparator(),
+        }
+    }
+}
+
+impl<K, V, A, C> RBTreeMap<K, V, A, C> {
+    /// Constructs a tree which keeps the augmentation `A` in every node, such as an
+    /// [`augment::Summary`](augment/struct.Summary.html), and orders its keys by `C`.
+    pub const fn new_augmented() -> Self {
+        RBTreeMap {
+            raw: RBTreeMapRaw::new_augmented(),
+        }
+    }
+}
+
+impl<
+        K: Send + Sync + 'static,
+        V: Borrow + Send + Sync + 'static,
+        A: Augment<K, TCell<V>>,
+        C: Compare<K>,
+    > RBTreeMap<K, V, A, C>
+{
     pub fn with<'tx, 'tcell>(
         &'tcell self,
         tx: &'tx mut RwTx<'tcell>,
-    ) -> RBTreeWith<'tx, 'tcell, K, V> {
+    ) -> RBTreeWith<'tx, 'tcell, K, V, A, C> {
         RBTreeWith {
             tree: &self.raw,
             tx,
@@ -171,7 +688,7 @@ impl<K: Send + Sync + Ord + 'static, V: Borrow + Send + Sync + 'static> RBTreeMa
 
     pub fn atomic<F, R>(&self, mut f: F) -> R
     where
-        F: for<'tx, 'tcell> FnMut(RBTreeWith<'tx, 'tcell, K, V>) -> Result<R, Status>,
+        F: for<'tx, 'tcell> FnMut(RBTreeWith<'tx, 'tcell, K, V, A, C>) -> Result<R, Status>,
     {
         thread_key::get().rw(move |tx| f(self.with(tx)))
     }
@@ -179,7 +696,8 @@ impl<K: Send + Sync + Ord + 'static, V: Borrow + Send + Sync + 'static> RBTreeMa
     pub fn contains_key<Q>(&self, key: &Q) -> bool
     where
         K: std::borrow::Borrow<Q>,
-        Q: Ord + ?Sized,
+        Q: ?Sized,
+        C: Compare<Q>,
     {
         thread_key::get().read(move |tx| Ok(self.raw.contains_key(tx, key)?))
     }
@@ -187,7 +705,8 @@ impl<K: Send + Sync + Ord + 'static, V: Borrow + Send + Sync + 'static> RBTreeMa
     pub fn get<Q>(&self, key: &Q) -> Option<V>
     where
         K: std::borrow::Borrow<Q>,
-        Q: Ord + ?Sized,
+        Q: ?Sized,
+        C: Compare<Q>,
         V: Clone,
     {
         thread_key::get().read(move |tx| {
@@ -198,17 +717,40 @@ impl<K: Send + Sync + Ord + 'static, V: Borrow + Send + Sync + 'static> RBTreeMa
 
     pub fn insert(&self, key: K, value: V) -> Option<V>
     where
-        K: Clone,
         V: Clone,
     {
-        // todo: remove these clones
-        self.atomic(move |mut tree| Ok(tree.insert(key.clone(), value.clone())?))
+        self.insert_with(key, value, V::clone)
+    }
+
+    /// Inserts an entry, dropping the previous value for the key, if any.
+    ///
+    /// Returns true if the key was already present.
+    pub fn set(&self, key: K, value: V) -> bool {
+        self.insert_with(key, value, |_| ()).is_some()
+    }
+
+    // the node is built before the transaction and only handed over to the tree once it commits, so
+    // retries can reuse it instead of needing copies of the key and value
+    fn insert_with<F, R>(&self, key: K, value: V, mut f: F) -> Option<R>
+    where
+        F: FnMut(&V) -> R,
+    {
+        let mut node = Box::new(RBNode::new(key, TCell::new(value)));
+        let result = thread_key::get().rw(|tx| {
+            let old = self.raw.insert_pending(tx, PendingNode::new(&mut node))?;
+            Ok(match old {
+                Some(old) => Some(f(&*old.value.borrow(tx, Ordering::default())?)),
+                None => None,
+            })
+        });
+        mem::forget(node);
+        result
     }
 
     pub fn remove<Q>(&self, key: &Q) -> Option<V>
     where
         K: std::borrow::Borrow<Q>,
-        Q: Ord,
+        C: Compare<Q>,
         V: Clone,
     {
         self.atomic(move |mut tree| {
@@ -216,22 +758,267 @@ impl<K: Send + Sync + Ord + 'static, V: Borrow + Send + Sync + 'static> RBTreeMa
             Ok(value.map(|value| value.clone()))
         })
     }
+
+    pub fn first_key_value(&self) -> Option<(K, V)>
+    where
+        K: Borrow + Clone,
+        V: Clone,
+    {
+        thread_key::get().read(move |tx| {
+            let r = self.raw.first_key_value(tx)?;
+            Ok(r.map(|(key, value)| (key.clone(), value.clone())))
+        })
+    }
+
+    pub fn last_key_value(&self) -> Option<(K, V)>
+    where
+        K: Borrow + Clone,
+        V: Clone,
+    {
+        thread_key::get().read(move |tx| {
+            let r = self.raw.last_key_value(tx)?;
+            Ok(r.map(|(key, value)| (key.clone(), value.clone())))
+        })
+    }
+
+    /// Returns a copy of the entry with the greatest key less than or equal to `key`.
+    pub fn floor<Q>(&self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow + std::borrow::Borrow<Q> + Clone,
+        V: Clone,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        thread_key::get().read(move |tx| {
+            let r = self.raw.floor(tx, key)?;
+            Ok(r.map(|(key, value)| (key.clone(), value.clone())))
+        })
+    }
+
+    /// Returns a copy of the entry with the least key greater than or equal to `key`.
+    pub fn ceiling<Q>(&self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow + std::borrow::Borrow<Q> + Clone,
+        V: Clone,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        thread_key::get().read(move |tx| {
+            let r = self.raw.ceiling(tx, key)?;
+            Ok(r.map(|(key, value)| (key.clone(), value.clone())))
+        })
+    }
+
+    pub fn pop_first(&self) -> Option<(K, V)>
+    where
+        K: Borrow + Clone,
+        V: Clone,
+    {
+        self.atomic(move |mut tree| {
+            let r = tree.pop_first()?;
+            Ok(r.map(|(key, value)| (key.clone(), value.clone())))
+        })
+    }
+
+    pub fn pop_last(&self) -> Option<(K, V)>
+    where
+        K: Borrow + Clone,
+        V: Clone,
+    {
+        self.atomic(move |mut tree| {
+            let r = tree.pop_last()?;
+            Ok(r.map(|(key, value)| (key.clone(), value.clone())))
+        })
+    }
+
+    /// Returns a copy of the entries of the tree, in ascending order.
+    pub fn to_vec(&self) -> Vec<(K, V)>
+    where
+        K: Borrow + Clone,
+        V: Clone,
+    {
+        self.range(..)
+    }
+
+    /// Returns a copy of the entries whose keys are in `range`, in ascending order.
+    pub fn range<Q, R>(&self, range: R) -> Vec<(K, V)>
+    where
+        K: Borrow + std::borrow::Borrow<Q> + Clone,
+        V: Clone,
+        Q: ?Sized,
+        C: Compare<Q>,
+        R: RangeBounds<Q> + Clone,
+    {
+        thread_key::get().read(move |tx| {
+            self.raw
+                .range(tx, range.clone())?
+                .map(|entry| entry.map(|(key, value)| (key.clone(), value.clone())))
+                .collect()
+        })
+    }
+
+    /// Returns a copy of the keys of the tree, in ascending order.
+    pub fn keys(&self) -> Vec<K>
+    where
+        K: Borrow + Clone,
+    {
+        thread_key::get().read(move |tx| {
+            self.raw
+                .keys(tx)?
+                .map(|key| key.map(|key| key.clone()))
+                .collect()
+        })
+    }
+
+    /// Returns a copy of the values of the tree, in ascending order of their keys.
+    pub fn values(&self) -> Vec<V>
+    where
+        K: Borrow,
+        V: Clone,
+    {
+        thread_key::get().read(move |tx| {
+            self.raw
+                .values(tx)?
+                .map(|value| value.map(|value| value.clone()))
+                .collect()
+        })
+    }
+}
+
+impl<
+        K: Borrow + Clone + Send + Sync + 'static,
+        V: Borrow + Clone + Send + Sync + 'static,
+        A: Augment<K, TCell<V>>,
+        C: Compare<K>,
+    > RBTreeMap<K, V, A, C>
+{
+    /// Moves the entries with keys greater than or equal to `key` into a new tree.
+    pub fn split_off<Q>(&self, key: &Q) -> Self
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let other = RBTreeMap::new_augmented();
+        thread_key::get().rw(|tx| Ok(self.raw.split_off(tx, key, &other.raw)?));
+        other
+    }
+
+    /// Moves all the entries of `other` into `self`, replacing the entries with the same keys.
+    pub fn append(&self, other: &Self) {
+        thread_key::get().rw(|tx| Ok(self.raw.append(tx, &other.raw)?))
+    }
+}
+
+/// Builds a balanced tree without any transactions.
+///
+/// Sorting is linear when the entries are already in ascending order of their keys. Of several
+/// entries with the same key, the last one is kept.
+impl<K, V, A, C> FromIterator<(K, V)> for RBTreeMapRaw<K, V, A, C>
+where
+    K: Send + Sync + 'static,
+    V: Send + Sync + 'static,
+    A: Augment<K, TCell<V>>,
+    C: Compare<K>,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut entries: Vec<_> = iter.into_iter().collect();
+        // stable, so equal keys stay in the order they were given
+        entries.sort_by(|(a, _), (b, _)| C::compare(a, b));
+        entries.dedup_by(|next, prev| {
+            let same_key = C::equal(&next.0, &prev.0);
+            if same_key {
+                mem::swap(next, prev);
+            }
+            same_key
+        });
+        let entries = entries
+            .into_iter()
+            .map(|(key, value)| (key, TCell::new(value)));
+        RBTreeMapRaw {
+            root: RBRoot::from_sorted(entries),
+        }
+    }
 }
 
-pub struct RBTreeWith<'tx, 'tcell, K, V> {
-    pub tree: &'tcell RBTreeMapRaw<K, V>,
+impl<K, V, A, C> FromIterator<(K, V)> for RBTreeMap<K, V, A, C>
+where
+    K: Send + Sync + 'static,
+    V: Send + Sync + 'static,
+    A: Augment<K, TCell<V>>,
+    C: Compare<K>,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        RBTreeMap {
+            raw: iter.into_iter().collect(),
+        }
+    }
+}
+
+// the number of entries inserted by each transaction of `extend`
+const EXTEND_BATCH_LEN: usize = 256;
+
+/// Inserts the entries in batches, each batch in a single transaction.
+impl<K, V, A, C> Extend<(K, V)> for &RBTreeMap<K, V, A, C>
+where
+    K: Send + Sync + 'static,
+    V: Borrow + Send + Sync + 'static,
+    A: Augment<K, TCell<V>>,
+    C: Compare<K>,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        let mut iter = iter.into_iter();
+        loop {
+            // as in `insert`, the nodes are only handed over to the tree once the transaction
+            // commits
+            let mut nodes: Vec<_> = iter
+                .by_ref()
+                .take(EXTEND_BATCH_LEN)
+                .map(|(key, value)| Box::new(RBNode::new(key, TCell::new(value))))
+                .collect();
+            if nodes.is_empty() {
+                break;
+            }
+            thread_key::get().rw(|tx| {
+                for node in &mut nodes {
+                    self.raw.insert_pending(tx, PendingNode::new(node))?;
+                }
+                Ok(())
+            });
+            nodes.into_iter().for_each(mem::forget);
+        }
+    }
+}
+
+impl<K, V, A, C> Extend<(K, V)> for RBTreeMap<K, V, A, C>
+where
+    K: Send + Sync + 'static,
+    V: Borrow + Send + Sync + 'static,
+    A: Augment<K, TCell<V>>,
+    C: Compare<K>,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        (&*self).extend(iter)
+    }
+}
+
+pub struct RBTreeWith<'tx, 'tcell, K, V, A = (), C = Natural> {
+    pub tree: &'tcell RBTreeMapRaw<K, V, A, C>,
     pub tx:   &'tx mut RwTx<'tcell>,
 }
 
-impl<'tx, 'tcell, K, V> RBTreeWith<'tx, 'tcell, K, V>
+impl<'tx, 'tcell, K, V, A, C> RBTreeWith<'tx, 'tcell, K, V, A, C>
 where
-    K: Send + Sync + Ord + 'static,
+    K: Send + Sync + 'static,
     V: Borrow + Send + Sync + 'static,
+    A: Augment<K, TCell<V>>,
+    C: Compare<K>,
 {
     pub fn get<Q>(&self, key: &Q) -> Result<Option<Ref<'_, V>>, Error>
     where
         K: std::borrow::Borrow<Q>,
-        Q: Ord + ?Sized,
+        Q: ?Sized,
+        C: Compare<Q>,
     {
         self.tree.get(self.tx, key)
     }
@@ -239,12 +1026,16 @@ where
     pub fn contains_key<Q>(&self, key: &Q) -> Result<bool, Error>
     where
         K: std::borrow::Borrow<Q>,
-        Q: Ord + ?Sized,
+        Q: ?Sized,
+        C: Compare<Q>,
     {
         self.tree.contains_key(self.tx, key)
     }
 
-    pub fn entry<'a>(&'a mut self, key: K) -> Result<Entry<'a, 'tcell, K, V>, Error> {
+    pub fn entry<'a>(&'a mut self, key: K) -> Result<Entry<'a, 'tcell, K, V, A, C>, Error>
+    where
+        A: KeyOnly,
+    {
         self.tree.entry(self.tx, key)
     }
 
@@ -255,26 +1046,324 @@ where
         self.tree.insert(self.tx, key, value)
     }
 
+    pub fn set(&mut self, key: K, value: V) -> Result<bool, Error> {
+        self.tree.set(self.tx, key, value)
+    }
+
     pub fn remove<'a, Q>(&'a mut self, key: &Q) -> Result<Option<Ref<'tx, V>>, Error>
     where
         K: std::borrow::Borrow<Q>,
-        Q: Ord,
+        C: Compare<Q>,
     {
         self.tree.remove(self.tx, key)
     }
 }
 
-pub struct VacantEntry<'tx, 'tcell, K, V> {
-    location: VacantLocation<'tcell, K, TCell<V>>,
-    tree:     &'tcell RBTreeMapRaw<K, V>,
+impl<'tx, 'tcell, K, V, A, C> RBTreeWith<'tx, 'tcell, K, V, A, C>
+where
+    K: Borrow + Send + Sync + 'static,
+    V: Borrow + Send + Sync + 'static,
+    A: Augment<K, TCell<V>>,
+    C: Compare<K>,
+{
+    pub fn first_key_value(&self) -> Result<Option<KeyValue<'_, K, V>>, Error> {
+        self.tree.first_key_value(self.tx)
+    }
+
+    pub fn last_key_value(&self) -> Result<Option<KeyValue<'_, K, V>>, Error> {
+        self.tree.last_key_value(self.tx)
+    }
+
+    pub fn split_off<Q>(
+        &mut self,
+        key: &Q,
+        other: &'tcell RBTreeMapRaw<K, V, A, C>,
+    ) -> Result<(), Error>
+    where
+        K: std::borrow::Borrow<Q> + Clone,
+        V: Clone,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.tree.split_off(self.tx, key, other)
+    }
+
+    pub fn append(&mut self, other: &'tcell RBTreeMapRaw<K, V, A, C>) -> Result<(), Error>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.tree.append(self.tx, other)
+    }
+
+    pub fn floor<Q>(&self, key: &Q) -> Result<Option<KeyValue<'_, K, V>>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.tree.floor(self.tx, key)
+    }
+
+    pub fn ceiling<Q>(&self, key: &Q) -> Result<Option<KeyValue<'_, K, V>>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.tree.ceiling(self.tx, key)
+    }
+
+    pub fn pop_first(&mut self) -> Result<Option<KeyValue<'tx, K, V>>, Error> {
+        self.tree.pop_first(self.tx)
+    }
+
+    pub fn pop_last(&mut self) -> Result<Option<KeyValue<'tx, K, V>>, Error> {
+        self.tree.pop_last(self.tx)
+    }
+
+    /// Removes the entries for which `f` returns false.
+    pub fn retain<F>(&mut self, f: F) -> Result<(), Error>
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        self.tree.retain(self.tx, f)
+    }
+
+    /// Removes and returns the entries for which `f` returns true, in ascending order.
+    pub fn drain_filter<F>(&mut self, f: F) -> Result<Vec<KeyValue<'tx, K, V>>, Error>
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        self.tree.drain_filter(self.tx, f)
+    }
+
+    pub fn clear(&mut self) -> Result<(), Error> {
+        self.tree.clear(self.tx)
+    }
+
+    /// Returns a cursor pointing at the entry with the least key.
+    pub fn cursor_front(&mut self) -> Result<CursorMut<'_, 'tcell, K, V, A, C>, Error> {
+        let node = self.tree.root.first(self.tx)?;
+        Ok(CursorMut {
+            tree: self.tree,
+            tx: self.tx,
+            node,
+        })
+    }
+
+    /// Returns a cursor pointing at the entry with the greatest key.
+    pub fn cursor_back(&mut self) -> Result<CursorMut<'_, 'tcell, K, V, A, C>, Error> {
+        let node = self.tree.root.last(self.tx)?;
+        Ok(CursorMut {
+            tree: self.tree,
+            tx: self.tx,
+            node,
+        })
+    }
+
+    /// Returns a cursor pointing at the entry with the least key greater than or equal to `key`.
+    pub fn cursor_at<Q>(&mut self, key: &Q) -> Result<CursorMut<'_, 'tcell, K, V, A, C>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let node = self.tree.root.ceiling(self.tx, key)?;
+        Ok(CursorMut {
+            tree: self.tree,
+            tx: self.tx,
+            node,
+        })
+    }
+
+    pub fn iter(&self) -> Result<Iter<'_, 'tcell, K, V, A, C, RwTx<'tcell>>, Error> {
+        self.tree.iter(self.tx)
+    }
+
+    pub fn range<'a, Q, R>(
+        &'a self,
+        range: R,
+    ) -> RangeResult<'a, 'tcell, K, V, A, C, Q, R, RwTx<'tcell>>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+        R: RangeBounds<Q>,
+    {
+        self.tree.range(self.tx, range)
+    }
+
+    pub fn keys(&self) -> Result<Keys<'_, 'tcell, K, V, A, C, RwTx<'tcell>>, Error> {
+        self.tree.keys(self.tx)
+    }
+
+    pub fn values(&self) -> Result<Values<'_, 'tcell, K, V, A, C, RwTx<'tcell>>, Error> {
+        self.tree.values(self.tx)
+    }
+}
+
+/// A cursor over the entries of a `RBTreeMapRaw`, which can modify the tree around its position.
+///
+/// Besides the entries, the cursor can point at a "ghost" position, which sits after the entry
+/// with the greatest key and before the entry with the least key.
+pub struct CursorMut<'tx, 'tcell, K, V, A = (), C = Natural> {
+    tree: &'tcell RBTreeMapRaw<K, V, A, C>,
+    tx:   &'tx mut RwTx<'tcell>,
+    node: RBRef<'tcell, K, TCell<V>, A, C>,
+}
+
+impl<'tx, 'tcell, K, V, A, C> CursorMut<'tx, 'tcell, K, V, A, C>
+where
+    K: Borrow + Send + Sync + 'static,
+    V: Borrow + Send + Sync + 'static,
+    A: Augment<K, TCell<V>>,
+    C: Compare<K>,
+{
+    /// Returns the key of the current entry, or `None` at the ghost position.
+    pub fn key(&self) -> Option<&K> {
+        self.node.into_option().map(|node| &node.key)
+    }
+
+    pub fn value(&self) -> Result<Option<Ref<'_, V>>, Error> {
+        match self.node {
+            RBRef::Valid(node) => Ok(Some(node.value.borrow(self.tx, Ordering::default())?)),
+            RBRef::Null => Ok(None),
+        }
+    }
+
+    pub fn current(&self) -> Result<Option<KeyValue<'_, K, V>>, Error> {
+        match self.node {
+            RBRef::Valid(node) => Ok(Some(key_value(self.tx, node)?)),
+            RBRef::Null => Ok(None),
+        }
+    }
+
+    pub fn view(&mut self) -> Option<View<'tcell, V, &mut RwTx<'tcell>>>
+    where
+        A: KeyOnly,
+    {
+        let tx = &mut *self.tx;
+        self.node.into_option().map(move |node| node.value.view(tx))
+    }
+
+    /// Replaces the value of the current entry, returning the old value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cursor is at the ghost position.
+    pub fn replace_value(&mut self, value: V) -> Result<V, Error>
+    where
+        V: Clone,
+    {
+        let node = self
+            .node
+            .into_option()
+            .expect("`CursorMut::replace_value` called at the ghost position");
+        let value = node.value.replace(self.tx, value)?;
+        self.tree.root.update_value(self.tx, node)?;
+        Ok(value)
+    }
+
+    /// Moves to the next entry. At the last entry, this moves to the ghost position, and at the
+    /// ghost position, this moves to the first entry.
+    pub fn move_next(&mut self) -> Result<(), Error> {
+        self.node = match self.node {
+            RBRef::Valid(node) => node.next(self.tx)?,
+            RBRef::Null => self.tree.root.first(self.tx)?,
+        };
+        Ok(())
+    }
+
+    /// Moves to the previous entry. At the first entry, this moves to the ghost position, and at
+    /// the ghost position, this moves to the last entry.
+    pub fn move_prev(&mut self) -> Result<(), Error> {
+        self.node = match self.node {
+            RBRef::Valid(node) => node.prev(self.tx)?,
+            RBRef::Null => self.tree.root.last(self.tx)?,
+        };
+        Ok(())
+    }
+
+    /// Removes the current entry, and moves to the next entry.
+    ///
+    /// Returns `None` and does nothing at the ghost position.
+    pub fn remove_current(&mut self) -> Result<Option<KeyValue<'tx, K, V>>, Error> {
+        let node = match self.node {
+            RBRef::Valid(node) => node,
+            RBRef::Null => return Ok(None),
+        };
+        // nodes are only relinked by rebalancing, so the next node stays valid
+        self.node = node.next(self.tx)?;
+        Ok(Some(self.tree.pop(self.tx, node)?))
+    }
+
+    /// Inserts an entry just before the current entry, or as the last entry at the ghost position.
+    /// The cursor doesn't move.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not strictly between the keys of the neighbouring entries.
+    pub fn insert_before(&mut self, key: K, value: V) -> Result<(), Error> {
+        let prev = match self.node {
+            RBRef::Valid(node) => node.prev(self.tx)?,
+            RBRef::Null => self.tree.root.last(self.tx)?,
+        };
+        self.insert_between(prev, self.node, key, value)
+    }
+
+    /// Inserts an entry just after the current entry, or as the first entry at the ghost position.
+    /// The cursor doesn't move.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not strictly between the keys of the neighbouring entries.
+    pub fn insert_after(&mut self, key: K, value: V) -> Result<(), Error> {
+        let next = match self.node {
+            RBRef::Valid(node) => node.next(self.tx)?,
+            RBRef::Null => self.tree.root.first(self.tx)?,
+        };
+        self.insert_between(self.node, next, key, value)
+    }
+
+    fn insert_between(
+        &mut self,
+        prev: RBRef<'tcell, K, TCell<V>, A, C>,
+        next: RBRef<'tcell, K, TCell<V>, A, C>,
+        key: K,
+        value: V,
+    ) -> Result<(), Error> {
+        assert!(
+            prev.into_option()
+                .map(|prev| C::less(&prev.key, &key))
+                .unwrap_or(true)
+                && next
+                    .into_option()
+                    .map(|next| C::less(&key, &next.key))
+                    .unwrap_or(true),
+            "key inserted out of order by `CursorMut`"
+        );
+        let location = self.tree.root.location_between(self.tx, prev, next)?;
+        self.tree
+            .root
+            .insert(self.tx, key, TCell::new(value), location)?;
+        Ok(())
+    }
+}
+
+pub struct VacantEntry<'tx, 'tcell, K, V, A = (), C = Natural> {
+    location: VacantLocation<'tcell, K, TCell<V>, A, C>,
+    tree:     &'tcell RBTreeMapRaw<K, V, A, C>,
     tx:       &'tx mut RwTx<'tcell>,
     key:      K,
 }
 
-impl<'tx, 'tcell, K, V> VacantEntry<'tx, 'tcell, K, V>
+impl<'tx, 'tcell, K, V, A, C> VacantEntry<'tx, 'tcell, K, V, A, C>
 where
-    K: Ord + Send + Sync + 'static,
+    K: Send + Sync + 'static,
     V: Borrow + Send + Sync + 'static,
+    A: Augment<K, TCell<V>> + KeyOnly,
+    C: Compare<K>,
 {
     pub fn key(&self) -> &K {
         &self.key
@@ -293,17 +1382,19 @@ where
     }
 }
 
-pub struct OccupiedEntry<'tx, 'tcell, K, V> {
-    node: &'tcell RBNode<K, TCell<V>>,
-    tree: &'tcell RBTreeMapRaw<K, V>,
+pub struct OccupiedEntry<'tx, 'tcell, K, V, A = (), C = Natural> {
+    node: &'tcell RBNode<K, TCell<V>, A, C>,
+    tree: &'tcell RBTreeMapRaw<K, V, A, C>,
     tx:   &'tx mut RwTx<'tcell>,
     key:  K,
 }
 
-impl<'tx, 'tcell, K, V> OccupiedEntry<'tx, 'tcell, K, V>
+impl<'tx, 'tcell, K, V, A, C> OccupiedEntry<'tx, 'tcell, K, V, A, C>
 where
-    K: Ord + Send + Sync + 'static,
+    K: Send + Sync + 'static,
     V: Borrow + Send + Sync + 'static,
+    A: Augment<K, TCell<V>> + KeyOnly,
+    C: Compare<K>,
 {
     pub fn key(&self) -> &K {
         &self.key
@@ -330,15 +1421,17 @@ where
     }
 }
 
-pub enum Entry<'tx, 'tcell, K, V> {
-    Vacant(VacantEntry<'tx, 'tcell, K, V>),
-    Occupied(OccupiedEntry<'tx, 'tcell, K, V>),
+pub enum Entry<'tx, 'tcell, K, V, A = (), C = Natural> {
+    Vacant(VacantEntry<'tx, 'tcell, K, V, A, C>),
+    Occupied(OccupiedEntry<'tx, 'tcell, K, V, A, C>),
 }
 
-impl<'tx, 'tcell, K, V> Entry<'tx, 'tcell, K, V>
+impl<'tx, 'tcell, K, V, A, C> Entry<'tx, 'tcell, K, V, A, C>
 where
-    K: Ord + Send + Sync + 'static,
+    K: Send + Sync + 'static,
     V: Borrow + Send + Sync + 'static,
+    A: Augment<K, TCell<V>> + KeyOnly,
+    C: Compare<K>,
 {
     #[inline]
     pub fn or_insert(self, default: V) -> Result<Value<'tx, 'tcell, V>, Error> {
@@ -0,0 +1,128 @@
+//! Scans over large trees in chunks, each read by its own short transaction.
+//!
+//! Iterating over a whole tree in one transaction conflicts with every writer of the tree, and is
+//! retried until no writer gets in the way. A [`Scan`](struct.Scan.html) instead remembers the
+//! last key it returned, and reads the next few entries after it in a new transaction.
+//!
+//! # Consistency
+//!
+//! Every chunk is a snapshot: its entries were all present together, with those values, at one
+//! point in time. Different chunks come from different points in time, so the scan as a whole is
+//! not a snapshot of the tree. The keys returned are strictly ascending, so no key is returned
+//! twice. An entry which is present for the whole scan is returned exactly once, and an entry
+//! which is inserted or removed during the scan may or may not be returned.
+
+use crate::{
+    base::Augment,
+    compare::{Compare, Natural},
+    RBTreeMap,
+};
+use std::ops::Bound;
+use swym::{
+    tcell::TCell,
+    thread_key,
+    tx::{Borrow, Error, Read},
+};
+
+/// A scan over the entries of a `RBTreeMap` in ascending order, a chunk per transaction.
+///
+/// As an iterator, a scan yields the non-empty chunks, and ends at the first empty one.
+pub struct Scan<'a, K, V, A = (), C = Natural> {
+    tree:      &'a RBTreeMap<K, V, A, C>,
+    chunk_len: usize,
+    // the greatest key returned so far
+    last:      Option<K>,
+}
+
+impl<K, V, A, C> RBTreeMap<K, V, A, C>
+where
+    K: Borrow + Clone + Send + Sync + 'static,
+    V: Borrow + Clone + Send + Sync + 'static,
+    A: Augment<K, TCell<V>>,
+    C: Compare<K>,
+{
+    /// Returns a scan over all the entries, reading up to `chunk_len` entries per transaction.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_len` is zero.
+    pub fn scan(&self, chunk_len: usize) -> Scan<'_, K, V, A, C> {
+        assert!(chunk_len > 0, "`Scan` requires a nonzero chunk length");
+        Scan {
+            tree: self,
+            chunk_len,
+            last: None,
+        }
+    }
+
+    /// Returns a scan over the entries with keys greater than `key`, such as the
+    /// [`last_key`](scan/struct.Scan.html#method.last_key) of an earlier scan.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_len` is zero.
+    pub fn scan_after(&self, key: K, chunk_len: usize) -> Scan<'_, K, V, A, C> {
+        let mut scan = self.scan(chunk_len);
+        scan.last = Some(key);
+        scan
+    }
+}
+
+impl<K, V, A, C> Scan<'_, K, V, A, C>
+where
+    K: Borrow + Clone + Send + Sync + 'static,
+    V: Borrow + Clone + Send + Sync + 'static,
+    A: Augment<K, TCell<V>>,
+    C: Compare<K>,
+{
+    /// Returns the greatest key returned so far, or the key the scan was resumed after.
+    pub fn last_key(&self) -> Option<&K> {
+        self.last.as_ref()
+    }
+
+    /// Returns a copy of the next entries after the last key, up to the chunk length.
+    ///
+    /// An empty chunk means that the scan reached the end of the tree. Calling this again later
+    /// returns the entries inserted after the last key since then.
+    pub fn next_chunk(&mut self) -> Vec<(K, V)> {
+        let tree = self.tree;
+        let chunk_len = self.chunk_len;
+        let last = self.last.as_ref();
+        let chunk = thread_key::get().read(move |tx| Self::read_chunk(tree, tx, last, chunk_len));
+        if let Some((key, _)) = chunk.last() {
+            self.last = Some(key.clone());
+        }
+        chunk
+    }
+
+    fn read_chunk<'tcell>(
+        tree: &'tcell RBTreeMap<K, V, A, C>,
+        tx: &impl Read<'tcell>,
+        last: Option<&K>,
+        chunk_len: usize,
+    ) -> Result<Vec<(K, V)>, Error> {
+        let start = match last {
+            Some(last) => Bound::Excluded(last),
+            None => Bound::Unbounded,
+        };
+        tree.raw
+            .range::<K, _, _>(tx, (start, Bound::Unbounded))?
+            .take(chunk_len)
+            .map(|entry| entry.map(|(key, value)| (key.clone(), value.clone())))
+            .collect()
+    }
+}
+
+impl<K, V, A, C> Iterator for Scan<'_, K, V, A, C>
+where
+    K: Borrow + Clone + Send + Sync + 'static,
+    V: Borrow + Clone + Send + Sync + 'static,
+    A: Augment<K, TCell<V>>,
+    C: Compare<K>,
+{
+    type Item = Vec<(K, V)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_chunk()).filter(|chunk| !chunk.is_empty())
+    }
+}
@@ -0,0 +1,109 @@
+// serde support: trees serialize a snapshot copied out in one read transaction, and deserialize
+// through `FromIterator`, without any transactions
+
+use crate::{base::Augment, compare::Compare, set::RBTreeSet, RBTreeMap};
+use serde::{
+    de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor},
+    ser::{Serialize, Serializer},
+};
+use std::{fmt, marker::PhantomData};
+use swym::{tcell::TCell, tx::Borrow};
+
+// an upper bound on the preallocation for untrusted length hints
+const MAX_PREALLOCATED_LEN: usize = 4096;
+
+/// Serializes a snapshot of the entries as a map, in ascending order of their keys.
+///
+/// # Panics
+///
+/// Panics if there is already a running transaction on the current thread.
+impl<K, V, A, C> Serialize for RBTreeMap<K, V, A, C>
+where
+    K: Borrow + Clone + Send + Sync + Serialize + 'static,
+    V: Borrow + Clone + Send + Sync + Serialize + 'static,
+    A: Augment<K, TCell<V>>,
+    C: Compare<K>,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // the serializer may have side effects, so it runs after the transaction
+        let entries = self.to_vec();
+        serializer.collect_map(entries.iter().map(|(key, value)| (key, value)))
+    }
+}
+
+impl<'de, K, V, A, C> Deserialize<'de> for RBTreeMap<K, V, A, C>
+where
+    K: Send + Sync + Deserialize<'de> + 'static,
+    V: Send + Sync + Deserialize<'de> + 'static,
+    A: Augment<K, TCell<V>>,
+    C: Compare<K>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct MapVisitor<K, V>(PhantomData<fn() -> (K, V)>);
+
+        impl<'de, K: Deserialize<'de>, V: Deserialize<'de>> Visitor<'de> for MapVisitor<K, V> {
+            type Value = Vec<(K, V)>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                formatter.write_str("a map")
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, mut access: M) -> Result<Self::Value, M::Error> {
+                let len = access.size_hint().unwrap_or(0);
+                let mut entries = Vec::with_capacity(len.min(MAX_PREALLOCATED_LEN));
+                while let Some(entry) = access.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(entries)
+            }
+        }
+
+        let entries = deserializer.deserialize_map(MapVisitor(PhantomData))?;
+        Ok(entries.into_iter().collect())
+    }
+}
+
+/// Serializes a snapshot of the keys as a sequence, in ascending order.
+///
+/// # Panics
+///
+/// Panics if there is already a running transaction on the current thread.
+impl<K, C> Serialize for RBTreeSet<K, C>
+where
+    K: Borrow + Clone + Send + Sync + Serialize + 'static,
+    C: Compare<K>,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.to_vec())
+    }
+}
+
+impl<'de, K, C> Deserialize<'de> for RBTreeSet<K, C>
+where
+    K: Send + Sync + Deserialize<'de> + 'static,
+    C: Compare<K>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SeqVisitor<K>(PhantomData<fn() -> K>);
+
+        impl<'de, K: Deserialize<'de>> Visitor<'de> for SeqVisitor<K> {
+            type Value = Vec<K>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                formatter.write_str("a sequence")
+            }
+
+            fn visit_seq<S: SeqAccess<'de>>(self, mut access: S) -> Result<Self::Value, S::Error> {
+                let len = access.size_hint().unwrap_or(0);
+                let mut keys = Vec::with_capacity(len.min(MAX_PREALLOCATED_LEN));
+                while let Some(key) = access.next_element()? {
+                    keys.push(key);
+                }
+                Ok(keys)
+            }
+        }
+
+        let keys = deserializer.deserialize_seq(SeqVisitor(PhantomData))?;
+        Ok(keys.into_iter().collect())
+    }
+}
@@ -0,0 +1,278 @@
+// a map with zero sized values, and set algebra over the keys of two maps merged in one transaction
+
+use crate::{
+    compare::{Compare, Natural},
+    RBTreeMap,
+};
+use std::{cmp, iter::FromIterator, ops::RangeBounds};
+use swym::{
+    thread_key,
+    tx::{Borrow, Error, Read},
+};
+
+pub struct RBTreeSet<K, C = Natural> {
+    pub map: RBTreeMap<K, (), (), C>,
+}
+
+impl<K> RBTreeSet<K> {
+    pub const fn new() -> Self {
+        RBTreeSet {
+            map: RBTreeMap::new(),
+        }
+    }
+}
+
+impl<K, C> RBTreeSet<K, C> {
+    /// Constructs a set which orders its keys by `C` instead of their `Ord` implementation.
+    pub const fn with_comparator() -> Self {
+        RBTreeSet {
+            map: RBTreeMap::with_comparator(),
+        }
+    }
+}
+
+impl<K, C> Default for RBTreeSet<K, C> {
+    fn default() -> Self {
+        Self::with_comparator()
+    }
+}
+
+// which of the two merged sets contain a key
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Side {
+    Left,
+    Right,
+    Both,
+}
+
+impl<K: Send + Sync + 'static, C: Compare<K>> RBTreeSet<K, C> {
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.map.contains_key(key)
+    }
+
+    /// Returns true if the key wasn't already present.
+    pub fn insert(&self, key: K) -> bool {
+        !self.map.set(key, ())
+    }
+
+    /// Returns true if the key was present.
+    pub fn remove<Q>(&self, key: &Q) -> bool
+    where
+        K: std::borrow::Borrow<Q>,
+        C: Compare<Q>,
+    {
+        self.map.remove(key).is_some()
+    }
+
+    pub fn is_empty(&self) -> bool
+    where
+        K: Borrow,
+    {
+        thread_key::get().read(move |tx| Ok(self.map.raw.first_key_value(tx)?.is_none()))
+    }
+
+    pub fn first(&self) -> Option<K>
+    where
+        K: Borrow + Clone,
+    {
+        self.map.first_key_value().map(|(key, ())| key)
+    }
+
+    pub fn last(&self) -> Option<K>
+    where
+        K: Borrow + Clone,
+    {
+        self.map.last_key_value().map(|(key, ())| key)
+    }
+
+    pub fn pop_first(&self) -> Option<K>
+    where
+        K: Borrow + Clone,
+    {
+        self.map.pop_first().map(|(key, ())| key)
+    }
+
+    pub fn pop_last(&self) -> Option<K>
+    where
+        K: Borrow + Clone,
+    {
+        self.map.pop_last().map(|(key, ())| key)
+    }
+
+    /// Returns a copy of the keys of the set, in ascending order.
+    pub fn to_vec(&self) -> Vec<K>
+    where
+        K: Borrow + Clone,
+    {
+        self.map.keys()
+    }
+
+    /// Returns a copy of the keys in `range`, in ascending order.
+    pub fn range<Q, R>(&self, range: R) -> Vec<K>
+    where
+        K: Borrow + std::borrow::Borrow<Q> + Clone,
+        Q: ?Sized,
+        C: Compare<Q>,
+        R: RangeBounds<Q> + Clone,
+    {
+        thread_key::get().read(move |tx| {
+            self.map
+                .raw
+                .range(tx, range.clone())?
+                .map(|entry| entry.map(|(key, _)| key.clone()))
+                .collect()
+        })
+    }
+
+    /// Returns the keys in `self` or `other`, in ascending order.
+    pub fn union(&self, other: &Self) -> Vec<K>
+    where
+        K: Borrow + Clone,
+    {
+        self.merged(other, |_| true)
+    }
+
+    /// Returns the keys in both `self` and `other`, in ascending order.
+    pub fn intersection(&self, other: &Self) -> Vec<K>
+    where
+        K: Borrow + Clone,
+    {
+        self.merged(other, |side| side == Side::Both)
+    }
+
+    /// Returns the keys in `self` but not in `other`, in ascending order.
+    pub fn difference(&self, other: &Self) -> Vec<K>
+    where
+        K: Borrow + Clone,
+    {
+        self.merged(other, |side| side == Side::Left)
+    }
+
+    /// Returns the keys in exactly one of `self` and `other`, in ascending order.
+    pub fn symmetric_difference(&self, other: &Self) -> Vec<K>
+    where
+        K: Borrow + Clone,
+    {
+        self.merged(other, |side| side != Side::Both)
+    }
+
+    /// Returns true if every key of `self` is in `other`.
+    pub fn is_subset(&self, other: &Self) -> bool
+    where
+        K: Borrow,
+    {
+        thread_key::get().read(move |tx| {
+            let mut subset = true;
+            self.merge(tx, other, |side, _| {
+                subset = side != Side::Left;
+                subset
+            })?;
+            Ok(subset)
+        })
+    }
+
+    /// Returns true if every key of `other` is in `self`.
+    pub fn is_superset(&self, other: &Self) -> bool
+    where
+        K: Borrow,
+    {
+        other.is_subset(self)
+    }
+
+    /// Returns true if no key is in both `self` and `other`.
+    pub fn is_disjoint(&self, other: &Self) -> bool
+    where
+        K: Borrow,
+    {
+        thread_key::get().read(move |tx| {
+            let mut disjoint = true;
+            self.merge(tx, other, |side, _| {
+                disjoint = side != Side::Both;
+                disjoint
+            })?;
+            Ok(disjoint)
+        })
+    }
+
+    fn merged<F>(&self, other: &Self, keep: F) -> Vec<K>
+    where
+        K: Borrow + Clone,
+        F: Fn(Side) -> bool,
+    {
+        thread_key::get().read(|tx| {
+            let mut keys = Vec::new();
+            self.merge(tx, other, |side, key| {
+                if keep(side) {
+                    keys.push(key.clone());
+                }
+                true
+            })?;
+            Ok(keys)
+        })
+    }
+
+    // visits the keys of both sets in ascending order, until `f` returns false
+    fn merge<'tcell, F>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+        other: &'tcell Self,
+        mut f: F,
+    ) -> Result<(), Error>
+    where
+        K: Borrow,
+        F: FnMut(Side, &K) -> bool,
+    {
+        let mut left_keys = self.map.raw.keys(tx)?;
+        let mut right_keys = other.map.raw.keys(tx)?;
+        let mut left = left_keys.next().transpose()?;
+        let mut right = right_keys.next().transpose()?;
+        loop {
+            let (side, key) = match (&left, &right) {
+                (Some(l), Some(r)) => match C::compare(l, r) {
+                    cmp::Ordering::Less => (Side::Left, l),
+                    cmp::Ordering::Greater => (Side::Right, r),
+                    cmp::Ordering::Equal => (Side::Both, l),
+                },
+                (Some(l), None) => (Side::Left, l),
+                (None, Some(r)) => (Side::Right, r),
+                (None, None) => return Ok(()),
+            };
+            if !f(side, key) {
+                return Ok(());
+            }
+            if side != Side::Right {
+                left = left_keys.next().transpose()?;
+            }
+            if side != Side::Left {
+                right = right_keys.next().transpose()?;
+            }
+        }
+    }
+}
+
+/// Builds a balanced tree without any transactions, as for `RBTreeMap`.
+impl<K: Send + Sync + 'static, C: Compare<K>> FromIterator<K> for RBTreeSet<K, C> {
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        RBTreeSet {
+            map: iter.into_iter().map(|key| (key, ())).collect(),
+        }
+    }
+}
+
+/// Inserts the keys in batches, each batch in a single transaction.
+impl<K: Send + Sync + 'static, C: Compare<K>> Extend<K> for &RBTreeSet<K, C> {
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+        (&self.map).extend(iter.into_iter().map(|key| (key, ())))
+    }
+}
+
+impl<K: Send + Sync + 'static, C: Compare<K>> Extend<K> for RBTreeSet<K, C> {
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+        (&*self).extend(iter)
+    }
+}
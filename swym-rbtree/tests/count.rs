@@ -1,6 +1,13 @@
 use crossbeam_utils::thread;
-use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
-use swym_rbtree::RBTreeMap;
+use std::{
+    collections::BTreeMap,
+    ops::Bound,
+    sync::atomic::{AtomicUsize, Ordering::Relaxed},
+};
+use swym_rbtree::{
+    augment::{Len, Monoid, Summary},
+    RBTreeMap,
+};
 
 static COUNT: AtomicUsize = AtomicUsize::new(0);
 
@@ -123,3 +130,526 @@ fn count_remove_rev() {
     assert_eq!(COUNT.load(Relaxed), 0);
     swym::stats::print_stats();
 }
+
+#[test]
+fn iter() {
+    const ITER_COUNT: usize = 10_000;
+    thread::scope(|scope| {
+        scope.spawn(move |_| {
+            let tree = RBTreeMap::new();
+            for elem in (0..ITER_COUNT).rev() {
+                tree.insert(elem * 2, Count::new(elem));
+            }
+            let entries = tree.to_vec();
+            assert!(entries
+                .iter()
+                .map(|(key, count)| (*key, count.0))
+                .eq((0..ITER_COUNT).map(|elem| (elem * 2, elem))));
+            assert!(tree
+                .keys()
+                .into_iter()
+                .eq((0..ITER_COUNT).map(|elem| elem * 2)));
+            assert!(tree.values().iter().map(|count| count.0).eq(0..ITER_COUNT));
+
+            let keys = |entries: Vec<(usize, Count)>| {
+                entries.into_iter().map(|(key, _)| key).collect::<Vec<_>>()
+            };
+            assert_eq!(keys(tree.range(10..16)), [10, 12, 14]);
+            assert_eq!(keys(tree.range(9..=16)), [10, 12, 14, 16]);
+            assert_eq!(keys(tree.range(..3)), [0, 2]);
+            assert_eq!(keys(tree.range(ITER_COUNT * 2 - 3..)), [ITER_COUNT * 2 - 2]);
+            assert_eq!(keys(tree.range(7..7)), []);
+            assert_eq!(keys(tree.range(ITER_COUNT * 2..)), []);
+
+            // iteration sees the writes of the current transaction
+            let seen = tree.atomic(|mut tree| {
+                tree.remove(&10)?;
+                tree.insert(11, Count::new(11))?;
+                let seen = tree
+                    .range(8..14)?
+                    .map(|entry| entry.map(|(key, count)| (*key, count.0)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(seen)
+            });
+            assert_eq!(seen, [(8, 4), (11, 11), (12, 6)]);
+            swym::thread_key::get().read(|tx| {
+                tree.raw.verify(tx)?;
+                assert_eq!(tree.raw.iter(tx)?.count(), ITER_COUNT);
+                Ok(())
+            });
+        });
+    })
+    .unwrap();
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
+
+#[test]
+fn iter_concurrent() {
+    const ITER_COUNT: usize = 10_000;
+    const THREAD_COUNT: usize = 4;
+    let tree = RBTreeMap::new();
+    thread::scope(|scope| {
+        scope.spawn(|_| {
+            for elem in 0..ITER_COUNT {
+                tree.insert(elem, Count::new(elem));
+            }
+        });
+        for _ in 0..THREAD_COUNT {
+            scope.spawn(|_| {
+                let mut last = 0;
+                while last < ITER_COUNT {
+                    // keys are inserted in order, so every snapshot is a prefix
+                    let keys = tree.keys();
+                    assert!(keys.len() >= last);
+                    assert!(keys.iter().cloned().eq(0..keys.len()));
+                    last = keys.len();
+                }
+            });
+        }
+    })
+    .unwrap();
+    swym::thread_key::get().read(|tx| {
+        tree.raw.verify(tx)?;
+        Ok(())
+    });
+    drop(tree);
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
+
+#[test]
+fn first_last() {
+    const ITER_COUNT: usize = 10_000;
+    thread::scope(|scope| {
+        scope.spawn(move |_| {
+            let tree = RBTreeMap::new();
+            assert!(tree.first_key_value().is_none());
+            assert!(tree.pop_last().is_none());
+            for elem in 0..ITER_COUNT {
+                tree.insert(elem * 2 + 1, Count::new(elem));
+            }
+            let key = |entry: Option<(usize, Count)>| entry.map(|(key, _)| key);
+            assert_eq!(key(tree.first_key_value()), Some(1));
+            assert_eq!(key(tree.last_key_value()), Some(ITER_COUNT * 2 - 1));
+            assert_eq!(key(tree.floor(&0)), None);
+            assert_eq!(key(tree.floor(&1)), Some(1));
+            assert_eq!(key(tree.floor(&6)), Some(5));
+            assert_eq!(key(tree.floor(&(ITER_COUNT * 4))), Some(ITER_COUNT * 2 - 1));
+            assert_eq!(key(tree.ceiling(&0)), Some(1));
+            assert_eq!(key(tree.ceiling(&6)), Some(7));
+            assert_eq!(key(tree.ceiling(&7)), Some(7));
+            assert_eq!(key(tree.ceiling(&(ITER_COUNT * 2))), None);
+
+            // drain from both ends
+            for elem in 0..ITER_COUNT / 2 {
+                let (key, count) = tree.pop_first().unwrap();
+                assert_eq!((key, count.0), (elem * 2 + 1, elem));
+                let (key, count) = tree.pop_last().unwrap();
+                let expected = ITER_COUNT - elem - 1;
+                assert_eq!((key, count.0), (expected * 2 + 1, expected));
+                if elem % 1_000 == 0 {
+                    swym::thread_key::get().read(|tx| {
+                        tree.raw.verify(tx)?;
+                        Ok(())
+                    });
+                }
+            }
+            assert!(tree.pop_first().is_none());
+            assert!(tree.last_key_value().is_none());
+            assert!(tree.ceiling(&0).is_none());
+        });
+    })
+    .unwrap();
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
+
+#[test]
+fn cursor() {
+    const ITER_COUNT: usize = 10_000;
+    thread::scope(|scope| {
+        scope.spawn(move |_| {
+            let tree = RBTreeMap::new();
+            for elem in 0..ITER_COUNT {
+                tree.insert(elem * 4, Count::new(elem));
+            }
+            // in a single walk, remove every other entry, and add an entry after each kept one
+            tree.atomic(|mut tree| {
+                let mut cursor = tree.cursor_front()?;
+                while let Some(&key) = cursor.key() {
+                    if key % 8 == 0 {
+                        let (removed, _) = cursor.remove_current()?.unwrap();
+                        assert_eq!(*removed, key);
+                    } else {
+                        let old = cursor.replace_value(Count::new(key))?;
+                        assert_eq!(old.0 * 4, key);
+                        cursor.insert_after(key + 1, Count::new(key + 1))?;
+                        cursor.move_next()?;
+                        assert_eq!(cursor.key(), Some(&(key + 1)));
+                        cursor.move_next()?;
+                    }
+                }
+                // the ghost position wraps around
+                cursor.move_next()?;
+                assert_eq!(cursor.key(), Some(&4));
+                cursor.move_prev()?;
+                assert!(cursor.current()?.is_none());
+                cursor.insert_after(0, Count::new(0))?;
+                cursor.insert_before(ITER_COUNT * 4, Count::new(ITER_COUNT * 4))?;
+                cursor.move_prev()?;
+                assert_eq!(cursor.value()?.map(|count| count.0), Some(ITER_COUNT * 4));
+                Ok(())
+            });
+            let mut expected = vec![0];
+            for elem in (0..ITER_COUNT).filter(|elem| elem % 2 == 1) {
+                expected.extend_from_slice(&[elem * 4, elem * 4 + 1]);
+            }
+            expected.push(ITER_COUNT * 4);
+            let entries = tree.to_vec();
+            assert!(entries.iter().all(|(key, count)| *key == count.0));
+            assert!(entries.iter().map(|(key, _)| *key).eq(expected));
+            swym::thread_key::get().read(|tx| {
+                tree.raw.verify(tx)?;
+                Ok(())
+            });
+
+            // walking backwards from the middle
+            let keys = tree.atomic(|mut tree| {
+                let mut cursor = tree.cursor_at(&14)?;
+                let mut keys = Vec::new();
+                while let Some(&key) = cursor.key() {
+                    keys.push(key);
+                    cursor.move_prev()?;
+                }
+                Ok(keys)
+            });
+            assert_eq!(keys, [20, 13, 12, 5, 4, 0]);
+        });
+    })
+    .unwrap();
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Sum(usize);
+
+impl Monoid<usize, Count> for Sum {
+    fn empty() -> Self {
+        Sum(0)
+    }
+
+    fn entry(_: &usize, value: &Count) -> Self {
+        Sum(value.0)
+    }
+
+    fn combine(&self, rhs: &Self) -> Self {
+        Sum(self.0 + rhs.0)
+    }
+}
+
+#[test]
+fn augmented() {
+    const ITER_COUNT: usize = 10_000;
+    const KEY_COUNT: usize = 1_000;
+    thread::scope(|scope| {
+        scope.spawn(move |_| {
+            let tree = RBTreeMap::<usize, Count, Summary<(Len, Sum)>>::new_augmented();
+            let mut expected = BTreeMap::new();
+            let mut seed = 1usize;
+            let mut random = move || {
+                seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+                seed >> 33
+            };
+            for elem in 0..ITER_COUNT {
+                let key = random() % KEY_COUNT;
+                let value = random() % 100;
+                match random() % 8 {
+                    0..=3 => {
+                        let old = tree.insert(key, Count::new(value));
+                        assert_eq!(old.map(|count| count.0), expected.insert(key, value));
+                    }
+                    4 | 5 => {
+                        let old = tree.remove(&key);
+                        assert_eq!(old.map(|count| count.0), expected.remove(&key));
+                    }
+                    6 => {
+                        let popped = tree.pop_first().map(|(key, count)| (key, count.0));
+                        let first = expected.keys().next().cloned();
+                        assert_eq!(
+                            popped,
+                            first.map(|key| (key, expected.remove(&key).unwrap()))
+                        );
+                    }
+                    _ => {
+                        // replace the value at one cursor position, and remove the next entry
+                        let removed = tree.atomic(|mut tree| {
+                            let mut cursor = tree.cursor_at(&key)?;
+                            if cursor.key().is_some() {
+                                cursor.replace_value(Count::new(value))?;
+                                cursor.move_next()?;
+                            }
+                            Ok(cursor.remove_current()?.map(|(key, _)| *key))
+                        });
+                        let mut keys = expected.range_mut(key..);
+                        if let Some((_, old)) = keys.next() {
+                            *old = value;
+                        }
+                        let next = keys.next().map(|(key, _)| *key);
+                        assert_eq!(removed, next);
+                        if let Some(key) = next {
+                            expected.remove(&key);
+                        }
+                    }
+                }
+                if elem % 1_000 == 0 {
+                    swym::thread_key::get().read(|tx| {
+                        tree.raw.verify(tx)?;
+                        Ok(())
+                    });
+                }
+            }
+            swym::thread_key::get().read(|tx| {
+                tree.raw.verify(tx)?;
+                Ok(())
+            });
+
+            assert_eq!(tree.len(), expected.len());
+            let (Len(len), Sum(sum)) = tree.summary();
+            assert_eq!(len, expected.len());
+            assert_eq!(sum, expected.values().sum::<usize>());
+            for (index, (key, value)) in expected.iter().enumerate() {
+                assert_eq!(tree.rank(key), index);
+                assert_eq!(tree.rank(&(key + 1)), index + 1);
+                let (selected, count) = tree.select(index).unwrap();
+                assert_eq!((selected, count.0), (*key, *value));
+            }
+            assert!(tree.select(expected.len()).is_none());
+            for start in (0..KEY_COUNT).step_by(37) {
+                for end in (start..KEY_COUNT + 10).step_by(53) {
+                    let (Len(len), Sum(sum)) = tree.range_summary(start..end);
+                    assert_eq!(len, expected.range(start..end).count());
+                    assert_eq!(
+                        sum,
+                        expected.range(start..end).map(|(_, v)| v).sum::<usize>()
+                    );
+                    let (Len(len), Sum(_)) = tree.range_summary(start..=end);
+                    assert_eq!(len, expected.range(start..=end).count());
+                }
+            }
+            let (Len(len), _) = tree.range_summary(..);
+            assert_eq!(len, expected.len());
+            let (Len(len), _) = tree.range_summary((Bound::Excluded(10), Bound::Unbounded));
+            assert_eq!(len, expected.range(11..).count());
+        });
+    })
+    .unwrap();
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct Key(usize);
+
+#[test]
+fn non_clone() {
+    const ITER_COUNT: usize = 10_000;
+    const KEY_COUNT: usize = 100;
+    const THREAD_COUNT: usize = 4;
+    let tree = RBTreeMap::<Key, Box<dyn Fn() -> usize + Send + Sync>>::new();
+    let vacant = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for thread in 0..THREAD_COUNT {
+            let (tree, vacant) = (&tree, &vacant);
+            scope.spawn(move |_| {
+                // every thread writes every key, so the inserts conflict and get retried
+                for elem in 0..ITER_COUNT {
+                    let count = Count::new(elem * THREAD_COUNT + thread);
+                    if !tree.set(Key(elem % KEY_COUNT), Box::new(move || count.0)) {
+                        vacant.fetch_add(1, Relaxed);
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+    assert_eq!(vacant.load(Relaxed), KEY_COUNT);
+    swym::thread_key::get().read(|tx| {
+        tree.raw.verify(tx)?;
+        for key in 0..KEY_COUNT {
+            let value = tree.raw.get(tx, &Key(key))?.unwrap();
+            assert_eq!(value() / THREAD_COUNT % KEY_COUNT, key);
+        }
+        Ok(())
+    });
+    thread::scope(|scope| {
+        scope.spawn(|_| {
+            assert!(tree.atomic(|mut tree| Ok(tree.remove(&Key(0))?.is_some())));
+            assert!(!tree.contains_key(&Key(0)));
+        });
+    })
+    .unwrap();
+    drop(tree);
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
+
+#[test]
+fn bulk() {
+    const KEY_COUNT: usize = 1_000;
+    thread::scope(|scope| {
+        scope.spawn(|_| {
+            let verify = |tree: &RBTreeMap<usize, Count, Summary<Len>>| {
+                swym::thread_key::get().read(|tx| {
+                    tree.raw.verify(tx)?;
+                    Ok(())
+                })
+            };
+            let entries = |tree: &RBTreeMap<usize, Count, Summary<Len>>| {
+                tree.to_vec()
+                    .into_iter()
+                    .map(|(key, count)| (key, count.0))
+                    .collect::<Vec<_>>()
+            };
+
+            // every tree size up to a few levels, to cover each shape of the bottom level
+            for len in 0..70 {
+                let tree: RBTreeMap<usize, Count, Summary<Len>> =
+                    (0..len).map(|key| (key, Count::new(key))).collect();
+                verify(&tree);
+                assert_eq!(tree.len(), len);
+                assert_eq!(
+                    entries(&tree),
+                    (0..len).map(|key| (key, key)).collect::<Vec<_>>()
+                );
+            }
+
+            // unsorted, with the last of several equal keys winning
+            let mut unsorted = BTreeMap::new();
+            let mut input = Vec::new();
+            for elem in 0..KEY_COUNT {
+                let key = elem * 7 % (KEY_COUNT / 2);
+                unsorted.insert(key, elem);
+                input.push((key, Count::new(elem)));
+            }
+            let tree: RBTreeMap<usize, Count, Summary<Len>> = input.into_iter().collect();
+            verify(&tree);
+            assert_eq!(tree.len(), unsorted.len());
+            assert_eq!(
+                entries(&tree),
+                unsorted.clone().into_iter().collect::<Vec<_>>()
+            );
+
+            let mut extended = RBTreeMap::new_augmented();
+            extended.extend((0..KEY_COUNT).rev().map(|key| (key, Count::new(key))));
+            (&extended).extend((0..KEY_COUNT).step_by(3).map(|key| (key, Count::new(0))));
+            verify(&extended);
+            assert_eq!(extended.len(), KEY_COUNT);
+            let mut expected: BTreeMap<_, _> = (0..KEY_COUNT).map(|key| (key, key)).collect();
+            expected.extend((0..KEY_COUNT).step_by(3).map(|key| (key, 0)));
+            assert_eq!(
+                entries(&extended),
+                expected.clone().into_iter().collect::<Vec<_>>()
+            );
+
+            let split = extended.split_off(&(KEY_COUNT / 3));
+            verify(&extended);
+            verify(&split);
+            assert_eq!(extended.len(), KEY_COUNT / 3);
+            assert_eq!(split.len(), KEY_COUNT - KEY_COUNT / 3);
+            assert_eq!(extended.keys(), (0..KEY_COUNT / 3).collect::<Vec<_>>());
+            assert_eq!(split.keys(), (KEY_COUNT / 3..KEY_COUNT).collect::<Vec<_>>());
+            assert_eq!(extended.split_off(&KEY_COUNT).len(), 0);
+
+            split.append(&tree);
+            verify(&split);
+            verify(&tree);
+            assert!(tree.is_empty());
+            let mut expected = expected.split_off(&(KEY_COUNT / 3));
+            expected.extend(unsorted);
+            assert_eq!(split.len(), expected.len());
+            assert_eq!(entries(&split), expected.into_iter().collect::<Vec<_>>());
+
+            extended.append(&split);
+            verify(&extended);
+            assert!(split.is_empty());
+            assert_eq!(extended.len(), KEY_COUNT);
+        });
+    })
+    .unwrap();
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
+
+#[test]
+fn retain() {
+    const KEY_COUNT: usize = 1_000;
+    const THREAD_COUNT: usize = 4;
+    let tree = RBTreeMap::<usize, Count, Summary<Len>>::new_augmented();
+    thread::scope(|scope| {
+        scope.spawn(|_| {
+            (&tree).extend((0..KEY_COUNT).map(|key| (key, Count::new(key))));
+            tree.atomic(|mut tree| Ok(tree.retain(|key, _| key % 2 == 0)?));
+            assert_eq!(tree.keys(), (0..KEY_COUNT).step_by(2).collect::<Vec<_>>());
+
+            let drained = tree.atomic(|mut tree| {
+                let drained = tree.drain_filter(|_, value| value.0 % 3 == 0)?;
+                Ok(drained.into_iter().map(|(key, _)| *key).collect::<Vec<_>>())
+            });
+            assert_eq!(drained, (0..KEY_COUNT).step_by(6).collect::<Vec<_>>());
+            let expected = (0..KEY_COUNT)
+                .filter(|key| key % 2 == 0 && key % 3 != 0)
+                .collect::<Vec<_>>();
+            assert_eq!(tree.keys(), expected);
+            assert_eq!(tree.len(), expected.len());
+            swym::thread_key::get().read(|tx| {
+                tree.raw.verify(tx)?;
+                Ok(())
+            });
+
+            tree.atomic(|mut tree| Ok(tree.clear()?));
+            assert_eq!(tree.len(), 0);
+            assert!(tree.keys().is_empty());
+            tree.atomic(|mut tree| Ok(tree.clear()?));
+        });
+    })
+    .unwrap();
+
+    // sweeps racing with inserts
+    thread::scope(|scope| {
+        for thread in 0..THREAD_COUNT {
+            let tree = &tree;
+            scope.spawn(move |_| {
+                for key in (thread..KEY_COUNT).step_by(THREAD_COUNT) {
+                    tree.set(key, Count::new(key));
+                    if key % 64 == thread {
+                        tree.atomic(|mut tree| {
+                            Ok(tree.retain(|key, _| key % THREAD_COUNT != thread)?)
+                        });
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+    swym::thread_key::get().read(|tx| {
+        tree.raw.verify(tx)?;
+        Ok(())
+    });
+    // every thread only sweeps its own keys, so all that is left of them is what it inserted after
+    // its last sweep
+    let last_sweep = |thread: usize| (KEY_COUNT - 1 - thread) / 64 * 64 + thread;
+    let expected = (0..KEY_COUNT)
+        .filter(|key| *key > last_sweep(key % THREAD_COUNT))
+        .collect::<Vec<_>>();
+    assert_eq!(tree.keys(), expected);
+    assert_eq!(tree.len(), expected.len());
+    thread::scope(|scope| {
+        scope.spawn(|_| tree.atomic(|mut tree| Ok(tree.clear()?)));
+    })
+    .unwrap();
+    drop(tree);
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
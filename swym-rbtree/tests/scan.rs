@@ -0,0 +1,62 @@
+use crossbeam_utils::thread;
+use std::sync::atomic::{AtomicBool, Ordering::Relaxed};
+use swym_rbtree::RBTreeMap;
+
+#[test]
+fn resume() {
+    const KEY_COUNT: usize = 100;
+    let tree: RBTreeMap<usize, usize> = (0..KEY_COUNT).map(|key| (key * 2, key)).collect();
+    let chunks = tree.scan(7).collect::<Vec<_>>();
+    assert_eq!(chunks.len(), 15);
+    assert!(chunks.iter().all(|chunk| chunk.len() <= 7));
+    assert_eq!(chunks.concat(), tree.to_vec());
+
+    let mut scan = tree.scan(10);
+    assert_eq!(scan.last_key(), None);
+    assert_eq!(scan.next_chunk().len(), 10);
+    assert_eq!(scan.last_key(), Some(&18));
+    // a new scan picks up where the last one stopped
+    let mut resumed = tree.scan_after(*scan.last_key().unwrap(), 1_000);
+    assert_eq!(resumed.next_chunk(), tree.range(19..));
+    assert!(resumed.next_chunk().is_empty());
+    tree.insert(1_000, 0);
+    assert_eq!(resumed.next_chunk(), [(1_000, 0)]);
+    assert_eq!(tree.scan_after(1_000, 1).next(), None);
+}
+
+#[test]
+fn concurrent() {
+    const STABLE_COUNT: usize = 1_000;
+    const ITER_COUNT: usize = 100;
+    // keys below STABLE_COUNT are never removed, only updated, and the others come and go
+    let tree: RBTreeMap<usize, usize> = (0..STABLE_COUNT).map(|key| (key, 0)).collect();
+    let done = AtomicBool::new(false);
+    thread::scope(|scope| {
+        let (tree, done) = (&tree, &done);
+        scope.spawn(move |_| {
+            for elem in 0.. {
+                if done.load(Relaxed) {
+                    break;
+                }
+                let key = elem * 7 % (2 * STABLE_COUNT);
+                if key < STABLE_COUNT || tree.remove(&key).is_none() {
+                    tree.insert(key, elem);
+                }
+            }
+        });
+        scope.spawn(move |_| {
+            for _ in 0..ITER_COUNT {
+                let keys = tree
+                    .scan(16)
+                    .flatten()
+                    .map(|(key, _)| key)
+                    .collect::<Vec<_>>();
+                assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+                let stable = keys.iter().filter(|key| **key < STABLE_COUNT).cloned();
+                assert!(stable.eq(0..STABLE_COUNT));
+            }
+            done.store(true, Relaxed);
+        });
+    })
+    .unwrap();
+}
@@ -0,0 +1,84 @@
+#![cfg(feature = "serde")]
+
+use crossbeam_utils::thread;
+use std::collections::BTreeMap;
+use swym_rbtree::{compare::Reverse, set::RBTreeSet, RBTreeMap};
+
+#[test]
+fn round_trip() {
+    let tree: RBTreeMap<String, Vec<usize>> = (0..100)
+        .map(|key| (format!("{:03}", key), (0..key % 5).collect()))
+        .collect();
+    let json = serde_json::to_string(&tree).unwrap();
+    let expected: BTreeMap<_, _> = tree.to_vec().into_iter().collect();
+    assert_eq!(json, serde_json::to_string(&expected).unwrap());
+    let copy: RBTreeMap<String, Vec<usize>> = serde_json::from_str(&json).unwrap();
+    assert_eq!(copy.to_vec(), tree.to_vec());
+    swym::thread_key::get().read(|tx| {
+        copy.raw.verify::<String>(tx)?;
+        Ok(())
+    });
+
+    // the order on the wire is the order of the comparator, which needn't be the order of the new
+    // tree
+    let reversed: RBTreeMap<usize, usize, (), Reverse> = (0..100).map(|key| (key, key)).collect();
+    let json = serde_json::to_string(&reversed).unwrap();
+    assert!(json.starts_with(r#"{"99":99,"98":98,"#));
+    let tree: RBTreeMap<usize, usize> = serde_json::from_str(&json).unwrap();
+    assert_eq!(tree.keys(), (0..100).collect::<Vec<_>>());
+    swym::thread_key::get().read(|tx| {
+        tree.raw.verify::<usize>(tx)?;
+        Ok(())
+    });
+
+    let empty: RBTreeMap<usize, usize> = serde_json::from_str("{}").unwrap();
+    assert_eq!(empty.first_key_value(), None);
+    assert!(serde_json::from_str::<RBTreeMap<usize, usize>>("[1]").is_err());
+
+    let set: RBTreeSet<usize> = (0..100).step_by(3).collect();
+    let json = serde_json::to_string(&set).unwrap();
+    assert_eq!(
+        json,
+        serde_json::to_string(&(0..100).step_by(3).collect::<Vec<_>>()).unwrap()
+    );
+    let copy: RBTreeSet<usize, Reverse> = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        copy.to_vec(),
+        set.to_vec().into_iter().rev().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn snapshot() {
+    const ITER_COUNT: usize = 10_000;
+    const KEY_COUNT: usize = 64;
+    const TOTAL: usize = KEY_COUNT * 100;
+    // every transfer keeps the total the same, so a consistent snapshot always has that total
+    let tree: RBTreeMap<usize, usize> = (0..KEY_COUNT).map(|key| (key, 100)).collect();
+    thread::scope(|scope| {
+        let tree = &tree;
+        scope.spawn(move |_| {
+            for elem in 0..ITER_COUNT {
+                let from = elem * 7 % KEY_COUNT;
+                let to = elem * 13 % KEY_COUNT;
+                swym::thread_key::get().rw(|tx| {
+                    let from_value = *tree.raw.get(tx, &from)?.unwrap();
+                    let amount = from_value.min(10);
+                    tree.raw.insert(tx, from, from_value - amount)?;
+                    let to_value = *tree.raw.get(tx, &to)?.unwrap();
+                    tree.raw.insert(tx, to, to_value + amount)?;
+                    Ok(())
+                });
+            }
+        });
+        scope.spawn(move |_| {
+            for _ in 0..ITER_COUNT / 100 {
+                let json = serde_json::to_vec(tree).unwrap();
+                let copy: BTreeMap<usize, usize> = serde_json::from_slice(&json).unwrap();
+                assert_eq!(copy.len(), KEY_COUNT);
+                assert_eq!(copy.values().sum::<usize>(), TOTAL);
+            }
+        });
+    })
+    .unwrap();
+}
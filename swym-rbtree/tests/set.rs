@@ -0,0 +1,151 @@
+use crossbeam_utils::thread;
+use std::{cmp::Ordering, collections::BTreeSet, ops::Bound};
+use swym_rbtree::{
+    compare::{Compare, Reverse},
+    set::RBTreeSet,
+    RBTreeMap,
+};
+
+enum CaseInsensitive {}
+
+impl Compare<str> for CaseInsensitive {
+    fn compare(lhs: &str, rhs: &str) -> Ordering {
+        let lhs = lhs.chars().flat_map(char::to_lowercase);
+        let rhs = rhs.chars().flat_map(char::to_lowercase);
+        lhs.cmp(rhs)
+    }
+}
+
+impl Compare<String> for CaseInsensitive {
+    fn compare(lhs: &String, rhs: &String) -> Ordering {
+        <Self as Compare<str>>::compare(lhs, rhs)
+    }
+}
+
+#[test]
+fn comparator() {
+    let tree = RBTreeMap::<String, usize, (), CaseInsensitive>::with_comparator();
+    assert_eq!(tree.insert("Hello".to_owned(), 0), None);
+    assert_eq!(tree.insert("world".to_owned(), 1), None);
+    assert_eq!(tree.insert("HELLO".to_owned(), 2), Some(0));
+    assert_eq!(tree.get("hello"), Some(2));
+    assert!(tree.contains_key("WORLD"));
+    assert!(!tree.contains_key("word"));
+    // inserting replaces the whole entry, key included
+    assert_eq!(
+        tree.to_vec(),
+        [("HELLO".to_owned(), 2), ("world".to_owned(), 1)]
+    );
+    let range = (Bound::Included("HELLO"), Bound::Excluded("World"));
+    assert_eq!(tree.range::<str, _>(range).len(), 1);
+    swym::thread_key::get().read(|tx| {
+        tree.raw.verify::<str>(tx)?;
+        Ok(())
+    });
+
+    let tree: RBTreeMap<usize, usize, (), Reverse> = (0..100).map(|key| (key, key)).collect();
+    swym::thread_key::get().read(|tx| {
+        tree.raw.verify::<usize>(tx)?;
+        Ok(())
+    });
+    assert_eq!(tree.keys(), (0..100).rev().collect::<Vec<_>>());
+    assert_eq!(tree.first_key_value(), Some((99, 99)));
+    assert_eq!(tree.range(&10..&5).len(), 5);
+    assert_eq!(
+        tree.split_off(&50).keys(),
+        (0..=50).rev().collect::<Vec<_>>()
+    );
+    // 50 moved out, so the greatest key up to 50 in reverse order is 51
+    assert_eq!(tree.floor(&50), Some((51, 51)));
+    assert_eq!(tree.pop_last(), Some((51, 51)));
+}
+
+#[test]
+fn set_algebra() {
+    const KEY_COUNT: usize = 1_000;
+    let evens: RBTreeSet<usize> = (0..KEY_COUNT).step_by(2).collect();
+    let thirds: RBTreeSet<usize> = RBTreeSet::new();
+    (&thirds).extend((0..KEY_COUNT).step_by(3));
+    let expected_evens: BTreeSet<_> = (0..KEY_COUNT).step_by(2).collect();
+    let expected_thirds: BTreeSet<_> = (0..KEY_COUNT).step_by(3).collect();
+
+    let collect = |keys: &mut dyn Iterator<Item = &usize>| keys.cloned().collect::<Vec<_>>();
+    assert_eq!(
+        evens.union(&thirds),
+        collect(&mut expected_evens.union(&expected_thirds))
+    );
+    assert_eq!(
+        evens.intersection(&thirds),
+        collect(&mut expected_evens.intersection(&expected_thirds))
+    );
+    assert_eq!(
+        evens.difference(&thirds),
+        collect(&mut expected_evens.difference(&expected_thirds))
+    );
+    assert_eq!(
+        evens.symmetric_difference(&thirds),
+        collect(&mut expected_evens.symmetric_difference(&expected_thirds))
+    );
+    assert_eq!(evens.union(&evens), evens.to_vec());
+    assert!(evens.difference(&evens).is_empty());
+    assert!(!evens.is_disjoint(&thirds));
+    assert!(!evens.is_subset(&thirds));
+
+    let sixths: RBTreeSet<usize> = (0..KEY_COUNT).step_by(6).collect();
+    assert!(sixths.is_subset(&evens));
+    assert!(thirds.is_superset(&sixths));
+    assert!(evens.is_subset(&evens));
+    assert!(sixths.remove(&0));
+    assert!(!sixths.remove(&0));
+    assert!(sixths.insert(1));
+    assert!(!sixths.insert(1));
+    assert!(!sixths.is_subset(&evens));
+    let odds: RBTreeSet<usize> = (1..KEY_COUNT).step_by(2).collect();
+    assert!(odds.is_disjoint(&evens));
+    assert!(!odds.is_disjoint(&sixths));
+
+    let empty = RBTreeSet::new();
+    assert!(empty.is_empty());
+    assert!(empty.is_subset(&odds));
+    assert!(empty.is_disjoint(&odds));
+    assert_eq!(empty.union(&odds), odds.to_vec());
+    assert_eq!(odds.first(), Some(1));
+    assert_eq!(odds.pop_last(), Some(KEY_COUNT - 1));
+    assert_eq!(odds.range(..10), [1, 3, 5, 7, 9]);
+}
+
+#[test]
+fn set_algebra_concurrent() {
+    const ITER_COUNT: usize = 10_000;
+    const KEY_COUNT: usize = 64;
+    // keys only move from `left` to `right` in single transactions, so they are always in exactly
+    // one of the sets
+    let left: RBTreeSet<usize, Reverse> = (0..KEY_COUNT).collect();
+    let right = RBTreeSet::with_comparator();
+    thread::scope(|scope| {
+        let (left, right) = (&left, &right);
+        scope.spawn(move |_| {
+            for elem in 0..ITER_COUNT {
+                let key = elem * 7 % KEY_COUNT;
+                let (from, to) = if (elem / KEY_COUNT) & 1 == 0 {
+                    (left, right)
+                } else {
+                    (right, left)
+                };
+                swym::thread_key::get().rw(|tx| {
+                    if from.map.raw.remove(tx, &key)?.is_some() {
+                        to.map.raw.set(tx, key, ())?;
+                    }
+                    Ok(())
+                });
+            }
+        });
+        scope.spawn(move |_| {
+            for _ in 0..ITER_COUNT / 10 {
+                assert!(left.is_disjoint(right));
+                assert_eq!(left.union(right), (0..KEY_COUNT).rev().collect::<Vec<_>>());
+            }
+        });
+    })
+    .unwrap();
+}
@@ -0,0 +1,357 @@
+// based off of https://en.wikipedia.org/wiki/Skip_list
+//
+// Inserts and removes only write the links immediately before (and, for removes, inside) the
+// affected node, so unrelated structural changes do not conflict.
+
+use std::{
+    cell::Cell,
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    ops::{Bound, RangeBounds},
+    ptr,
+};
+use swym::{
+    tptr::TPtr,
+    tx::{Error, Ordering, Read, Rw},
+};
+
+/// Maximum number of levels in the list.
+pub const MAX_HEIGHT: usize = 16;
+
+pub struct Node<K, V> {
+    next:      Box<[TPtr<Node<K, V>>]>,
+    pub key:   K,
+    pub value: V,
+}
+
+impl<K, V> Node<K, V> {
+    #[inline]
+    fn height(&self) -> usize {
+        self.next.len()
+    }
+}
+
+// Links preceding a key on every level, from the bottom up.
+pub type Preds<'a, K, V> = [&'a TPtr<Node<K, V>>; MAX_HEIGHT];
+
+pub struct VacantLocation<'a, K, V> {
+    preds: Preds<'a, K, V>,
+}
+
+pub enum Location<'a, K, V> {
+    Vacant(VacantLocation<'a, K, V>),
+    Occupied {
+        preds: Preds<'a, K, V>,
+        node:  &'a Node<K, V>,
+    },
+}
+
+pub struct SkipRoot<K, V> {
+    heads: [TPtr<Node<K, V>>; MAX_HEIGHT],
+}
+
+impl<K, V> SkipRoot<K, V> {
+    pub const fn new() -> Self {
+        SkipRoot {
+            heads: [
+                TPtr::null(),
+                TPtr::null(),
+                TPtr::null(),
+                TPtr::null(),
+                TPtr::null(),
+                TPtr::null(),
+                TPtr::null(),
+                TPtr::null(),
+                TPtr::null(),
+                TPtr::null(),
+                TPtr::null(),
+                TPtr::null(),
+                TPtr::null(),
+                TPtr::null(),
+                TPtr::null(),
+                TPtr::null(),
+            ],
+        }
+    }
+}
+
+impl<K, V> Drop for SkipRoot<K, V> {
+    fn drop(&mut self) {
+        let mut node = *self.heads[0].borrow_mut() as *mut Node<K, V>;
+        while !node.is_null() {
+            let mut owned = unsafe { Box::from_raw(node) };
+            node = *owned.next[0].borrow_mut() as *mut Node<K, V>;
+        }
+    }
+}
+
+thread_local! {
+    static RNG: Cell<u64> = Cell::new(RandomState::new().build_hasher().finish() | 1);
+}
+
+// geometric distribution with p = 1/4 (xorshift64*)
+fn random_height() -> usize {
+    let random = RNG.with(|rng| {
+        let mut x = rng.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        rng.set(x);
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    });
+    (random.trailing_zeros() as usize / 2 + 1).min(MAX_HEIGHT)
+}
+
+impl<K: Send + Sync + Ord + 'static, V: Send + Sync + 'static> SkipRoot<K, V> {
+    #[inline]
+    fn next<'tcell>(
+        link: &'tcell TPtr<Node<K, V>>,
+        tx: &impl Read<'tcell>,
+        ordering: Ordering,
+    ) -> Result<Option<&'tcell Node<K, V>>, Error> {
+        Ok(unsafe { link.as_ptr(tx, ordering)?.as_ref() })
+    }
+
+    // The last link on each level whose target is not less than `key`.
+    fn preds<'tcell, Q>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+        key: &Q,
+        ordering: Ordering,
+    ) -> Result<(Preds<'tcell, K, V>, Option<&'tcell Node<K, V>>), Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut preds = [&self.heads[0]; MAX_HEIGHT];
+        let mut links: &'tcell [TPtr<Node<K, V>>] = &self.heads;
+        let mut found = None;
+        for level in (0..MAX_HEIGHT).rev() {
+            loop {
+                match Self::next(&links[level], tx, ordering)? {
+                    Some(next) if next.key.borrow() < key => links = &next.next,
+                    next => {
+                        found = next;
+                        break;
+                    }
+                }
+            }
+            preds[level] = &links[level];
+        }
+        Ok((preds, found.filter(|node| node.key.borrow() == key)))
+    }
+
+    /// Finds the location of `key`.
+    ///
+    /// Inserts and removes should use `Ordering::Read`. The links they read are only validated
+    /// if they're written, keeping conflicts local. The bottom level link to an occupied node is
+    /// always validated, because replacing the value of a node does not write any links, and
+    /// would otherwise not conflict with a concurrent remove of the node.
+    pub fn location<'tcell, Q>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+        key: &Q,
+        ordering: Ordering,
+    ) -> Result<Location<'tcell, K, V>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (preds, found) = self.preds(tx, key, ordering)?;
+        Ok(match found {
+            Some(node) => {
+                let link = Self::next(preds[0], tx, Ordering::default())?;
+                debug_assert!(link.map(|link| ptr::eq(link, node)).unwrap_or(false));
+                Location::Occupied { preds, node }
+            }
+            None => Location::Vacant(VacantLocation { preds }),
+        })
+    }
+
+    pub fn find<'tcell, Q>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+        key: &Q,
+    ) -> Result<Option<&'tcell Node<K, V>>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        // only the bottom level link to the node needs to be validated
+        let mut links: &'tcell [TPtr<Node<K, V>>] = &self.heads;
+        for level in (1..MAX_HEIGHT).rev() {
+            while let Some(next) = Self::next(&links[level], tx, Ordering::Read)? {
+                if next.key.borrow() < key {
+                    links = &next.next
+                } else {
+                    break;
+                }
+            }
+        }
+        while let Some(next) = Self::next(&links[0], tx, Ordering::default())? {
+            if next.key.borrow() < key {
+                links = &next.next
+            } else if next.key.borrow() == key {
+                return Ok(Some(next));
+            } else {
+                break;
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn contains_key<'tcell, Q>(
+        &'tcell self,
+        tx: &impl Read<'tcell>,
+        key: &Q,
+    ) -> Result<bool, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        Ok(self.find(tx, key)?.is_some())
+    }
+
+    pub fn insert<'tx, 'tcell>(
+        &'tcell self,
+        tx: &'tx mut impl Rw<'tcell>,
+        key: K,
+        value: V,
+        location: VacantLocation<'tcell, K, V>,
+    ) -> Result<&'tx mut V, Error> {
+        let preds = location.preds;
+        let height = random_height();
+        let mut next = Vec::with_capacity(height);
+        for pred in &preds[..height] {
+            next.push(TPtr::new(pred.as_ptr(tx, Ordering::Read)?));
+        }
+        let mut n = Box::new(Node {
+            next: next.into_boxed_slice(),
+            key,
+            value,
+        });
+        let n_ptr = &mut *n as *mut Node<K, V>;
+        for pred in &preds[1..height] {
+            pred.set(tx, n_ptr)?;
+        }
+        // the bottom level owns the node
+        preds[0].publish_box(tx, n)?;
+        Ok(&mut unsafe { &mut *n_ptr }.value)
+    }
+
+    pub fn remove<'tcell>(
+        &'tcell self,
+        tx: &mut impl Rw<'tcell>,
+        preds: Preds<'tcell, K, V>,
+        node: &'tcell Node<K, V>,
+    ) -> Result<&'tcell V, Error> {
+        for (pred, link) in preds.iter().zip(node.next.iter()) {
+            let next = link.as_ptr(tx, Ordering::Read)?;
+            pred.set(tx, next)?;
+            // Writing the removed node's own links makes concurrent inserts after this node
+            // conflict.
+            link.set(tx, next)?;
+        }
+        unsafe { TPtr::privatize_as_box(tx, node) };
+        Ok(&node.value)
+    }
+
+    pub fn range<'tx, 'tcell, Q, R, Tx>(
+        &'tcell self,
+        tx: &'tx Tx,
+        range: R,
+    ) -> Result<Range<'tx, 'tcell, K, V, Q, R, Tx>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+        Tx: Read<'tcell>,
+    {
+        let first = match range.start_bound() {
+            Bound::Unbounded => Self::next(&self.heads[0], tx, Ordering::default())?,
+            Bound::Included(start) => {
+                let (preds, _) = self.preds(tx, start, Ordering::Read)?;
+                Self::next(preds[0], tx, Ordering::default())?
+            }
+            Bound::Excluded(start) => {
+                let (preds, found) = self.preds(tx, start, Ordering::Read)?;
+                match found {
+                    Some(found) => Self::next(&found.next[0], tx, Ordering::default())?,
+                    None => Self::next(preds[0], tx, Ordering::default())?,
+                }
+            }
+        };
+        Ok(Range {
+            tx,
+            next: first,
+            range,
+            phantom: std::marker::PhantomData,
+        })
+    }
+
+    // checks all the skip list properties (for debugging)
+    pub fn verify<'tcell>(&'tcell self, tx: &impl Read<'tcell>) -> Result<(), Error> {
+        let mut counts = [0; MAX_HEIGHT];
+        let mut prev: Option<&K> = None;
+        let mut node = Self::next(&self.heads[0], tx, Ordering::default())?;
+        while let Some(n) = node {
+            assert!(n.height() >= 1 && n.height() <= MAX_HEIGHT);
+            assert!(prev.map(|prev| *prev < n.key).unwrap_or(true));
+            for count in &mut counts[..n.height()] {
+                *count += 1;
+            }
+            prev = Some(&n.key);
+            node = Self::next(&n.next[0], tx, Ordering::default())?;
+        }
+        for level in 1..MAX_HEIGHT {
+            let mut count = 0;
+            let mut prev: Option<&K> = None;
+            let mut node = Self::next(&self.heads[level], tx, Ordering::default())?;
+            while let Some(n) = node {
+                assert!(n.height() > level);
+                assert!(prev.map(|prev| *prev < n.key).unwrap_or(true));
+                count += 1;
+                prev = Some(&n.key);
+                node = Self::next(&n.next[level], tx, Ordering::default())?;
+            }
+            assert_eq!(count, counts[level]);
+        }
+        Ok(())
+    }
+}
+
+/// An iterator over a range of entries of a skip list, in ascending order.
+pub struct Range<'tx, 'tcell, K, V, Q: ?Sized, R, Tx> {
+    tx:      &'tx Tx,
+    next:    Option<&'tcell Node<K, V>>,
+    range:   R,
+    phantom: std::marker::PhantomData<fn(&Q)>,
+}
+
+impl<'tx, 'tcell, K, V, Q, R, Tx> Iterator for Range<'tx, 'tcell, K, V, Q, R, Tx>
+where
+    K: Send + Sync + Ord + std::borrow::Borrow<Q> + 'static,
+    V: Send + Sync + 'static,
+    Q: Ord + ?Sized,
+    R: RangeBounds<Q>,
+    Tx: Read<'tcell>,
+{
+    type Item = Result<&'tcell Node<K, V>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.next.take()?;
+        let in_range = match self.range.end_bound() {
+            Bound::Unbounded => true,
+            Bound::Included(end) => node.key.borrow() <= end,
+            Bound::Excluded(end) => node.key.borrow() < end,
+        };
+        if !in_range {
+            return None;
+        }
+        match SkipRoot::next(&node.next[0], self.tx, Ordering::default()) {
+            Ok(next) => self.next = next,
+            Err(error) => return Some(Err(error)),
+        }
+        Some(Ok(node))
+    }
+}
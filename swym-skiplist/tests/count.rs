@@ -0,0 +1,235 @@
+use crossbeam_utils::thread;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering::Relaxed},
+    mpsc,
+};
+use swym_skiplist::TSkipList;
+
+static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Count(usize);
+impl Clone for Count {
+    fn clone(&self) -> Self {
+        Count::new(self.0)
+    }
+}
+impl Count {
+    pub fn new(v: usize) -> Self {
+        COUNT.fetch_add(1, Relaxed);
+        Count(v)
+    }
+}
+
+impl Drop for Count {
+    fn drop(&mut self) {
+        COUNT.fetch_sub(1, Relaxed);
+    }
+}
+
+#[test]
+fn count() {
+    const ITER_COUNT: usize = 100_000;
+    thread::scope(|scope| {
+        scope.spawn(move |_| {
+            let list = TSkipList::new();
+            for elem in 0..ITER_COUNT {
+                list.atomic(|mut list| {
+                    list.entry(elem)?.or_insert(Count::new(0))?;
+                    Ok(())
+                })
+            }
+            swym::thread_key::get().read(|tx| {
+                list.raw.verify(tx)?;
+                Ok(())
+            });
+        });
+    })
+    .unwrap();
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
+
+#[test]
+fn count_rev() {
+    const ITER_COUNT: usize = 100_000;
+    thread::scope(|scope| {
+        scope.spawn(move |_| {
+            let list = TSkipList::new();
+            for elem in (0..ITER_COUNT).rev() {
+                list.atomic(|mut list| {
+                    list.entry(elem)?.or_insert(Count::new(0))?;
+                    Ok(())
+                })
+            }
+            swym::thread_key::get().read(|tx| {
+                list.raw.verify(tx)?;
+                Ok(())
+            });
+        });
+    })
+    .unwrap();
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
+
+#[test]
+fn count_remove() {
+    const ITER_COUNT: usize = 100_000;
+    thread::scope(|scope| {
+        scope.spawn(move |_| {
+            let list = TSkipList::new();
+            for elem in 0..ITER_COUNT {
+                list.atomic(|mut list| {
+                    list.insert(elem, Count::new(0))?;
+                    Ok(())
+                })
+            }
+            for elem in 0..ITER_COUNT {
+                list.remove(&elem).unwrap();
+            }
+            swym::thread_key::get().read(|tx| {
+                list.raw.verify(tx)?;
+                Ok(())
+            });
+            std::mem::forget(list);
+        });
+    })
+    .unwrap();
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
+
+#[test]
+fn count_remove_rev() {
+    const ITER_COUNT: usize = 100_000;
+    thread::scope(|scope| {
+        scope.spawn(move |_| {
+            let list = TSkipList::new();
+            for elem in (0..ITER_COUNT).rev() {
+                list.atomic(|mut list| {
+                    list.insert(elem, Count::new(0))?;
+                    Ok(())
+                })
+            }
+            for elem in (0..ITER_COUNT).rev() {
+                list.remove(&elem);
+            }
+            swym::thread_key::get().read(|tx| {
+                list.raw.verify(tx)?;
+                Ok(())
+            });
+            std::mem::forget(list);
+        });
+    })
+    .unwrap();
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
+
+#[test]
+fn range() {
+    const ITER_COUNT: usize = 10_000;
+    thread::scope(|scope| {
+        scope.spawn(move |_| {
+            let list = TSkipList::new();
+            for elem in (0..ITER_COUNT).rev() {
+                list.insert(elem, Count::new(elem));
+            }
+            let all = list.range(..);
+            assert_eq!(all.len(), ITER_COUNT);
+            assert!(all
+                .iter()
+                .enumerate()
+                .all(|(i, (k, v))| i == *k && i == v.0));
+            drop(all);
+            let keys: Vec<_> = list.range(10..20).into_iter().map(|(k, _)| k).collect();
+            assert_eq!(keys, (10..20).collect::<Vec<_>>());
+            let keys: Vec<_> = list
+                .range(9990..=20000)
+                .into_iter()
+                .map(|(k, _)| k)
+                .collect();
+            assert_eq!(keys, (9990..10000).collect::<Vec<_>>());
+            let keys: Vec<_> = list.range(..3).into_iter().map(|(k, _)| k).collect();
+            assert_eq!(keys, [0, 1, 2]);
+            assert!(list.range(20000..).is_empty());
+            swym::thread_key::get().read(|tx| {
+                list.raw.verify(tx)?;
+                Ok(())
+            });
+        });
+    })
+    .unwrap();
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
+
+#[test]
+fn concurrent() {
+    const ITER_COUNT: usize = 10_000;
+    const THREAD_COUNT: usize = 4;
+    let list = TSkipList::new();
+    thread::scope(|scope| {
+        for thread in 0..THREAD_COUNT {
+            let list = &list;
+            scope.spawn(move |_| {
+                for elem in 0..ITER_COUNT {
+                    let key = elem * THREAD_COUNT + thread;
+                    list.insert(key, Count::new(key));
+                    if elem % 2 == 1 {
+                        let removed = list.remove(&(key - THREAD_COUNT));
+                        assert_eq!(removed.map(|count| count.0), Some(key - THREAD_COUNT));
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+    let all = list.range(..);
+    assert_eq!(all.len(), ITER_COUNT * THREAD_COUNT / 2);
+    drop(all);
+    swym::thread_key::get().read(|tx| {
+        list.raw.verify(tx)?;
+        Ok(())
+    });
+    drop(list);
+    assert_eq!(COUNT.load(Relaxed), 0);
+    swym::stats::print_stats();
+}
+
+#[test]
+fn replace_remove() {
+    let list = TSkipList::new();
+    list.insert(0, Count::new(0));
+    let (start, start_recv) = mpsc::channel();
+    let (done, done_recv) = mpsc::channel();
+    let mut attempts = 0;
+    let (replaced, removed) = thread::scope(|scope| {
+        let list = &list;
+        let remover = scope.spawn(move |_| {
+            start_recv.recv().unwrap();
+            let removed = list.remove(&0).map(|count| count.0);
+            done.send(()).unwrap();
+            removed
+        });
+        // the key is removed after the replacing transaction found it, but before it commits
+        let replaced = list.atomic(|mut list| {
+            let replaced = list.insert(0, Count::new(1))?.map(|count| count.0);
+            if attempts == 0 {
+                start.send(()).unwrap();
+                done_recv.recv().unwrap();
+            }
+            attempts += 1;
+            Ok(replaced)
+        });
+        (replaced, remover.join().unwrap())
+    })
+    .unwrap();
+    // the replace must be retried after the remove, instead of writing to the removed node
+    assert_eq!(removed, Some(0));
+    assert_eq!(replaced, None);
+    assert_eq!(list.get(&0).map(|count| count.0), Some(1));
+    drop(list);
+    assert_eq!(COUNT.load(Relaxed), 0);
+}
@@ -0,0 +1,169 @@
+mod blocking {
+    use crossbeam_utils::thread;
+    use std::{
+        sync::mpsc::{self, RecvTimeoutError},
+        thread::JoinHandle,
+        time::Duration,
+    };
+    use swym::{
+        tbarrier::TBarrier, thread_key, tlock::TLock, tmvar::TMVar, tqueue::TQueue,
+        tsemaphore::TSemaphore,
+    };
+
+    // if a test hasn't completed in a reasonable amount of time, a thread was never unparked, abort
+    struct Deadline {
+        done:     Option<mpsc::Sender<()>>,
+        watchdog: Option<JoinHandle<()>>,
+    }
+
+    impl Drop for Deadline {
+        fn drop(&mut self) {
+            // disconnecting the channel stops the watchdog
+            drop(self.done.take());
+            self.watchdog.take().unwrap().join().unwrap();
+        }
+    }
+
+    fn deadline() -> Deadline {
+        let (done, wait) = mpsc::channel();
+        let watchdog = std::thread::spawn(move || {
+            if wait.recv_timeout(Duration::from_secs(60)) == Err(RecvTimeoutError::Timeout) {
+                std::process::abort();
+            }
+        });
+        Deadline {
+            done:     Some(done),
+            watchdog: Some(watchdog),
+        }
+    }
+
+    #[test]
+    fn tlock() {
+        const ITER_COUNT: usize = 10_000;
+        const THREAD_COUNT: usize = 16;
+        let _deadline = deadline();
+
+        let string = TLock::new(String::new());
+        thread::scope(|s| {
+            for i in 0..THREAD_COUNT {
+                let string = &string;
+                s.spawn(move |_| {
+                    for _ in 0..ITER_COUNT {
+                        let mut guard = string.lock();
+                        *guard = format!("hello there from thread {}", i);
+                    }
+                });
+            }
+        })
+        .unwrap();
+        assert!(string.into_inner().starts_with("hello there from thread"));
+    }
+
+    #[test]
+    fn tsemaphore() {
+        const ITER_COUNT: usize = 1_000;
+        const THREAD_COUNT: usize = 16;
+        let _deadline = deadline();
+
+        // every thread blocks until released by the thread before it
+        let semaphores: Vec<_> = (0..THREAD_COUNT).map(|_| TSemaphore::new(0)).collect();
+        let semaphores = &semaphores;
+        thread::scope(|s| {
+            for i in 0..THREAD_COUNT {
+                s.spawn(move |_| {
+                    let thread_key = thread_key::get();
+                    for _ in 0..ITER_COUNT {
+                        thread_key.rw(|tx| Ok(semaphores[i].acquire(tx, 1)?));
+                        thread_key.rw(|tx| Ok(semaphores[(i + 1) % THREAD_COUNT].release(tx, 1)?));
+                    }
+                });
+            }
+            thread_key::get().rw(|tx| Ok(semaphores[0].release(tx, 1)?));
+        })
+        .unwrap();
+        let thread_key = thread_key::get();
+        let total = thread_key.read(|tx| {
+            let mut total = 0;
+            for semaphore in semaphores {
+                total += semaphore.available(tx)?;
+            }
+            Ok(total)
+        });
+        assert_eq!(total, 1);
+    }
+
+    #[test]
+    fn tbarrier() {
+        const ITER_COUNT: usize = 1_000;
+        const THREAD_COUNT: usize = 16;
+        let _deadline = deadline();
+
+        let barrier = TBarrier::new(THREAD_COUNT);
+        thread::scope(|s| {
+            for _ in 0..THREAD_COUNT {
+                s.spawn(|_| {
+                    for _ in 0..ITER_COUNT {
+                        barrier.wait();
+                    }
+                });
+            }
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn tmvar() {
+        const ITER_COUNT: usize = 1_000;
+        const THREAD_COUNT: usize = 16;
+        let _deadline = deadline();
+
+        // a token passed around a ring of threads
+        let ring: Vec<_> = (0..THREAD_COUNT).map(|_| TMVar::new_empty()).collect();
+        let mvars = &ring;
+        thread::scope(|s| {
+            for i in 0..THREAD_COUNT {
+                s.spawn(move |_| {
+                    let thread_key = thread_key::get();
+                    for _ in 0..ITER_COUNT {
+                        thread_key.rw(|tx| {
+                            let token = *mvars[i].take(tx)?;
+                            mvars[(i + 1) % THREAD_COUNT].put(tx, token + 1)?;
+                            Ok(())
+                        });
+                    }
+                });
+            }
+            thread_key::get().rw(|tx| Ok(mvars[0].put(tx, 0)?));
+        })
+        .unwrap();
+        let tokens: Vec<_> = ring.into_iter().filter_map(TMVar::into_inner).collect();
+        assert_eq!(tokens, [ITER_COUNT * THREAD_COUNT]);
+    }
+
+    #[test]
+    fn tqueue() {
+        const ITER_COUNT: usize = 10_000;
+        const THREAD_COUNT: usize = 8;
+        let _deadline = deadline();
+
+        let queue = TQueue::bounded(1);
+        thread::scope(|s| {
+            for _ in 0..THREAD_COUNT {
+                s.spawn(|_| {
+                    let thread_key = thread_key::get();
+                    for i in 0..ITER_COUNT {
+                        thread_key.rw(|tx| Ok(queue.send(tx, i)?));
+                    }
+                });
+                s.spawn(|_| {
+                    let thread_key = thread_key::get();
+                    for _ in 0..ITER_COUNT {
+                        thread_key.rw(|tx| Ok(*queue.recv(tx)?));
+                    }
+                });
+            }
+        })
+        .unwrap();
+        assert!(thread_key::get().read(|tx| Ok(queue.is_empty(tx)?)));
+    }
+}
@@ -70,4 +70,125 @@ mod memory {
         }
         assert_eq!(ALLOC_COUNT.load(Relaxed), 0);
     }
+
+    #[test]
+    fn tstack() {
+        const ITER_COUNT: usize = 100_000;
+        static ALLOC_COUNT: AtomicIsize = AtomicIsize::new(0);
+
+        struct Count(usize);
+        impl Count {
+            fn new(x: usize) -> Self {
+                ALLOC_COUNT.fetch_add(1, Relaxed);
+                Count(x)
+            }
+        }
+        impl Drop for Count {
+            fn drop(&mut self) {
+                ALLOC_COUNT.fetch_sub(1, Relaxed);
+            }
+        }
+
+        use swym::tstack::TStack;
+        let stack = TStack::<Count>::new();
+        thread::scope(|s| {
+            s.spawn(|_| {
+                let thread_key = thread_key::get();
+                let mut total = 0;
+                for _ in 0..ITER_COUNT {
+                    total += thread_key.rw(|tx| Ok(stack.pop(tx)?.0));
+                }
+                assert_eq!(total, (ITER_COUNT - 1) * ITER_COUNT / 2);
+            });
+            s.spawn(|_| {
+                let thread_key = thread_key::get();
+                for x in 0..ITER_COUNT {
+                    thread_key.rw(|tx| Ok(stack.push(tx, Count::new(x))?));
+                }
+            });
+        })
+        .unwrap();
+        let elems = thread_key::get().read(|tx| {
+            Ok(stack
+                .iter(tx)
+                .map(|ok| ok.map(|ok| ok.0))
+                .collect::<Result<Vec<_>, _>>()?)
+        });
+        assert!(elems.is_empty());
+        thread_key::get().rw(|tx| {
+            for x in 0..10 {
+                stack.push(tx, Count::new(x))?;
+            }
+            Ok(())
+        });
+        drop(stack);
+        assert_eq!(ALLOC_COUNT.load(Relaxed), 0);
+    }
+
+    #[test]
+    fn tdeque() {
+        const ITER_COUNT: usize = 100_000;
+        static ALLOC_COUNT: AtomicIsize = AtomicIsize::new(0);
+
+        struct Count(usize);
+        impl Count {
+            fn new(x: usize) -> Self {
+                ALLOC_COUNT.fetch_add(1, Relaxed);
+                Count(x)
+            }
+        }
+        impl Drop for Count {
+            fn drop(&mut self) {
+                ALLOC_COUNT.fetch_sub(1, Relaxed);
+            }
+        }
+
+        use swym::tdeque::TDeque;
+        let deque = TDeque::<Count>::new();
+        thread::scope(|s| {
+            s.spawn(|_| {
+                let thread_key = thread_key::get();
+                let mut total = 0;
+                for x in 0..ITER_COUNT {
+                    total += thread_key.rw(|tx| {
+                        Ok(if x % 2 == 0 {
+                            deque.pop_front(tx)?.0
+                        } else {
+                            deque.pop_back(tx)?.0
+                        })
+                    });
+                }
+                assert_eq!(total, (ITER_COUNT - 1) * ITER_COUNT / 2);
+            });
+            s.spawn(|_| {
+                let thread_key = thread_key::get();
+                for x in 0..ITER_COUNT {
+                    thread_key.rw(|tx| {
+                        if x % 3 == 0 {
+                            deque.push_front(tx, Count::new(x))?;
+                        } else {
+                            deque.push_back(tx, Count::new(x))?;
+                        }
+                        Ok(())
+                    });
+                }
+            });
+        })
+        .unwrap();
+        let elems = thread_key::get().read(|tx| {
+            Ok(deque
+                .iter(tx)
+                .map(|ok| ok.map(|ok| ok.0))
+                .collect::<Result<Vec<_>, _>>()?)
+        });
+        assert!(elems.is_empty());
+        thread_key::get().rw(|tx| {
+            for x in 0..10 {
+                deque.push_back(tx, Count::new(x))?;
+            }
+            Ok(())
+        });
+        drop(deque);
+        assert_eq!(ALLOC_COUNT.load(Relaxed), 0);
+    }
 }